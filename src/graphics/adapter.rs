@@ -0,0 +1,105 @@
+use std::fmt;
+
+use oxidx::dx::{self, IAdapter3, IFactory4};
+
+use super::{
+    adapter_policy::{select_adapter, select_adapter_pair, AdapterInfo, AdapterPolicy},
+    capabilities::DeviceCapabilities,
+    device::Device,
+};
+
+#[derive(Debug)]
+pub struct AdapterList {
+    factory: dx::Factory4,
+    raw: Vec<dx::Adapter3>,
+    infos: Vec<AdapterInfo>,
+}
+
+impl AdapterList {
+    pub fn enumerate(factory: dx::Factory4) -> Self {
+        let mut raw = vec![];
+        let mut infos = vec![];
+
+        for index in 0.. {
+            let Ok(adapter) = factory.enum_adapters::<dx::Adapter3>(index) else {
+                break;
+            };
+
+            let desc = match adapter.get_desc1() {
+                Ok(desc) => desc,
+                Err(err) => {
+                    tracing::warn!(
+                        "Skipping adapter {}: failed to query desc: {:?}",
+                        index,
+                        err
+                    );
+                    continue;
+                }
+            };
+            let software = desc.flags().contains(dx::AdapterFlags::Software);
+
+            let device =
+                dx::create_device::<_, dx::Device>(Some(&adapter), dx::FeatureLevel::Level11);
+            let device = match device {
+                Ok(device) => device,
+                Err(err) => {
+                    tracing::warn!(
+                        "Skipping adapter {} ({}): failed to create device: {:?}",
+                        index,
+                        desc.description(),
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            infos.push(AdapterInfo {
+                index: raw.len(),
+                software,
+                capabilities: Device::query_capabilities(&desc, &device),
+            });
+            raw.push(adapter);
+        }
+
+        Self {
+            factory,
+            raw,
+            infos,
+        }
+    }
+
+    pub fn adapters(&self) -> &[AdapterInfo] {
+        &self.infos
+    }
+
+    pub fn create_device(&self, policies: &[AdapterPolicy]) -> Option<Device> {
+        let info = select_adapter(&self.infos, policies)?;
+
+        Some(self.device_for(info))
+    }
+
+    pub fn create_device_pair(&self) -> Option<(Device, Device)> {
+        let pair = select_adapter_pair(&self.infos)?;
+
+        Some((
+            self.device_for(pair.primary),
+            self.device_for(pair.secondary),
+        ))
+    }
+
+    fn device_for(&self, info: &AdapterInfo) -> Device {
+        Device::new(self.factory.clone(), self.raw[info.index].clone())
+    }
+}
+
+impl fmt::Display for AdapterList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let capabilities = self
+            .infos
+            .iter()
+            .map(|a| a.capabilities.clone())
+            .collect::<Vec<_>>();
+
+        DeviceCapabilities::table(&capabilities).fmt(f)
+    }
+}
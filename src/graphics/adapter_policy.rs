@@ -0,0 +1,244 @@
+use std::cmp::Reverse;
+
+use super::capabilities::{AdapterLuid, DeviceCapabilities};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdapterInfo {
+    pub index: usize,
+    pub software: bool,
+    pub capabilities: DeviceCapabilities,
+}
+
+impl AdapterInfo {
+    pub fn name(&self) -> &str {
+        &self.capabilities.adapter
+    }
+
+    pub fn luid(&self) -> AdapterLuid {
+        self.capabilities.luid
+    }
+
+    pub fn is_discrete(&self) -> bool {
+        !self.software && !self.capabilities.uma
+    }
+
+    pub fn is_integrated(&self) -> bool {
+        !self.software && self.capabilities.uma
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AdapterPolicy {
+    HighPerformance,
+    LowPower,
+    Software,
+    ByName(String),
+    ByLuid(AdapterLuid),
+}
+
+impl AdapterPolicy {
+    pub fn select<'a>(&self, adapters: &'a [AdapterInfo]) -> Option<&'a AdapterInfo> {
+        self.select_from(adapters.iter())
+    }
+
+    fn select_from<'a>(
+        &self,
+        mut adapters: impl Iterator<Item = &'a AdapterInfo>,
+    ) -> Option<&'a AdapterInfo> {
+        match self {
+            AdapterPolicy::HighPerformance => adapters.filter(|a| !a.software).min_by_key(|a| {
+                (
+                    !a.is_discrete(),
+                    Reverse(a.capabilities.dedicated_video_memory),
+                    a.index,
+                )
+            }),
+            AdapterPolicy::LowPower => adapters.filter(|a| !a.software).min_by_key(|a| {
+                (
+                    !a.is_integrated(),
+                    a.capabilities.dedicated_video_memory,
+                    a.index,
+                )
+            }),
+            AdapterPolicy::Software => adapters.find(|a| a.software),
+            AdapterPolicy::ByName(name) => {
+                let name = name.to_lowercase();
+                adapters.find(|a| a.name().to_lowercase().contains(&name))
+            }
+            AdapterPolicy::ByLuid(luid) => adapters.find(|a| a.luid() == *luid),
+        }
+    }
+}
+
+pub fn select_adapter<'a>(
+    adapters: &'a [AdapterInfo],
+    policies: &[AdapterPolicy],
+) -> Option<&'a AdapterInfo> {
+    policies.iter().find_map(|policy| policy.select(adapters))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdapterPair<'a> {
+    pub primary: &'a AdapterInfo,
+    pub secondary: &'a AdapterInfo,
+}
+
+pub fn select_adapter_pair(adapters: &[AdapterInfo]) -> Option<AdapterPair<'_>> {
+    let primary = AdapterPolicy::HighPerformance.select(adapters)?;
+
+    let secondary = AdapterPolicy::LowPower
+        .select_from(adapters.iter().filter(|a| a.luid() != primary.luid()))?;
+
+    Some(AdapterPair { primary, secondary })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::capabilities::{
+        AdapterLuid, CrossNodeSharingTier, DeviceCapabilities, FeatureLevel, ShaderModel,
+    };
+
+    use super::{select_adapter, select_adapter_pair, AdapterInfo, AdapterPolicy};
+
+    const GB: usize = 1024 * 1024 * 1024;
+
+    fn adapter(index: usize, name: &str, uma: bool, memory: usize, software: bool) -> AdapterInfo {
+        AdapterInfo {
+            index,
+            software,
+            capabilities: DeviceCapabilities {
+                adapter: name.to_string(),
+                luid: AdapterLuid {
+                    low: index as u32 + 1,
+                    high: 0,
+                },
+                feature_level: FeatureLevel::new(12, 1),
+                shader_model: ShaderModel::new(6, 6),
+                root_signature_1_1: true,
+                resource_binding_tier: 3,
+                resource_heap_tier: 2,
+                cross_node_sharing_tier: CrossNodeSharingTier::NotSupported,
+                cross_adapter_row_major_texture: true,
+                copy_queue_timestamps: true,
+                uma,
+                cache_coherent_uma: uma,
+                dedicated_video_memory: memory,
+            },
+        }
+    }
+
+    fn laptop() -> Vec<AdapterInfo> {
+        vec![
+            adapter(0, "Intel(R) UHD Graphics", true, 128 * 1024 * 1024, false),
+            adapter(1, "NVIDIA GeForce RTX 4060", false, 8 * GB, false),
+            adapter(2, "Microsoft Basic Render Driver", true, 0, true),
+        ]
+    }
+
+    #[test]
+    fn test_high_performance_prefers_discrete() {
+        let adapters = laptop();
+
+        assert_eq!(
+            AdapterPolicy::HighPerformance
+                .select(&adapters)
+                .unwrap()
+                .index,
+            1
+        );
+    }
+
+    #[test]
+    fn test_high_performance_picks_largest_discrete() {
+        let adapters = vec![
+            adapter(0, "Small", false, 4 * GB, false),
+            adapter(1, "Large", false, 16 * GB, false),
+        ];
+
+        assert_eq!(
+            AdapterPolicy::HighPerformance
+                .select(&adapters)
+                .unwrap()
+                .name(),
+            "Large"
+        );
+    }
+
+    #[test]
+    fn test_low_power_prefers_integrated() {
+        let adapters = laptop();
+
+        assert_eq!(AdapterPolicy::LowPower.select(&adapters).unwrap().index, 0);
+    }
+
+    #[test]
+    fn test_software_fallback() {
+        let adapters = vec![adapter(0, "Microsoft Basic Render Driver", true, 0, true)];
+
+        assert_eq!(AdapterPolicy::HighPerformance.select(&adapters), None);
+        assert_eq!(
+            select_adapter(
+                &adapters,
+                &[AdapterPolicy::HighPerformance, AdapterPolicy::Software]
+            )
+            .unwrap()
+            .index,
+            0
+        );
+    }
+
+    #[test]
+    fn test_select_by_name_and_luid() {
+        let adapters = laptop();
+
+        assert_eq!(
+            AdapterPolicy::ByName("geforce".to_string())
+                .select(&adapters)
+                .unwrap()
+                .index,
+            1
+        );
+        assert_eq!(
+            AdapterPolicy::ByLuid(AdapterLuid { low: 1, high: 0 })
+                .select(&adapters)
+                .unwrap()
+                .index,
+            0
+        );
+        assert_eq!(
+            AdapterPolicy::ByName("radeon".to_string()).select(&adapters),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pair_is_discrete_and_integrated() {
+        let adapters = laptop();
+        let pair = select_adapter_pair(&adapters).unwrap();
+
+        assert_eq!(pair.primary.index, 1);
+        assert_eq!(pair.secondary.index, 0);
+    }
+
+    #[test]
+    fn test_pair_of_two_discrete_adapters() {
+        let adapters = vec![
+            adapter(0, "Secondary", false, 4 * GB, false),
+            adapter(1, "Primary", false, 12 * GB, false),
+        ];
+        let pair = select_adapter_pair(&adapters).unwrap();
+
+        assert_eq!(pair.primary.name(), "Primary");
+        assert_eq!(pair.secondary.name(), "Secondary");
+    }
+
+    #[test]
+    fn test_pair_requires_two_hardware_adapters() {
+        let adapters = vec![
+            adapter(0, "Only", false, 8 * GB, false),
+            adapter(1, "Microsoft Basic Render Driver", true, 0, true),
+        ];
+
+        assert_eq!(select_adapter_pair(&adapters), None);
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::graphics::{scan_dependencies, ShaderOptions};
+
+const CACHE_MAGIC: [u8; 4] = *b"MGPC";
+const CACHE_VERSION: u32 = 1;
+const HEADER_SIZE: usize = 4 + 4 + 16 + 8 + 8;
+
+const FNV_OFFSET_128: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME_128: u128 = 0x0000000001000000000000000000013b;
+const FNV_OFFSET_64: u64 = 0xcbf29ce484222325;
+const FNV_PRIME_64: u64 = 0x00000100000001b3;
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME_64)
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CacheKey(u128);
+
+impl CacheKey {
+    pub fn shader(
+        path: impl AsRef<Path>,
+        entry_point: &str,
+        target: &str,
+        options: &ShaderOptions,
+    ) -> io::Result<Self> {
+        let mut hasher = CacheKeyHasher::new("shader")
+            .with_str(entry_point)
+            .with_str(target)
+            .with_u64(options.config as u64)
+            .with_str(&options.compiler_identity())
+            .with_u64(options.defines.len() as u64);
+
+        for (name, value) in &options.defines {
+            hasher = hasher.with_str(name).with_str(value);
+        }
+
+        for file in scan_dependencies(path.as_ref(), &options.include_dirs) {
+            hasher = hasher.with_bytes(&std::fs::read(file)?);
+        }
+
+        Ok(hasher.finish())
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CacheKeyHasher {
+    state: u128,
+}
+
+impl CacheKeyHasher {
+    pub fn new(domain: &str) -> Self {
+        Self {
+            state: FNV_OFFSET_128,
+        }
+        .with_str(domain)
+    }
+
+    pub fn with_bytes(mut self, bytes: &[u8]) -> Self {
+        let len = (bytes.len() as u64).to_le_bytes();
+
+        for &byte in len.iter().chain(bytes) {
+            self.state = (self.state ^ byte as u128).wrapping_mul(FNV_PRIME_128);
+        }
+
+        self
+    }
+
+    pub fn with_str(self, value: &str) -> Self {
+        self.with_bytes(value.as_bytes())
+    }
+
+    pub fn with_u64(self, value: u64) -> Self {
+        self.with_bytes(&value.to_le_bytes())
+    }
+
+    pub fn with_key(self, key: CacheKey) -> Self {
+        self.with_bytes(&key.0.to_le_bytes())
+    }
+
+    pub fn finish(self) -> CacheKey {
+        CacheKey(self.state)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CacheKind {
+    Shader,
+    Pipeline,
+}
+
+impl CacheKind {
+    fn extension(self) -> &'static str {
+        match self {
+            CacheKind::Shader => "shader",
+            CacheKind::Pipeline => "pso",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub corrupted: usize,
+    pub stored: usize,
+}
+
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    corrupted: AtomicUsize,
+    stored: AtomicUsize,
+}
+
+impl DiskCache {
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            corrupted: AtomicUsize::new(0),
+            stored: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            corrupted: self.corrupted.load(Ordering::Relaxed),
+            stored: self.stored.load(Ordering::Relaxed),
+        }
+    }
+
+    fn entry_path(&self, kind: CacheKind, key: CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, kind.extension()))
+    }
+
+    pub fn contains(&self, kind: CacheKind, key: CacheKey) -> bool {
+        self.entry_path(kind, key).is_file()
+    }
+
+    pub fn load(&self, kind: CacheKind, key: CacheKey) -> Option<Vec<u8>> {
+        let path = self.entry_path(kind, key);
+
+        let Ok(data) = std::fs::read(&path) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+
+        match Self::decode(&data, key) {
+            Some(payload) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(payload.to_vec())
+            }
+            None => {
+                tracing::warn!("Discarding corrupted cache entry {}", path.display());
+
+                self.corrupted.fetch_add(1, Ordering::Relaxed);
+                let _ = std::fs::remove_file(&path);
+
+                None
+            }
+        }
+    }
+
+    pub fn store(&self, kind: CacheKind, key: CacheKey, payload: &[u8]) -> io::Result<()> {
+        let mut data = Vec::with_capacity(HEADER_SIZE + payload.len());
+        data.extend_from_slice(&CACHE_MAGIC);
+        data.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        data.extend_from_slice(&key.0.to_le_bytes());
+        data.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        data.extend_from_slice(&checksum(payload).to_le_bytes());
+        data.extend_from_slice(payload);
+
+        let temp = self.dir.join(format!(
+            "{}.{}.{}.tmp",
+            key,
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = std::fs::write(&temp, &data)
+            .and_then(|_| std::fs::rename(&temp, self.entry_path(kind, key)));
+
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        } else {
+            self.stored.fetch_add(1, Ordering::Relaxed);
+        }
+
+        result
+    }
+
+    pub fn invalidate(&self, kind: CacheKind, key: CacheKey) {
+        let _ = std::fs::remove_file(self.entry_path(kind, key));
+    }
+
+    pub fn clear(&self) -> io::Result<usize> {
+        let mut removed = 0;
+
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let is_entry = path.extension().is_some_and(|ext| {
+                ext == CacheKind::Shader.extension()
+                    || ext == CacheKind::Pipeline.extension()
+                    || ext == "tmp"
+            });
+
+            if is_entry && std::fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    fn decode(data: &[u8], key: CacheKey) -> Option<&[u8]> {
+        let (header, payload) = data.split_at_checked(HEADER_SIZE)?;
+
+        let magic = &header[0..4];
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let stored_key = u128::from_le_bytes(header[8..24].try_into().unwrap());
+        let len = u64::from_le_bytes(header[24..32].try_into().unwrap());
+        let sum = u64::from_le_bytes(header[32..40].try_into().unwrap());
+
+        let valid = magic == CACHE_MAGIC
+            && version == CACHE_VERSION
+            && stored_key == key.0
+            && len == payload.len() as u64
+            && sum == checksum(payload);
+
+        valid.then_some(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::graphics::{ShaderModel, ShaderOptions};
+
+    use super::{CacheKey, CacheKeyHasher, CacheKind, CacheStats, DiskCache};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("disk-cache-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_key_hasher_separates_fields() {
+        let a = CacheKeyHasher::new("shader").with_str("ab").with_str("c");
+        let b = CacheKeyHasher::new("shader").with_str("a").with_str("bc");
+        let c = CacheKeyHasher::new("pipeline").with_str("ab").with_str("c");
+
+        assert_ne!(a.finish(), b.finish());
+        assert_ne!(a.finish(), c.finish());
+        assert_eq!(
+            a.finish(),
+            CacheKeyHasher::new("shader")
+                .with_str("ab")
+                .with_str("c")
+                .finish()
+        );
+    }
+
+    #[test]
+    fn test_shader_key_tracks_sources_and_options() {
+        let dir = temp_dir("key");
+        let root = dir.join("shadow.hlsl");
+        let include = dir.join("pcf.hlsli");
+
+        std::fs::write(&root, "#include \"pcf.hlsli\"\n").unwrap();
+        std::fs::write(&include, "float pcf() { return 1; }\n").unwrap();
+
+        let options = ShaderOptions::new();
+        let key = CacheKey::shader(&root, "main", "ps_6_0", &options).unwrap();
+
+        assert_eq!(
+            key,
+            CacheKey::shader(&root, "main", "ps_6_0", &options).unwrap()
+        );
+        assert_ne!(
+            key,
+            CacheKey::shader(&root, "other", "ps_6_0", &options).unwrap()
+        );
+        assert_ne!(
+            key,
+            CacheKey::shader(&root, "main", "ps_6_6", &options).unwrap()
+        );
+        assert_ne!(
+            key,
+            CacheKey::shader(
+                &root,
+                "main",
+                "ps_6_0",
+                &options.clone().with_define("PCF_TAPS", "16")
+            )
+            .unwrap()
+        );
+
+        std::fs::write(&include, "float pcf() { return 0; }\n").unwrap();
+        assert_ne!(
+            key,
+            CacheKey::shader(&root, "main", "ps_6_0", &options).unwrap()
+        );
+
+        assert!(CacheKey::shader(dir.join("missing.hlsl"), "main", "ps_6_0", &options).is_err());
+    }
+
+    #[test]
+    fn test_shader_key_tracks_compiler() {
+        let dir = temp_dir("compiler");
+        let root = dir.join("blur.hlsl");
+        std::fs::write(&root, "float4 main() : SV_Target { return 0; }\n").unwrap();
+
+        let dxc = ShaderOptions::new().with_model(ShaderModel::new(6, 0));
+        let key =
+            CacheKey::shader(&root, "main", "ps_6_0", &dxc.clone().with_compiler("dxc-a")).unwrap();
+
+        assert_ne!(
+            key,
+            CacheKey::shader(&root, "main", "ps_6_0", &dxc.with_compiler("dxc-b")).unwrap()
+        );
+        assert_ne!(
+            key,
+            CacheKey::shader(&root, "main", "ps_6_0", &ShaderOptions::new()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let cache = DiskCache::open(temp_dir("store")).unwrap();
+        let key = CacheKeyHasher::new("test").with_str("blob").finish();
+
+        assert_eq!(cache.load(CacheKind::Shader, key), None);
+
+        cache
+            .store(CacheKind::Shader, key, b"DXBC bytecode")
+            .unwrap();
+
+        assert_eq!(
+            cache.load(CacheKind::Shader, key).as_deref(),
+            Some(&b"DXBC bytecode"[..])
+        );
+        assert_eq!(cache.load(CacheKind::Pipeline, key), None);
+
+        cache.invalidate(CacheKind::Shader, key);
+        assert!(!cache.contains(CacheKind::Shader, key));
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                corrupted: 0,
+                stored: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_corrupted_entries_are_discarded() {
+        let cache = DiskCache::open(temp_dir("corrupt")).unwrap();
+        let key = CacheKeyHasher::new("test").with_str("pso").finish();
+        let path = cache.dir().join(format!("{}.pso", key));
+
+        cache
+            .store(CacheKind::Pipeline, key, b"cached pso")
+            .unwrap();
+
+        let mut data = std::fs::read(&path).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(cache.load(CacheKind::Pipeline, key), None);
+        assert!(!path.exists());
+
+        cache
+            .store(CacheKind::Pipeline, key, b"cached pso")
+            .unwrap();
+        std::fs::write(&path, &std::fs::read(&path).unwrap()[..10]).unwrap();
+
+        assert_eq!(cache.load(CacheKind::Pipeline, key), None);
+        assert_eq!(cache.stats().corrupted, 2);
+
+        let other = CacheKeyHasher::new("test").with_str("other").finish();
+        cache
+            .store(CacheKind::Pipeline, other, b"other pso")
+            .unwrap();
+        std::fs::copy(cache.dir().join(format!("{}.pso", other)), &path).unwrap();
+
+        assert_eq!(cache.load(CacheKind::Pipeline, key), None);
+        assert_eq!(cache.clear().unwrap(), 1);
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FeatureLevel {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl FeatureLevel {
+    pub fn new(major: u8, minor: u8) -> Self {
+        Self { major, minor }
+    }
+
+    pub fn from_raw(raw: u32) -> Self {
+        Self {
+            major: (raw >> 12) as u8,
+            minor: ((raw >> 8) & 0xf) as u8,
+        }
+    }
+}
+
+impl fmt::Display for FeatureLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.major, self.minor)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShaderModel {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl ShaderModel {
+    pub fn new(major: u8, minor: u8) -> Self {
+        Self { major, minor }
+    }
+
+    pub fn from_raw(raw: u32) -> Self {
+        Self {
+            major: (raw >> 4) as u8,
+            minor: (raw & 0xf) as u8,
+        }
+    }
+}
+
+impl fmt::Display for ShaderModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CrossNodeSharingTier {
+    NotSupported,
+    Tier1Emulated,
+    Tier1,
+    Tier2,
+    Tier3,
+}
+
+impl CrossNodeSharingTier {
+    pub fn from_raw(raw: u32) -> Self {
+        match raw {
+            0 => CrossNodeSharingTier::NotSupported,
+            1 => CrossNodeSharingTier::Tier1Emulated,
+            2 => CrossNodeSharingTier::Tier1,
+            3 => CrossNodeSharingTier::Tier2,
+            _ => CrossNodeSharingTier::Tier3,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        *self != CrossNodeSharingTier::NotSupported
+    }
+}
+
+impl fmt::Display for CrossNodeSharingTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrossNodeSharingTier::NotSupported => write!(f, "-"),
+            CrossNodeSharingTier::Tier1Emulated => write!(f, "Tier 1 (emulated)"),
+            CrossNodeSharingTier::Tier1 => write!(f, "Tier 1"),
+            CrossNodeSharingTier::Tier2 => write!(f, "Tier 2"),
+            CrossNodeSharingTier::Tier3 => write!(f, "Tier 3"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AdapterLuid {
+    pub low: u32,
+    pub high: i32,
+}
+
+impl fmt::Display for AdapterLuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08x}-{:08x}", self.high as u32, self.low)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceCapabilities {
+    pub adapter: String,
+    pub luid: AdapterLuid,
+    pub feature_level: FeatureLevel,
+    pub shader_model: ShaderModel,
+    pub root_signature_1_1: bool,
+    pub resource_binding_tier: u8,
+    pub resource_heap_tier: u8,
+    pub cross_node_sharing_tier: CrossNodeSharingTier,
+    pub cross_adapter_row_major_texture: bool,
+    pub copy_queue_timestamps: bool,
+    pub uma: bool,
+    pub cache_coherent_uma: bool,
+    pub dedicated_video_memory: usize,
+}
+
+impl DeviceCapabilities {
+    pub fn supports_shader_model(&self, major: u8, minor: u8) -> bool {
+        self.shader_model >= ShaderModel::new(major, minor)
+    }
+
+    pub fn supports_feature_level(&self, major: u8, minor: u8) -> bool {
+        self.feature_level >= FeatureLevel::new(major, minor)
+    }
+
+    pub fn is_integrated(&self) -> bool {
+        self.uma
+    }
+
+    pub fn table(devices: &[DeviceCapabilities]) -> CapabilitiesTable<'_> {
+        CapabilitiesTable(devices)
+    }
+
+    fn rows(&self) -> [(&'static str, String); 13] {
+        const MB: usize = 1024 * 1024;
+
+        let flag = |value: bool| if value { "yes" } else { "no" }.to_string();
+
+        [
+            ("Adapter", self.adapter.clone()),
+            ("LUID", self.luid.to_string()),
+            ("Feature level", self.feature_level.to_string()),
+            ("Shader model", self.shader_model.to_string()),
+            ("Root signature 1.1", flag(self.root_signature_1_1)),
+            (
+                "Resource binding tier",
+                format!("Tier {}", self.resource_binding_tier),
+            ),
+            (
+                "Resource heap tier",
+                format!("Tier {}", self.resource_heap_tier),
+            ),
+            (
+                "Cross-node sharing",
+                self.cross_node_sharing_tier.to_string(),
+            ),
+            (
+                "Cross-adapter row-major",
+                flag(self.cross_adapter_row_major_texture),
+            ),
+            ("Copy queue timestamps", flag(self.copy_queue_timestamps)),
+            ("UMA", flag(self.uma)),
+            ("Cache-coherent UMA", flag(self.cache_coherent_uma)),
+            (
+                "Dedicated video memory",
+                format!("{} MB", self.dedicated_video_memory / MB),
+            ),
+        ]
+    }
+}
+
+impl fmt::Display for DeviceCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        CapabilitiesTable(std::slice::from_ref(self)).fmt(f)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CapabilitiesTable<'a>(&'a [DeviceCapabilities]);
+
+impl fmt::Display for CapabilitiesTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self.0.iter().map(|d| d.rows()).collect::<Vec<_>>();
+
+        let label_width = rows
+            .first()
+            .map(|r| r.iter().map(|(label, _)| label.len()).max().unwrap_or(0))
+            .unwrap_or(0);
+
+        let widths = rows
+            .iter()
+            .map(|r| r.iter().map(|(_, value)| value.len()).max().unwrap_or(0))
+            .collect::<Vec<_>>();
+
+        for row in 0..rows.first().map_or(0, |r| r.len()) {
+            write!(f, "{:<width$}", rows[0][row].0, width = label_width)?;
+
+            for (device, width) in rows.iter().zip(widths.iter()) {
+                write!(f, " | {:<width$}", device[row].1, width = *width)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdapterLuid, CrossNodeSharingTier, DeviceCapabilities, FeatureLevel, ShaderModel};
+
+    fn capabilities(adapter: &str) -> DeviceCapabilities {
+        DeviceCapabilities {
+            adapter: adapter.to_string(),
+            luid: AdapterLuid {
+                low: 0xbeef,
+                high: 0,
+            },
+            feature_level: FeatureLevel::from_raw(0xc100),
+            shader_model: ShaderModel::from_raw(0x66),
+            root_signature_1_1: true,
+            resource_binding_tier: 3,
+            resource_heap_tier: 2,
+            cross_node_sharing_tier: CrossNodeSharingTier::from_raw(3),
+            cross_adapter_row_major_texture: true,
+            copy_queue_timestamps: false,
+            uma: false,
+            cache_coherent_uma: false,
+            dedicated_video_memory: 8 * 1024 * 1024 * 1024,
+        }
+    }
+
+    #[test]
+    fn test_decodes_raw_values() {
+        let caps = capabilities("Discrete");
+
+        assert_eq!(caps.feature_level, FeatureLevel::new(12, 1));
+        assert_eq!(caps.shader_model, ShaderModel::new(6, 6));
+        assert_eq!(caps.cross_node_sharing_tier, CrossNodeSharingTier::Tier2);
+
+        assert!(caps.supports_shader_model(6, 5));
+        assert!(!caps.supports_shader_model(6, 7));
+        assert!(caps.supports_feature_level(12, 0));
+    }
+
+    #[test]
+    fn test_prints_table() {
+        let table = capabilities("Discrete").to_string();
+
+        assert!(table.contains("Feature level"));
+        assert!(table.contains("12_1"));
+        assert!(table.contains("6.6"));
+        assert!(table.contains("Tier 2"));
+        assert!(table.contains("8192 MB"));
+        assert!(table.contains("00000000-0000beef"));
+        assert_eq!(table.lines().count(), 13);
+    }
+
+    #[test]
+    fn test_table_aligns_columns_per_device() {
+        let devices = [capabilities("Discrete"), capabilities("Integrated GPU")];
+        let table = DeviceCapabilities::table(&devices).to_string();
+
+        let separators = table
+            .lines()
+            .map(|line| {
+                line.match_indices(" | ")
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert!(separators.iter().all(|s| s.len() == 2));
+        assert!(separators.windows(2).all(|w| w[0] == w[1]));
+    }
+}
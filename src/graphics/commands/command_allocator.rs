@@ -1,13 +1,17 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, sync::Arc};
 
 use oxidx::dx::{self, IDevice};
+use parking_lot::Mutex;
 
 use super::worker_type::WorkerType;
 
+pub(crate) type CommandAllocatorPool<T> = Arc<Mutex<Vec<CommandAllocator<T>>>>;
+
 #[derive(Debug)]
 pub(crate) struct CommandAllocator<T: WorkerType> {
     pub(crate) raw: dx::CommandAllocator,
     pub(crate) fence_value: u64,
+    pub(crate) pool: Option<CommandAllocatorPool<T>>,
     _marker: PhantomData<T>,
 }
 
@@ -18,6 +22,7 @@ impl<T: WorkerType> CommandAllocator<T> {
         Self {
             raw,
             fence_value: 0,
+            pool: None,
             _marker: PhantomData,
         }
     }
@@ -1,11 +1,18 @@
-use std::{collections::VecDeque, marker::PhantomData, ops::Deref, sync::Arc};
+use std::{collections::VecDeque, marker::PhantomData, ops::Deref, sync::Arc, time::Duration};
 
 use oxidx::dx::{self, ICommandAllocator, ICommandQueue, IDevice, IGraphicsCommandList, PSO_NONE};
 use parking_lot::Mutex;
 
-use crate::graphics::{device::Device, fence::Fence};
+use crate::graphics::{
+    deferred::FenceSignal,
+    device::Device,
+    fence::{Fence, SharedFence, SharedSignal},
+    fence_wait::{token, FenceError, FenceFuture, FenceValue, TimelineFence},
+};
 
-use super::{command_allocator::CommandAllocator, worker_type::WorkerType, WorkerThread};
+use super::{
+    command_allocator::CommandAllocator, worker_type::WorkerType, TrackedBarrier, WorkerThread,
+};
 
 #[derive(Clone, Debug)]
 pub struct CommandQueue<T: WorkerType>(Arc<CommandQueueInner<T>>);
@@ -33,6 +40,7 @@ impl<T: WorkerType> CommandQueue<T> {
         let desc = T::queue_desc();
 
         let queue: dx::CommandQueue = device.raw.create_command_queue(&desc).unwrap();
+        device.register_queue_fence(&fence);
 
         let cmd_allocators = (0..3)
             .map(|_| device.create_command_allocator())
@@ -67,20 +75,29 @@ impl<T: WorkerType> CommandQueue<T> {
 
 impl<T: WorkerType> CommandQueue<T> {
     pub fn push_worker(&self, worker: WorkerThread<T>) {
+        worker.flush_barriers();
         worker.list.close().unwrap();
-        self.temp_buffer.lock().push(Some(worker.list.clone()));
         self.pending_list.lock().push(worker);
     }
 
-    pub fn wait_on_cpu(&self, value: u64) {
-        if !self.is_fence_complete(value) {
-            let event_handle = dx::Event::create(false, false).unwrap();
+    pub fn wait_on_cpu(&self, value: FenceValue) -> Result<(), FenceError> {
+        self.fence.wait(value, None)
+    }
 
-            self.fence.set_event_on_completion(value, event_handle);
-            event_handle.wait(u32::MAX);
+    pub fn wait_on_cpu_timeout(
+        &self,
+        value: FenceValue,
+        timeout: Duration,
+    ) -> Result<(), FenceError> {
+        self.fence.wait(value, Some(timeout))
+    }
 
-            event_handle.close().unwrap();
-        }
+    pub fn wait_async(&self, value: FenceValue) -> Result<FenceFuture<Fence>, FenceError> {
+        self.fence.wait_async(value)
+    }
+
+    pub fn last_value(&self) -> FenceValue {
+        self.fence.current()
     }
 
     pub fn wait_other_queue_on_gpu<OT: WorkerType>(&self, queue: &CommandQueue<OT>) {
@@ -93,23 +110,81 @@ impl<T: WorkerType> CommandQueue<T> {
     pub fn wait_fence_gpu(&self, fence: &Fence) {
         self.raw
             .lock()
-            .wait(fence.get_raw(), fence.get_current_value())
+            .wait(fence.get_raw(), fence.get_signaled_value())
             .unwrap();
     }
 
-    pub fn execute(&self) -> u64 {
-        let threads = self.pending_list.lock().drain(..).collect::<Vec<_>>();
+    pub fn last_signal(&self) -> FenceSignal {
+        FenceSignal::new(self.fence.clone(), self.fence.get_current_value())
+    }
+
+    pub fn wait_fence_value_gpu(&self, fence: &Fence, value: FenceValue) -> Result<(), FenceError> {
+        let value = fence.check(value)?;
+        fence.debug_validate_wait(value);
+        self.raw.lock().wait(fence.get_raw(), value).unwrap();
+        Ok(())
+    }
+
+    pub fn signal_fence(&self, fence: &Fence) -> FenceValue {
+        let value = fence.inc_value();
+        self.raw.lock().signal(fence.get_raw(), value).unwrap();
+        fence.mark_signaled(value);
+        token(fence, value)
+    }
+
+    pub fn signal_shared(&self, fence: &SharedFence) -> SharedSignal {
+        assert!(
+            fence.owner() == &self.device,
+            "CommandQueue: shared fence must be opened on the signaling device"
+        );
+
+        let value = fence.inc_value();
+        self.raw.lock().signal(fence.get_raw(), value).unwrap();
+        fence.signal_handle(value)
+    }
+
+    pub fn wait_shared(&self, fence: &SharedFence, signal: &SharedSignal) {
+        assert!(
+            fence.owner() == &self.device,
+            "CommandQueue: shared fence must be opened on the waiting device"
+        );
+
+        let value = fence.validate_wait(signal);
+        self.raw.lock().wait(fence.get_raw(), value).unwrap();
+    }
+
+    pub fn execute(&self) -> FenceValue {
+        let pending = self.pending_list.lock().drain(..).collect::<Vec<_>>();
 
-        let lists = self.temp_buffer.lock().drain(..).collect::<Vec<_>>();
+        let mut threads = Vec::with_capacity(pending.len());
+        let mut temp_buffer = self.temp_buffer.lock();
+
+        for thread in pending {
+            if let Some(patch) = self.patch_worker(&thread) {
+                temp_buffer.push(Some(patch.list.clone()));
+                threads.push(patch);
+            }
+
+            temp_buffer.push(Some(thread.list.clone()));
+            threads.push(thread);
+        }
+
+        let lists = temp_buffer.drain(..).collect::<Vec<_>>();
+        drop(temp_buffer);
 
         self.raw.lock().execute_command_lists(&lists);
         let fence_value = self.signal();
 
-        let allocators = threads.into_iter().map(|mut thread| {
-            thread.allocator.fence_value += 1;
-            thread.allocator
-        });
-        self.cmd_allocators.lock().extend(allocators);
+        let mut cmd_allocators = self.cmd_allocators.lock();
+        for mut thread in threads {
+            thread.allocator.fence_value = fence_value.value();
+
+            match thread.allocator.pool.clone() {
+                Some(pool) => pool.lock().push(thread.allocator),
+                None => cmd_allocators.push_back(thread.allocator),
+            }
+        }
+        drop(cmd_allocators);
 
         let lists = lists
             .into_iter()
@@ -134,6 +209,14 @@ impl<T: WorkerType> CommandQueue<T> {
             self.device.create_command_allocator()
         };
 
+        self.worker_thread_with(allocator, pso)
+    }
+
+    pub(crate) fn worker_thread_with(
+        &self,
+        allocator: CommandAllocator<T>,
+        pso: Option<&dx::PipelineState>,
+    ) -> WorkerThread<T> {
         let list = if let Some(list) = self.cmd_list.lock().pop() {
             list.reset(&allocator.raw, pso).unwrap();
             list
@@ -149,15 +232,36 @@ impl<T: WorkerType> CommandQueue<T> {
             allocator,
             list,
             frequency: self.frequency,
+            tracker: Default::default(),
         }
     }
 }
 
 impl<T: WorkerType> CommandQueue<T> {
-    fn signal(&self) -> u64 {
-        let value = self.fence.inc_value();
-        self.raw.lock().signal(self.fence.get_raw(), value).unwrap();
-        value
+    fn patch_worker(&self, thread: &WorkerThread<T>) -> Option<WorkerThread<T>> {
+        let mut tracker = thread.tracker.lock();
+        let patches = tracker.resolve();
+
+        let patch = (!patches.is_empty()).then(|| {
+            let patch = self.get_worker_thread(None);
+            let barriers = patches
+                .into_iter()
+                .map(TrackedBarrier::Transition)
+                .collect::<Vec<_>>();
+
+            WorkerThread::<T>::record_barriers(&patch.list, &tracker, &barriers);
+            patch.list.close().unwrap();
+
+            patch
+        });
+
+        tracker.reset();
+
+        patch
+    }
+
+    fn signal(&self) -> FenceValue {
+        self.signal_fence(&self.fence)
     }
 
     fn is_fence_complete(&self, value: u64) -> bool {
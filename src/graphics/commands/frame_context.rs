@@ -0,0 +1,230 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use oxidx::dx::{self, ICommandAllocator, IDevice, IResource};
+use parking_lot::Mutex;
+
+use crate::graphics::{
+    device::Device,
+    fence_wait::FenceValue,
+    heaps::{MemoryKind, MemoryTrack, UploadRing},
+    utils::NonNullSend,
+    MemoryHeapType,
+};
+
+use super::{
+    command_allocator::{CommandAllocator, CommandAllocatorPool},
+    CommandQueue, WorkerThread, WorkerType,
+};
+
+const CONSTANT_ALIGNMENT: usize = 256;
+
+#[derive(Debug)]
+pub struct FrameContext<T: WorkerType> {
+    index: usize,
+    fence_value: FenceValue,
+    allocators: CommandAllocatorPool<T>,
+}
+
+impl<T: WorkerType> FrameContext<T> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn fence_value(&self) -> FenceValue {
+        self.fence_value
+    }
+}
+
+#[derive(Debug)]
+pub struct UploadAllocation<'a> {
+    pub offset: usize,
+    pub size: usize,
+    pub gpu_address: dx::GpuVirtualAddress,
+    cpu: NonNullSend<u8>,
+    _ring: PhantomData<&'a UploadRing>,
+}
+
+impl UploadAllocation<'_> {
+    pub fn write<V: Copy>(&self, data: &[V]) {
+        assert!(size_of_val(data) <= self.size);
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr() as *const u8,
+                self.cpu.as_ptr(),
+                size_of_val(data),
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UploadBuffer {
+    raw: dx::Resource,
+    mapped: NonNullSend<u8>,
+    base: dx::GpuVirtualAddress,
+    _memory: MemoryTrack,
+}
+
+impl Drop for UploadBuffer {
+    fn drop(&mut self) {
+        self.raw.unmap(0, None);
+    }
+}
+
+#[derive(Debug)]
+pub struct FrameContexts<T: WorkerType> {
+    device: Device,
+    queue: CommandQueue<T>,
+    frames: Vec<FrameContext<T>>,
+    upload: UploadBuffer,
+    ring: Mutex<UploadRing>,
+    frame: u64,
+}
+
+impl<T: WorkerType> FrameContexts<T> {
+    pub(crate) fn inner_new(
+        device: &Device,
+        queue: CommandQueue<T>,
+        count: usize,
+        upload_size: usize,
+    ) -> Self {
+        assert!(
+            count > 0,
+            "FrameContexts: at least one frame in flight is required"
+        );
+
+        let upload_size = upload_size.next_multiple_of(CONSTANT_ALIGNMENT);
+        let desc = dx::ResourceDesc::buffer(upload_size);
+
+        let raw: dx::Resource = device
+            .raw
+            .create_committed_resource(
+                &dx::HeapProperties::upload(),
+                dx::HeapFlags::empty(),
+                &desc,
+                dx::ResourceStates::GenericRead,
+                None,
+            )
+            .unwrap();
+
+        let mapped = raw.map::<u8>(0, None).unwrap();
+        let base = raw.get_gpu_virtual_address();
+        let memory = device.track_resource(&desc, MemoryHeapType::Cpu, MemoryKind::Committed);
+
+        let frames = (0..count)
+            .map(|index| FrameContext {
+                index,
+                fence_value: queue.fence.completed(),
+                allocators: Arc::new(Mutex::new(vec![])),
+            })
+            .collect();
+
+        Self {
+            device: device.clone(),
+            queue,
+            frames,
+            upload: UploadBuffer {
+                raw,
+                mapped: mapped.into(),
+                base,
+                _memory: memory,
+            },
+            ring: Mutex::new(UploadRing::new(upload_size)),
+            frame: 0,
+        }
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn current(&self) -> &FrameContext<T> {
+        &self.frames[(self.frame % self.frames.len() as u64) as usize]
+    }
+
+    pub fn begin_frame(&mut self) -> usize {
+        let current = self.current();
+
+        self.queue.wait_on_cpu(current.fence_value).unwrap();
+        self.ring.lock().release(current.fence_value.value());
+        self.device.collect_garbage();
+
+        for allocator in current.allocators.lock().iter() {
+            allocator.raw.reset().unwrap();
+        }
+
+        current.index
+    }
+
+    pub fn get_worker_thread(&self, pso: Option<&dx::PipelineState>) -> WorkerThread<T> {
+        let current = self.current();
+
+        let allocator = current.allocators.lock().pop().unwrap_or_else(|| {
+            let mut allocator: CommandAllocator<T> = self.device.create_command_allocator();
+            allocator.pool = Some(Arc::clone(&current.allocators));
+            allocator
+        });
+
+        self.queue.worker_thread_with(allocator, pso)
+    }
+
+    pub fn upload<V: Copy>(&self, data: &[V], alignment: usize) -> UploadAllocation<'_> {
+        let size = size_of_val(data).max(1);
+
+        let offset = self
+            .ring
+            .lock()
+            .allocate(size, alignment.max(align_of::<V>()))
+            .expect("FrameContexts: upload ring is out of memory, increase upload size");
+
+        let allocation = UploadAllocation {
+            offset,
+            size,
+            gpu_address: self.upload.base + offset as u64,
+            cpu: unsafe {
+                std::ptr::NonNull::new_unchecked(self.upload.mapped.as_ptr().add(offset))
+            }
+            .into(),
+            _ring: PhantomData,
+        };
+        allocation.write(data);
+
+        allocation
+    }
+
+    pub fn upload_constant<V: Copy>(&self, value: &V) -> dx::GpuVirtualAddress {
+        self.upload(std::slice::from_ref(value), CONSTANT_ALIGNMENT)
+            .gpu_address
+    }
+
+    pub fn end_frame(&mut self) -> FenceValue {
+        let fence_value = self.queue.execute();
+
+        let index = (self.frame % self.frames.len() as u64) as usize;
+        self.frames[index].fence_value = fence_value;
+        self.ring.lock().end_frame(fence_value.value());
+
+        self.frame += 1;
+
+        fence_value
+    }
+
+    pub fn wait_idle(&self) {
+        let Some(value) = self
+            .frames
+            .iter()
+            .map(|f| f.fence_value)
+            .max_by_key(|v| v.value())
+        else {
+            return;
+        };
+
+        self.queue.wait_on_cpu(value).unwrap();
+        self.ring.lock().release(value.value());
+    }
+}
@@ -1,9 +1,13 @@
 mod command_allocator;
 mod command_queue;
+mod frame_context;
+mod state_tracker;
 mod worker_thread;
 mod worker_type;
 
 pub(crate) use command_allocator::*;
 pub use command_queue::*;
+pub use frame_context::*;
+pub use state_tracker::*;
 pub use worker_thread::*;
 pub use worker_type::*;
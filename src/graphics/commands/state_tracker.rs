@@ -0,0 +1,749 @@
+use std::{collections::HashMap, ops::Range};
+
+use smallvec::{smallvec, SmallVec};
+
+use crate::graphics::ResourceStates;
+
+pub trait TrackedState: Clone {
+    fn id(&self) -> usize;
+    fn subresource_count(&self) -> usize;
+    fn global_state(&self, subresource: usize) -> ResourceStates;
+    fn set_global_state(&self, subresource: usize, state: ResourceStates);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateTransition {
+    pub id: usize,
+    pub subresource: Option<usize>,
+    pub before: ResourceStates,
+    pub after: ResourceStates,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrackedBarrier {
+    Transition(StateTransition),
+    BeginSplit(StateTransition),
+    EndSplit(StateTransition),
+    Uav(Option<usize>),
+    Aliasing {
+        before: Option<usize>,
+        after: Option<usize>,
+    },
+}
+
+#[derive(Debug)]
+struct TrackedEntry<R> {
+    resource: R,
+    first: Vec<Option<ResourceStates>>,
+    current: Vec<Option<ResourceStates>>,
+    split: Vec<Option<StateTransition>>,
+}
+
+#[derive(Debug)]
+pub struct StateTracker<R: TrackedState> {
+    entries: Vec<TrackedEntry<R>>,
+    lookup: HashMap<usize, usize>,
+    batched: Vec<TrackedBarrier>,
+}
+
+impl<R: TrackedState> Default for StateTracker<R> {
+    fn default() -> Self {
+        Self {
+            entries: Default::default(),
+            lookup: Default::default(),
+            batched: Default::default(),
+        }
+    }
+}
+
+impl<R: TrackedState> StateTracker<R> {
+    pub fn transition(&mut self, resource: &R, subresource: Option<usize>, state: ResourceStates) {
+        let index = self.register(resource);
+        let entry = &mut self.entries[index];
+
+        if let Some(subresource) = subresource {
+            assert!(subresource < entry.current.len());
+
+            Self::transition_subresource(&mut self.batched, entry, Some(subresource), state);
+
+            return;
+        }
+
+        if Self::is_uniform(entry) {
+            Self::transition_subresource(&mut self.batched, entry, None, state);
+        } else {
+            for subresource in 0..entry.current.len() {
+                Self::transition_subresource(&mut self.batched, entry, Some(subresource), state);
+            }
+        }
+    }
+
+    pub fn begin_transition(
+        &mut self,
+        resource: &R,
+        subresource: Option<usize>,
+        state: ResourceStates,
+    ) {
+        let index = self.register(resource);
+        let entry = &mut self.entries[index];
+        let range = Self::range(entry, subresource);
+
+        if range.clone().any(|i| entry.current[i].is_none()) {
+            self.transition(resource, subresource, state);
+
+            return;
+        }
+
+        let subresources = if subresource.is_some() || Self::is_uniform(entry) {
+            smallvec![subresource]
+        } else {
+            range.map(Some).collect::<SmallVec<[_; 8]>>()
+        };
+
+        for subresource in subresources {
+            let range = Self::range(entry, subresource);
+            let before = entry.current[range.start].unwrap();
+
+            assert!(
+                range.clone().all(|i| entry.split[i].is_none()),
+                "StateTracker: split transition is already in flight"
+            );
+
+            if before == state {
+                continue;
+            }
+
+            let transition = StateTransition {
+                id: entry.resource.id(),
+                subresource,
+                before,
+                after: state,
+            };
+
+            for i in range {
+                entry.current[i] = Some(state);
+                entry.split[i] = Some(transition);
+            }
+
+            self.batched.push(TrackedBarrier::BeginSplit(transition));
+        }
+    }
+
+    pub fn end_transition(
+        &mut self,
+        resource: &R,
+        subresource: Option<usize>,
+        state: ResourceStates,
+    ) {
+        let index = self.register(resource);
+        let entry = &mut self.entries[index];
+
+        for i in Self::range(entry, subresource) {
+            let Some(transition) = entry.split[i] else {
+                continue;
+            };
+
+            assert_eq!(
+                transition.after, state,
+                "StateTracker: split transition ends in another state"
+            );
+
+            for j in Self::range(entry, transition.subresource) {
+                entry.split[j] = None;
+            }
+
+            self.batched.push(TrackedBarrier::EndSplit(transition));
+        }
+    }
+
+    pub fn uav(&mut self, resource: Option<&R>) {
+        let id = resource.map(|resource| {
+            self.register(resource);
+            resource.id()
+        });
+
+        self.batched.push(TrackedBarrier::Uav(id));
+    }
+
+    pub fn aliasing(&mut self, before: Option<&R>, after: Option<&R>) {
+        let before = before.map(|resource| {
+            self.register(resource);
+            resource.id()
+        });
+        let after = after.map(|resource| {
+            self.register(resource);
+            resource.id()
+        });
+
+        self.batched
+            .push(TrackedBarrier::Aliasing { before, after });
+    }
+
+    pub fn local_state(&self, id: usize, subresource: usize) -> Option<ResourceStates> {
+        self.lookup
+            .get(&id)
+            .and_then(|index| self.entries[*index].current[subresource])
+    }
+
+    pub fn resource(&self, id: usize) -> Option<&R> {
+        self.lookup
+            .get(&id)
+            .map(|index| &self.entries[*index].resource)
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.batched.is_empty()
+    }
+
+    pub fn flush(&mut self) -> impl Iterator<Item = TrackedBarrier> + '_ {
+        self.batched.drain(..)
+    }
+
+    pub fn resolve(&mut self) -> Vec<StateTransition> {
+        assert!(
+            self.batched.is_empty(),
+            "StateTracker: resolving with unflushed transitions"
+        );
+
+        let mut patches = vec![];
+
+        for entry in self.entries.iter() {
+            assert!(
+                entry.split.iter().all(|s| s.is_none()),
+                "StateTracker: split transition wasn't ended"
+            );
+
+            let mut transitions = (0..entry.first.len())
+                .filter_map(|subresource| {
+                    let first = entry.first[subresource]?;
+                    let global = entry.resource.global_state(subresource);
+
+                    (global != first).then_some(StateTransition {
+                        id: entry.resource.id(),
+                        subresource: Some(subresource),
+                        before: global,
+                        after: first,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let whole = transitions.len() == entry.first.len()
+                && transitions
+                    .iter()
+                    .all(|t| t.before == transitions[0].before && t.after == transitions[0].after);
+
+            if whole {
+                transitions.truncate(1);
+                transitions[0].subresource = None;
+            }
+
+            patches.extend(transitions);
+
+            for (subresource, state) in entry.current.iter().enumerate() {
+                if let Some(state) = state {
+                    entry.resource.set_global_state(subresource, *state);
+                }
+            }
+        }
+
+        patches
+    }
+
+    pub fn reset(&mut self) {
+        self.entries.clear();
+        self.lookup.clear();
+        self.batched.clear();
+    }
+
+    fn register(&mut self, resource: &R) -> usize {
+        *self.lookup.entry(resource.id()).or_insert_with(|| {
+            let count = resource.subresource_count();
+
+            self.entries.push(TrackedEntry {
+                resource: resource.clone(),
+                first: vec![None; count],
+                current: vec![None; count],
+                split: vec![None; count],
+            });
+            self.entries.len() - 1
+        })
+    }
+
+    fn range(entry: &TrackedEntry<R>, subresource: Option<usize>) -> Range<usize> {
+        match subresource {
+            Some(subresource) => subresource..(subresource + 1),
+            None => 0..entry.current.len(),
+        }
+    }
+
+    fn is_uniform(entry: &TrackedEntry<R>) -> bool {
+        entry.current.len() == 1
+            || entry
+                .current
+                .iter()
+                .all(|s| s.is_some() && *s == entry.current[0])
+    }
+
+    fn transition_subresource(
+        batched: &mut Vec<TrackedBarrier>,
+        entry: &mut TrackedEntry<R>,
+        subresource: Option<usize>,
+        state: ResourceStates,
+    ) {
+        let range = Self::range(entry, subresource);
+
+        assert!(
+            range.clone().all(|i| entry.split[i].is_none()),
+            "StateTracker: resource is used in the middle of split transition"
+        );
+
+        let Some(before) = entry.current[range.start] else {
+            for i in range {
+                entry.first[i] = Some(state);
+                entry.current[i] = Some(state);
+            }
+
+            return;
+        };
+
+        for i in range {
+            entry.current[i] = Some(state);
+        }
+
+        if before == state {
+            return;
+        }
+
+        let id = entry.resource.id();
+
+        let start = batched
+            .iter()
+            .rposition(|b| !matches!(b, TrackedBarrier::Transition(_)))
+            .map_or(0, |pos| pos + 1);
+
+        let target = |b: &TrackedBarrier| match b {
+            TrackedBarrier::Transition(t) if t.id == id => Some(t.subresource),
+            _ => None,
+        };
+
+        let merged = batched[start..]
+            .iter()
+            .rposition(|b| target(b) == Some(subresource))
+            .map(|pos| pos + start)
+            .filter(|pos| {
+                !batched[(pos + 1)..]
+                    .iter()
+                    .filter_map(target)
+                    .any(|other| other.is_none() || subresource.is_none())
+            });
+
+        if let Some(pos) = merged {
+            let TrackedBarrier::Transition(transition) = &mut batched[pos] else {
+                unreachable!()
+            };
+
+            if transition.before == state {
+                batched.remove(pos);
+            } else {
+                transition.after = state;
+            }
+        } else {
+            batched.push(TrackedBarrier::Transition(StateTransition {
+                id,
+                subresource,
+                before,
+                after: state,
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use parking_lot::Mutex;
+
+    use crate::graphics::ResourceStates;
+
+    use super::{StateTracker, StateTransition, TrackedBarrier, TrackedState};
+
+    #[derive(Clone, Debug, Default)]
+    struct MockTable(Arc<Mutex<HashMap<(usize, usize), ResourceStates>>>);
+
+    #[derive(Clone, Debug)]
+    struct MockResource {
+        id: usize,
+        count: usize,
+        table: MockTable,
+    }
+
+    impl MockTable {
+        fn resource(&self, id: usize, count: usize, state: ResourceStates) -> MockResource {
+            let mut table = self.0.lock();
+            for i in 0..count {
+                table.insert((id, i), state);
+            }
+
+            MockResource {
+                id,
+                count,
+                table: self.clone(),
+            }
+        }
+
+        fn state(&self, id: usize, subresource: usize) -> ResourceStates {
+            self.0.lock()[&(id, subresource)]
+        }
+    }
+
+    impl TrackedState for MockResource {
+        fn id(&self) -> usize {
+            self.id
+        }
+
+        fn subresource_count(&self) -> usize {
+            self.count
+        }
+
+        fn global_state(&self, subresource: usize) -> ResourceStates {
+            self.table.state(self.id, subresource)
+        }
+
+        fn set_global_state(&self, subresource: usize, state: ResourceStates) {
+            self.table.0.lock().insert((self.id, subresource), state);
+        }
+    }
+
+    #[test]
+    fn test_first_use_is_deferred_to_patch() {
+        let table = MockTable::default();
+        let buffer = table.resource(1, 1, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.transition(&buffer, None, ResourceStates::CopyDst);
+
+        assert!(!tracker.has_pending());
+        assert_eq!(tracker.local_state(1, 0), Some(ResourceStates::CopyDst));
+
+        tracker.transition(&buffer, None, ResourceStates::GenericRead);
+        assert_eq!(
+            tracker.flush().collect::<Vec<_>>(),
+            vec![TrackedBarrier::Transition(StateTransition {
+                id: 1,
+                subresource: None,
+                before: ResourceStates::CopyDst,
+                after: ResourceStates::GenericRead,
+            })]
+        );
+
+        let patches = tracker.resolve();
+        assert_eq!(
+            patches,
+            vec![StateTransition {
+                id: 1,
+                subresource: None,
+                before: ResourceStates::Common,
+                after: ResourceStates::CopyDst,
+            }]
+        );
+        assert_eq!(table.state(1, 0), ResourceStates::GenericRead);
+    }
+
+    #[test]
+    fn test_batched_transitions_are_merged() {
+        let table = MockTable::default();
+        let a = table.resource(1, 1, ResourceStates::Common);
+        let b = table.resource(2, 1, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.transition(&a, None, ResourceStates::CopySrc);
+        tracker.transition(&b, None, ResourceStates::CopyDst);
+
+        tracker.transition(&a, None, ResourceStates::CopyDst);
+        tracker.transition(&a, None, ResourceStates::PixelShaderResource);
+        tracker.transition(&b, None, ResourceStates::RenderTarget);
+        tracker.transition(&b, None, ResourceStates::CopyDst);
+
+        assert_eq!(
+            tracker.flush().collect::<Vec<_>>(),
+            vec![TrackedBarrier::Transition(StateTransition {
+                id: 1,
+                subresource: None,
+                before: ResourceStates::CopySrc,
+                after: ResourceStates::PixelShaderResource,
+            })]
+        );
+    }
+
+    #[test]
+    fn test_subresources() {
+        let table = MockTable::default();
+        let image = table.resource(7, 4, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.transition(&image, Some(2), ResourceStates::CopyDst);
+        tracker.transition(&image, None, ResourceStates::PixelShaderResource);
+
+        let flushed = tracker.flush().collect::<Vec<_>>();
+        assert_eq!(flushed.len(), 1);
+        assert!(matches!(
+            flushed[0],
+            TrackedBarrier::Transition(StateTransition {
+                subresource: Some(2),
+                ..
+            })
+        ));
+
+        let patches = tracker.resolve();
+        assert_eq!(patches.len(), 4);
+        assert_eq!(patches[2].after, ResourceStates::CopyDst);
+        assert!(patches
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 2)
+            .all(|(_, p)| p.after == ResourceStates::PixelShaderResource));
+
+        for i in 0..4 {
+            assert_eq!(table.state(7, i), ResourceStates::PixelShaderResource);
+        }
+    }
+
+    #[test]
+    fn test_subresource_is_not_merged_across_whole_resource() {
+        let table = MockTable::default();
+        let image = table.resource(3, 2, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.transition(&image, None, ResourceStates::CopyDst);
+        tracker.transition(&image, Some(0), ResourceStates::RenderTarget);
+        tracker.transition(&image, Some(1), ResourceStates::RenderTarget);
+        tracker.transition(&image, None, ResourceStates::PixelShaderResource);
+        tracker.transition(&image, Some(0), ResourceStates::CopyDst);
+
+        let transition = |subresource, before, after| {
+            TrackedBarrier::Transition(StateTransition {
+                id: 3,
+                subresource,
+                before,
+                after,
+            })
+        };
+
+        assert_eq!(
+            tracker.flush().collect::<Vec<_>>(),
+            vec![
+                transition(
+                    Some(0),
+                    ResourceStates::CopyDst,
+                    ResourceStates::RenderTarget
+                ),
+                transition(
+                    Some(1),
+                    ResourceStates::CopyDst,
+                    ResourceStates::RenderTarget
+                ),
+                transition(
+                    None,
+                    ResourceStates::RenderTarget,
+                    ResourceStates::PixelShaderResource
+                ),
+                transition(
+                    Some(0),
+                    ResourceStates::PixelShaderResource,
+                    ResourceStates::CopyDst
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_concurrent_lists_patch_in_submission_order() {
+        let table = MockTable::default();
+        let image = table.resource(3, 1, ResourceStates::Common);
+
+        let mut first = StateTracker::default();
+        let mut second = StateTracker::default();
+
+        first.transition(&image, None, ResourceStates::RenderTarget);
+        first.transition(&image, None, ResourceStates::PixelShaderResource);
+        second.transition(&image, None, ResourceStates::CopySrc);
+
+        assert_eq!(first.flush().count(), 1);
+        assert_eq!(second.flush().count(), 0);
+
+        let patches = first.resolve();
+        assert_eq!(patches[0].before, ResourceStates::Common);
+        assert_eq!(patches[0].after, ResourceStates::RenderTarget);
+
+        let patches = second.resolve();
+        assert_eq!(patches[0].before, ResourceStates::PixelShaderResource);
+        assert_eq!(patches[0].after, ResourceStates::CopySrc);
+
+        assert_eq!(table.state(3, 0), ResourceStates::CopySrc);
+    }
+
+    #[test]
+    fn test_no_patch_when_states_match() {
+        let table = MockTable::default();
+        let buffer = table.resource(1, 1, ResourceStates::GenericRead);
+
+        let mut tracker = StateTracker::default();
+        tracker.transition(&buffer, None, ResourceStates::GenericRead);
+
+        assert!(tracker.resolve().is_empty());
+        assert!(tracker.resource(1).is_some());
+
+        tracker.reset();
+        assert!(tracker.resource(1).is_none());
+    }
+
+    #[test]
+    fn test_transitions_are_not_merged_across_uav() {
+        let table = MockTable::default();
+        let buffer = table.resource(1, 1, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.transition(&buffer, None, ResourceStates::CopyDst);
+        tracker.transition(&buffer, None, ResourceStates::UnorderedAccess);
+        tracker.uav(Some(&buffer));
+        tracker.transition(&buffer, None, ResourceStates::PixelShaderResource);
+        tracker.uav(None);
+
+        let flushed = tracker.flush().collect::<Vec<_>>();
+        assert_eq!(flushed.len(), 4);
+        assert_eq!(flushed[1], TrackedBarrier::Uav(Some(1)));
+        assert_eq!(flushed[3], TrackedBarrier::Uav(None));
+        assert!(matches!(
+            flushed[2],
+            TrackedBarrier::Transition(StateTransition {
+                before: ResourceStates::UnorderedAccess,
+                after: ResourceStates::PixelShaderResource,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_whole_resource_is_not_merged_across_subresources_after_uav() {
+        let table = MockTable::default();
+        let image = table.resource(4, 2, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.transition(&image, None, ResourceStates::CopyDst);
+        tracker.uav(None);
+        tracker.transition(&image, None, ResourceStates::RenderTarget);
+        tracker.transition(&image, Some(0), ResourceStates::PixelShaderResource);
+        tracker.transition(&image, Some(1), ResourceStates::PixelShaderResource);
+        tracker.transition(&image, None, ResourceStates::CopyDst);
+
+        let transition = |subresource, before, after| {
+            TrackedBarrier::Transition(StateTransition {
+                id: 4,
+                subresource,
+                before,
+                after,
+            })
+        };
+
+        assert_eq!(
+            tracker.flush().collect::<Vec<_>>(),
+            vec![
+                TrackedBarrier::Uav(None),
+                transition(None, ResourceStates::CopyDst, ResourceStates::RenderTarget),
+                transition(
+                    Some(0),
+                    ResourceStates::RenderTarget,
+                    ResourceStates::PixelShaderResource
+                ),
+                transition(
+                    Some(1),
+                    ResourceStates::RenderTarget,
+                    ResourceStates::PixelShaderResource
+                ),
+                transition(
+                    None,
+                    ResourceStates::PixelShaderResource,
+                    ResourceStates::CopyDst
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_transition() {
+        let table = MockTable::default();
+        let image = table.resource(5, 2, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.transition(&image, None, ResourceStates::RenderTarget);
+        tracker.begin_transition(&image, None, ResourceStates::PixelShaderResource);
+
+        let split = StateTransition {
+            id: 5,
+            subresource: None,
+            before: ResourceStates::RenderTarget,
+            after: ResourceStates::PixelShaderResource,
+        };
+
+        assert_eq!(
+            tracker.flush().collect::<Vec<_>>(),
+            vec![TrackedBarrier::BeginSplit(split)]
+        );
+
+        tracker.end_transition(&image, None, ResourceStates::PixelShaderResource);
+        assert_eq!(
+            tracker.flush().collect::<Vec<_>>(),
+            vec![TrackedBarrier::EndSplit(split)]
+        );
+
+        tracker.resolve();
+        assert_eq!(table.state(5, 1), ResourceStates::PixelShaderResource);
+    }
+
+    #[test]
+    fn test_split_transition_on_first_use_is_patched() {
+        let table = MockTable::default();
+        let buffer = table.resource(1, 1, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.begin_transition(&buffer, None, ResourceStates::CopySrc);
+        tracker.end_transition(&buffer, None, ResourceStates::CopySrc);
+
+        assert!(!tracker.has_pending());
+        assert_eq!(tracker.resolve()[0].after, ResourceStates::CopySrc);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_use_during_split_transition() {
+        let table = MockTable::default();
+        let buffer = table.resource(1, 1, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.transition(&buffer, None, ResourceStates::CopyDst);
+        tracker.begin_transition(&buffer, None, ResourceStates::CopySrc);
+        tracker.transition(&buffer, None, ResourceStates::GenericRead);
+    }
+
+    #[test]
+    fn test_aliasing_registers_resources() {
+        let table = MockTable::default();
+        let a = table.resource(1, 1, ResourceStates::Common);
+        let b = table.resource(2, 1, ResourceStates::Common);
+
+        let mut tracker = StateTracker::default();
+        tracker.aliasing(Some(&a), Some(&b));
+
+        assert!(tracker.resource(1).is_some());
+        assert!(tracker.resource(2).is_some());
+        assert_eq!(
+            tracker.flush().collect::<Vec<_>>(),
+            vec![TrackedBarrier::Aliasing {
+                before: Some(1),
+                after: Some(2),
+            }]
+        );
+        assert!(tracker.resolve().is_empty());
+    }
+}
@@ -2,19 +2,23 @@ use std::ops::Deref;
 
 use super::{
     command_allocator::CommandAllocator,
+    state_tracker::{StateTracker, TrackedBarrier},
     worker_type::{Compute, Direct, Transfer, WorkerType},
 };
 
 use crate::graphics::{
     device::Device,
+    pipelines::{Graphics, Pipeline, PipelineLayout},
     resources::{
-        BufferResource, Image, ImageResource, IndexBuffer, IndexBufferType, SharedResource,
-        VertexBuffer,
+        BufferResource, DirtyRegion, DirtyUpdate, Image, ImageResource, IndexBuffer,
+        IndexBufferType, Resource, ShareableImage, SharedResource, TrackedResource, VertexBuffer,
     },
-    ResourceStates,
+    views::{DsvView, GpuView, RtvView, ViewAllocator, ViewType},
+    ResourceStates, SubresourceIndex,
 };
 
 use oxidx::dx::{self, IDevice, IGraphicsCommandList};
+use parking_lot::Mutex;
 use smallvec::SmallVec;
 
 #[derive(Debug)]
@@ -23,6 +27,7 @@ pub struct WorkerThread<T: WorkerType> {
     pub(crate) frequency: f64,
     pub(crate) allocator: CommandAllocator<T>,
     pub(crate) list: dx::GraphicsCommandList,
+    pub(crate) tracker: Mutex<StateTracker<TrackedResource>>,
 }
 
 impl<T: WorkerType> WorkerThread<T> {
@@ -42,6 +47,7 @@ impl<T: WorkerType> WorkerThread<T> {
             list,
             allocator,
             frequency,
+            tracker: Default::default(),
         }
     }
 }
@@ -63,17 +69,295 @@ impl Deref for WorkerThread<Compute> {
 }
 
 impl<T: WorkerType> WorkerThread<T> {
-    // TODO: Batched barrier
     pub fn barrier(&self, barriers: &[dx::ResourceBarrier<'_>]) {
+        self.flush_barriers();
         self.list.resource_barrier(barriers);
     }
+
+    pub fn transition_buffer<R: BufferResource>(&self, resource: &R, state: ResourceStates) {
+        self.tracker
+            .lock()
+            .transition(&TrackedResource::new(resource), None, state);
+    }
+
+    pub fn transition_image<R: ImageResource>(
+        &self,
+        resource: &R,
+        state: ResourceStates,
+        subresource: Option<SubresourceIndex>,
+    ) {
+        self.tracker.lock().transition(
+            &TrackedResource::new(resource),
+            subresource.map(|s| resource.subresource_index(s)),
+            state,
+        );
+    }
+
+    pub fn begin_transition_buffer<R: BufferResource>(&self, resource: &R, state: ResourceStates) {
+        self.tracker
+            .lock()
+            .begin_transition(&TrackedResource::new(resource), None, state);
+    }
+
+    pub fn end_transition_buffer<R: BufferResource>(&self, resource: &R, state: ResourceStates) {
+        self.tracker
+            .lock()
+            .end_transition(&TrackedResource::new(resource), None, state);
+    }
+
+    pub fn begin_transition_image<R: ImageResource>(
+        &self,
+        resource: &R,
+        state: ResourceStates,
+        subresource: Option<SubresourceIndex>,
+    ) {
+        self.tracker.lock().begin_transition(
+            &TrackedResource::new(resource),
+            subresource.map(|s| resource.subresource_index(s)),
+            state,
+        );
+    }
+
+    pub fn end_transition_image<R: ImageResource>(
+        &self,
+        resource: &R,
+        state: ResourceStates,
+        subresource: Option<SubresourceIndex>,
+    ) {
+        self.tracker.lock().end_transition(
+            &TrackedResource::new(resource),
+            subresource.map(|s| resource.subresource_index(s)),
+            state,
+        );
+    }
+
+    pub fn uav_barrier<R: Resource>(&self, resource: &R) {
+        self.tracker
+            .lock()
+            .uav(Some(&TrackedResource::new(resource)));
+    }
+
+    pub fn global_uav_barrier(&self) {
+        self.tracker.lock().uav(None);
+    }
+
+    pub fn aliasing_barrier<B: Resource, A: Resource>(&self, before: &B, after: &A) {
+        self.tracker.lock().aliasing(
+            Some(&TrackedResource::new(before)),
+            Some(&TrackedResource::new(after)),
+        );
+    }
+
+    pub fn aliasing_barrier_any<A: Resource>(&self, after: &A) {
+        self.tracker
+            .lock()
+            .aliasing(None, Some(&TrackedResource::new(after)));
+    }
+
+    pub fn flush_barriers(&self) {
+        let mut tracker = self.tracker.lock();
+
+        if !tracker.has_pending() {
+            return;
+        }
+
+        let barriers = tracker.flush().collect::<SmallVec<[_; 8]>>();
+        Self::record_barriers(&self.list, &tracker, &barriers);
+    }
+
+    pub(crate) fn record_barriers(
+        list: &dx::GraphicsCommandList,
+        tracker: &StateTracker<TrackedResource>,
+        barriers: &[TrackedBarrier],
+    ) {
+        let raw = |id: usize| &tracker.resource(id).unwrap().raw;
+
+        let barriers = barriers
+            .iter()
+            .map(|barrier| match barrier {
+                TrackedBarrier::Transition(t) => dx::ResourceBarrier::transition(
+                    raw(t.id),
+                    t.before.as_raw(),
+                    t.after.as_raw(),
+                    t.subresource,
+                ),
+                TrackedBarrier::BeginSplit(t) => dx::ResourceBarrier::transition(
+                    raw(t.id),
+                    t.before.as_raw(),
+                    t.after.as_raw(),
+                    t.subresource,
+                )
+                .with_flags(dx::ResourceBarrierFlags::BeginOnly),
+                TrackedBarrier::EndSplit(t) => dx::ResourceBarrier::transition(
+                    raw(t.id),
+                    t.before.as_raw(),
+                    t.after.as_raw(),
+                    t.subresource,
+                )
+                .with_flags(dx::ResourceBarrierFlags::EndOnly),
+                TrackedBarrier::Uav(id) => dx::ResourceBarrier::uav(id.map(raw)),
+                TrackedBarrier::Aliasing { before, after } => {
+                    dx::ResourceBarrier::aliasing(before.map(raw), after.map(raw))
+                }
+            })
+            .collect::<SmallVec<[_; 8]>>();
+
+        list.resource_barrier(&barriers);
+    }
+}
+
+impl WorkerThread<Compute> {
+    pub fn set_view_heaps(&self, views: &ViewAllocator) {
+        let heaps = views.shader_visible_heaps();
+        self.list
+            .set_descriptor_heaps(&[Some(heaps[0].clone()), Some(heaps[1].clone())]);
+    }
+
+    pub fn set_compute_pipeline(&self, pipeline: &Pipeline<Compute>) {
+        self.list.set_pipeline_state(&pipeline.raw());
+    }
+
+    pub fn set_compute_layout(&self, layout: &PipelineLayout) {
+        self.list.set_compute_root_signature(Some(&layout.raw));
+    }
+
+    pub fn set_compute_constants<C: RootConstants>(&self, index: u32, constants: &C) {
+        self.list
+            .set_compute_root_32bit_constants(index, as_root_constants(constants), 0);
+    }
+
+    pub fn set_compute_cbv(&self, index: u32, address: dx::GpuVirtualAddress) {
+        self.list
+            .set_compute_root_constant_buffer_view(index, address);
+    }
+
+    pub fn set_compute_srv(&self, index: u32, address: dx::GpuVirtualAddress) {
+        self.list
+            .set_compute_root_shader_resource_view(index, address);
+    }
+
+    pub fn set_compute_uav(&self, index: u32, address: dx::GpuVirtualAddress) {
+        self.list
+            .set_compute_root_unordered_access_view(index, address);
+    }
+
+    pub fn set_compute_table<V: ViewType>(&self, index: u32, view: GpuView<V>) {
+        self.list
+            .set_compute_root_descriptor_table(index, view.gpu());
+    }
+
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        self.flush_barriers();
+        self.list.dispatch(x, y, z);
+    }
 }
 
 impl WorkerThread<Direct> {
     pub fn clear_rt(&self, handle: dx::CpuDescriptorHandle, color: [f32; 4]) {
+        self.flush_barriers();
         self.list.clear_render_target_view(handle, color, &[]);
     }
 
+    pub fn clear_rtv(&self, view: GpuView<RtvView>, color: [f32; 4]) {
+        self.clear_rt(view.cpu(), color);
+    }
+
+    pub fn clear_dsv(&self, view: GpuView<DsvView>, depth: f32, stencil: u8) {
+        self.flush_barriers();
+        self.list.clear_depth_stencil_view(
+            view.cpu(),
+            dx::ClearFlags::Depth | dx::ClearFlags::Stencil,
+            depth,
+            stencil,
+            &[],
+        );
+    }
+
+    pub fn set_pipeline(&self, pipeline: &Pipeline<Graphics>) {
+        self.list.set_pipeline_state(&pipeline.raw());
+    }
+
+    pub fn set_layout(&self, layout: &PipelineLayout) {
+        self.list.set_graphics_root_signature(Some(&layout.raw));
+    }
+
+    pub fn set_graphics_constants<C: RootConstants>(&self, index: u32, constants: &C) {
+        self.list
+            .set_graphics_root_32bit_constants(index, as_root_constants(constants), 0);
+    }
+
+    pub fn set_graphics_cbv(&self, index: u32, address: dx::GpuVirtualAddress) {
+        self.list
+            .set_graphics_root_constant_buffer_view(index, address);
+    }
+
+    pub fn set_graphics_srv(&self, index: u32, address: dx::GpuVirtualAddress) {
+        self.list
+            .set_graphics_root_shader_resource_view(index, address);
+    }
+
+    pub fn set_graphics_table<V: ViewType>(&self, index: u32, view: GpuView<V>) {
+        self.list
+            .set_graphics_root_descriptor_table(index, view.gpu());
+    }
+
+    pub fn set_viewport(&self, viewport: dx::Viewport) {
+        self.list.rs_set_viewports(&[viewport]);
+    }
+
+    pub fn set_viewports(&self, viewports: &[dx::Viewport]) {
+        self.list.rs_set_viewports(viewports);
+    }
+
+    pub fn set_scissor(&self, rect: dx::Rect) {
+        self.list.rs_set_scissor_rects(&[rect]);
+    }
+
+    pub fn set_scissors(&self, rects: &[dx::Rect]) {
+        self.list.rs_set_scissor_rects(rects);
+    }
+
+    pub fn set_topology(&self, topology: dx::PrimitiveTopology) {
+        self.list.ia_set_primitive_topology(topology);
+    }
+
+    pub fn set_render_targets(&self, rtvs: &[GpuView<RtvView>], dsv: Option<GpuView<DsvView>>) {
+        let rtvs = rtvs.iter().map(|v| v.cpu()).collect::<SmallVec<[_; 8]>>();
+        let dsv = dsv.map(|v| v.cpu());
+
+        self.list.om_set_render_targets(&rtvs, false, dsv.as_ref());
+    }
+
+    pub fn draw(
+        &self,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    ) {
+        self.flush_barriers();
+        self.list
+            .draw_instanced(vertex_count, instance_count, first_vertex, first_instance);
+    }
+
+    pub fn draw_indexed(
+        &self,
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        base_vertex: i32,
+        first_instance: u32,
+    ) {
+        self.flush_barriers();
+        self.list.draw_indexed_instanced(
+            index_count,
+            instance_count,
+            first_index,
+            base_vertex,
+            first_instance,
+        );
+    }
+
     pub fn bind_vertex_buffer(&self, slot: u32, view: dx::VertexBufferView) {
         self.list.ia_set_vertex_buffers(slot, &[view]);
     }
@@ -93,22 +377,17 @@ impl WorkerThread<Transfer> {
             return;
         }
 
-        let mut barriers: SmallVec<[dx::ResourceBarrier<'_>; 2]> = Default::default();
-
-        if let Some(shared_state) = shared_resource
-            .cross_resource()
-            .get_barrier(ResourceStates::CopySrc, None)
-        {
-            barriers.push(shared_state);
-        }
-        if let Some(local_state) = shared_resource
-            .local_resource()
-            .get_barrier(ResourceStates::CopyDst, None)
-        {
-            barriers.push(local_state);
-        }
-
-        self.barrier(&barriers);
+        self.transition_image(
+            shared_resource.cross_resource(),
+            ResourceStates::CopySrc,
+            None,
+        );
+        self.transition_image(
+            shared_resource.local_resource(),
+            ResourceStates::CopyDst,
+            None,
+        );
+        self.flush_barriers();
 
         self.list.copy_resource(
             shared_resource.local_resource().get_raw(),
@@ -121,22 +400,17 @@ impl WorkerThread<Transfer> {
             return;
         }
 
-        let mut barriers: SmallVec<[dx::ResourceBarrier<'_>; 2]> = Default::default();
-
-        if let Some(shared_state) = shared_resource
-            .cross_resource()
-            .get_barrier(ResourceStates::CopyDst, None)
-        {
-            barriers.push(shared_state);
-        }
-        if let Some(local_state) = shared_resource
-            .local_resource()
-            .get_barrier(ResourceStates::CopySrc, None)
-        {
-            barriers.push(local_state);
-        }
-
-        self.barrier(&barriers);
+        self.transition_image(
+            shared_resource.cross_resource(),
+            ResourceStates::CopyDst,
+            None,
+        );
+        self.transition_image(
+            shared_resource.local_resource(),
+            ResourceStates::CopySrc,
+            None,
+        );
+        self.flush_barriers();
 
         self.list.copy_resource(
             shared_resource.cross_resource().get_raw(),
@@ -144,27 +418,128 @@ impl WorkerThread<Transfer> {
         );
     }
 
-    pub fn pull_shared_buffer<R: BufferResource>(&self, shared_resource: &SharedResource<R>) {
+    pub fn pull_shared_texture_region<R: ImageResource>(
+        &self,
+        shared_resource: &SharedResource<R>,
+        region: DirtyRegion,
+    ) {
+        if self.device.is_cross_adapter_texture_supported() {
+            return;
+        }
+
+        self.transition_image(
+            shared_resource.cross_resource(),
+            ResourceStates::CopySrc,
+            None,
+        );
+        self.transition_image(
+            shared_resource.local_resource(),
+            ResourceStates::CopyDst,
+            None,
+        );
+        self.flush_barriers();
+
+        self.copy_texture_region(
+            shared_resource.local_resource(),
+            shared_resource.cross_resource(),
+            region,
+        );
+    }
+
+    pub fn push_shared_texture_region<R: ImageResource>(
+        &self,
+        shared_resource: &SharedResource<R>,
+        region: DirtyRegion,
+    ) {
         if self.device.is_cross_adapter_texture_supported() {
             return;
         }
 
-        let mut barriers: SmallVec<[dx::ResourceBarrier<'_>; 2]> = Default::default();
+        self.transition_image(
+            shared_resource.cross_resource(),
+            ResourceStates::CopyDst,
+            None,
+        );
+        self.transition_image(
+            shared_resource.local_resource(),
+            ResourceStates::CopySrc,
+            None,
+        );
+        self.flush_barriers();
+
+        self.copy_texture_region(
+            shared_resource.cross_resource(),
+            shared_resource.local_resource(),
+            region,
+        );
+    }
+
+    pub fn push_shared_texture_dirty<R: ShareableImage>(
+        &self,
+        shared_resource: &SharedResource<R>,
+    ) -> DirtyUpdate {
+        let update = shared_resource.take_dirty();
+
+        match &update {
+            DirtyUpdate::Clean => {}
+            DirtyUpdate::Full => self.push_shared_texture(shared_resource),
+            DirtyUpdate::Regions(regions) => {
+                for region in regions {
+                    self.push_shared_texture_region(shared_resource, *region);
+                }
+            }
+        }
+
+        update
+    }
 
-        if let Some(shared_state) = shared_resource
-            .cross_resource()
-            .get_barrier(ResourceStates::CopySrc)
-        {
-            barriers.push(shared_state);
+    pub fn pull_shared_texture_dirty<R: ShareableImage>(
+        &self,
+        shared_resource: &SharedResource<R>,
+    ) -> DirtyUpdate {
+        let update = shared_resource.take_pushed();
+
+        match &update {
+            DirtyUpdate::Clean => {}
+            DirtyUpdate::Full => self.pull_shared_texture(shared_resource),
+            DirtyUpdate::Regions(regions) => {
+                for region in regions {
+                    self.pull_shared_texture_region(shared_resource, *region);
+                }
+            }
         }
-        if let Some(local_state) = shared_resource
-            .local_resource()
-            .get_barrier(ResourceStates::CopyDst)
-        {
-            barriers.push(local_state);
+
+        update
+    }
+
+    fn copy_texture_region<R: ImageResource>(&self, dst: &R, src: &R, region: DirtyRegion) {
+        let rect = region.rect;
+        let src_box = dx::DxBox::new(rect.left, rect.top, 0, rect.right, rect.bottom, 1);
+
+        self.list.copy_texture_region(
+            &dx::TextureCopyLocation::subresource(
+                dst.get_raw(),
+                dst.subresource_index(region.subresource) as u32,
+            ),
+            rect.left,
+            rect.top,
+            0,
+            &dx::TextureCopyLocation::subresource(
+                src.get_raw(),
+                src.subresource_index(region.subresource) as u32,
+            ),
+            Some(&src_box),
+        );
+    }
+
+    pub fn pull_shared_buffer<R: BufferResource>(&self, shared_resource: &SharedResource<R>) {
+        if self.device.is_cross_adapter_texture_supported() {
+            return;
         }
 
-        self.barrier(&barriers);
+        self.transition_buffer(shared_resource.cross_resource(), ResourceStates::CopySrc);
+        self.transition_buffer(shared_resource.local_resource(), ResourceStates::CopyDst);
+        self.flush_barriers();
 
         self.list.copy_resource(
             shared_resource.local_resource().get_raw(),
@@ -177,22 +552,9 @@ impl WorkerThread<Transfer> {
             return;
         }
 
-        let mut barriers: SmallVec<[dx::ResourceBarrier<'_>; 2]> = Default::default();
-
-        if let Some(shared_state) = shared_resource
-            .cross_resource()
-            .get_barrier(ResourceStates::CopyDst)
-        {
-            barriers.push(shared_state);
-        }
-        if let Some(local_state) = shared_resource
-            .local_resource()
-            .get_barrier(ResourceStates::CopySrc)
-        {
-            barriers.push(local_state);
-        }
-
-        self.barrier(&barriers);
+        self.transition_buffer(shared_resource.cross_resource(), ResourceStates::CopyDst);
+        self.transition_buffer(shared_resource.local_resource(), ResourceStates::CopySrc);
+        self.flush_barriers();
 
         self.list.copy_resource(
             shared_resource.cross_resource().get_raw(),
@@ -201,15 +563,12 @@ impl WorkerThread<Transfer> {
     }
 
     pub fn upload_to_vertex_buffer<VT: Clone + Copy>(&self, dst: &VertexBuffer<VT>, src: &[VT]) {
-        if let Some(barrier) = dst.get_barrier(ResourceStates::CopyDst) {
-            self.barrier(&[barrier]);
-        }
+        self.transition_buffer(dst, ResourceStates::CopyDst);
+        self.flush_barriers();
 
         dst.upload_data(self, src);
 
-        if let Some(barrier) = dst.get_barrier(ResourceStates::GenericRead) {
-            self.barrier(&[barrier]);
-        }
+        self.transition_buffer(dst, ResourceStates::GenericRead);
     }
 
     pub fn upload_to_index_buffer<IT: IndexBufferType>(
@@ -217,27 +576,60 @@ impl WorkerThread<Transfer> {
         dst: &IndexBuffer<IT>,
         src: &[IT::Raw],
     ) {
-        if let Some(barrier) = dst.get_barrier(ResourceStates::CopyDst) {
-            self.barrier(&[barrier]);
-        }
+        self.transition_buffer(dst, ResourceStates::CopyDst);
+        self.flush_barriers();
 
         dst.upload_data(self, src);
 
-        if let Some(barrier) = dst.get_barrier(ResourceStates::GenericRead) {
-            self.barrier(&[barrier]);
-        }
+        self.transition_buffer(dst, ResourceStates::GenericRead);
     }
 
     pub fn upload_to_texture2d(&self, dst: &Image, src: &[u8]) {
-        if let Some(barrier) = dst.get_barrier(ResourceStates::CopyDst, None) {
-            self.barrier(&[barrier]);
-        }
+        self.transition_image(dst, ResourceStates::CopyDst, None);
+        self.flush_barriers();
 
         dst.upload_data(self, src);
 
         // TODO: Return in prev state?
-        if let Some(barrier) = dst.get_barrier(ResourceStates::Common, None) {
-            self.barrier(&[barrier]);
-        }
+        self.transition_image(dst, ResourceStates::Common, None);
     }
 }
+
+/// Values that can be uploaded as root constants.
+///
+/// # Safety
+///
+/// The type must consist only of 32-bit values with no padding, so that it can be
+/// read as a `[u32]` of `size_of::<Self>() / 4` elements.
+pub unsafe trait RootConstants: Copy {}
+
+macro_rules! impl_root_constants {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            unsafe impl RootConstants for $ty {}
+        )*
+    };
+}
+
+impl_root_constants! {
+    u32,
+    i32,
+    f32,
+    glam::Vec2,
+    glam::Vec3,
+    glam::Vec4,
+    glam::UVec2,
+    glam::UVec3,
+    glam::UVec4,
+    glam::IVec2,
+    glam::IVec3,
+    glam::IVec4,
+    glam::Mat3,
+    glam::Mat4,
+}
+
+unsafe impl<C: RootConstants, const N: usize> RootConstants for [C; N] {}
+
+fn as_root_constants<C: RootConstants>(constants: &C) -> &[u32] {
+    unsafe { std::slice::from_raw_parts(constants as *const C as *const u32, size_of::<C>() / 4) }
+}
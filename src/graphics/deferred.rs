@@ -0,0 +1,276 @@
+use std::any::Any;
+
+use smallvec::SmallVec;
+
+use super::{
+    fence::Fence,
+    views::{GpuView, ViewAllocator, ViewType},
+};
+
+pub trait CompletedValue {
+    fn completed_value(&self) -> u64;
+}
+
+impl CompletedValue for Fence {
+    fn completed_value(&self) -> u64 {
+        self.get_completed_value()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FenceSignal<F = Fence> {
+    pub fence: F,
+    pub value: u64,
+}
+
+impl<F: CompletedValue> FenceSignal<F> {
+    pub fn new(fence: F, value: u64) -> Self {
+        Self { fence, value }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.fence.completed_value() >= self.value
+    }
+}
+
+#[derive(Debug)]
+struct DeferredEntry<T, F> {
+    item: T,
+    signals: SmallVec<[FenceSignal<F>; 2]>,
+}
+
+#[derive(Debug)]
+pub struct DeferredQueue<T, F = Fence> {
+    entries: Vec<DeferredEntry<T, F>>,
+}
+
+impl<T, F> Default for DeferredQueue<T, F> {
+    fn default() -> Self {
+        Self { entries: vec![] }
+    }
+}
+
+impl<T, F: CompletedValue> DeferredQueue<T, F> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn push(
+        &mut self,
+        item: T,
+        signals: impl IntoIterator<Item = FenceSignal<F>>,
+    ) -> Option<T> {
+        let signals = signals
+            .into_iter()
+            .filter(|s| !s.is_complete())
+            .collect::<SmallVec<_>>();
+
+        if signals.is_empty() {
+            return Some(item);
+        }
+
+        self.entries.push(DeferredEntry { item, signals });
+
+        None
+    }
+
+    pub fn take_completed(&mut self) -> Vec<T> {
+        let mut completed = vec![];
+
+        for mut entry in std::mem::take(&mut self.entries) {
+            entry.signals.retain(|s| !s.is_complete());
+
+            if entry.signals.is_empty() {
+                completed.push(entry.item);
+            } else {
+                self.entries.push(entry);
+            }
+        }
+
+        completed
+    }
+
+    pub fn collect(&mut self) -> usize {
+        self.take_completed().len()
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.entries.drain(..).map(|entry| entry.item)
+    }
+}
+
+pub type DeferredRelease = Box<dyn Any + Send + Sync>;
+
+#[derive(Debug)]
+pub struct DeferredView<V: ViewType> {
+    allocator: ViewAllocator,
+    view: GpuView<V>,
+}
+
+impl<V: ViewType> DeferredView<V> {
+    pub fn new(allocator: ViewAllocator, view: GpuView<V>) -> Self {
+        Self { allocator, view }
+    }
+}
+
+impl<V: ViewType> Drop for DeferredView<V> {
+    fn drop(&mut self) {
+        self.allocator.remove_view(&self.view);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use parking_lot::Mutex;
+
+    use super::{CompletedValue, DeferredQueue, FenceSignal};
+
+    #[derive(Clone, Debug, Default)]
+    struct FakeFence(Arc<AtomicU64>);
+
+    impl FakeFence {
+        fn complete(&self, value: u64) {
+            self.0.store(value, Ordering::Relaxed);
+        }
+    }
+
+    impl CompletedValue for FakeFence {
+        fn completed_value(&self) -> u64 {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Tracked(Arc<AtomicUsize>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_releases_after_fence() {
+        let fence = FakeFence::default();
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut queue = DeferredQueue::new();
+
+        queue.push(
+            Tracked(Arc::clone(&dropped)),
+            [FenceSignal::new(fence.clone(), 2)],
+        );
+
+        fence.complete(1);
+        assert_eq!(queue.collect(), 0);
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+
+        fence.complete(2);
+        assert_eq!(queue.collect(), 1);
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_waits_for_every_queue() {
+        let direct = FakeFence::default();
+        let compute = FakeFence::default();
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut queue = DeferredQueue::new();
+
+        queue.push(
+            Tracked(Arc::clone(&dropped)),
+            [
+                FenceSignal::new(direct.clone(), 5),
+                FenceSignal::new(compute.clone(), 3),
+            ],
+        );
+
+        direct.complete(5);
+        queue.collect();
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+
+        compute.complete(3);
+        queue.collect();
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_completed_signals_release_immediately() {
+        let fence = FakeFence::default();
+        fence.complete(10);
+
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut queue = DeferredQueue::new();
+
+        queue.push(
+            Tracked(Arc::clone(&dropped)),
+            [FenceSignal::new(fence.clone(), 4)],
+        );
+
+        assert!(queue.is_empty());
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_completed_items_can_release_more() {
+        struct Chained(
+            Arc<Mutex<DeferredQueue<Option<Chained>, FakeFence>>>,
+            FakeFence,
+        );
+
+        impl Drop for Chained {
+            fn drop(&mut self) {
+                let released = self
+                    .0
+                    .lock()
+                    .push(None, [FenceSignal::new(self.1.clone(), 2)]);
+
+                drop(released);
+            }
+        }
+
+        let fence = FakeFence::default();
+        let queue = Arc::new(Mutex::new(DeferredQueue::new()));
+
+        let released = queue.lock().push(
+            Some(Chained(Arc::clone(&queue), fence.clone())),
+            [FenceSignal::new(fence.clone(), 1)],
+        );
+        assert!(released.is_none());
+
+        fence.complete(1);
+        let completed = queue.lock().take_completed();
+        assert_eq!(completed.len(), 1);
+
+        drop(completed);
+        assert_eq!(queue.lock().len(), 1);
+    }
+
+    #[test]
+    fn test_releases_out_of_order() {
+        let first = FakeFence::default();
+        let second = FakeFence::default();
+        let mut queue = DeferredQueue::new();
+
+        queue.push(1, [FenceSignal::new(first.clone(), 1)]);
+        queue.push(2, [FenceSignal::new(second.clone(), 1)]);
+        queue.push(3, [FenceSignal::new(first.clone(), 2)]);
+
+        second.complete(1);
+        assert_eq!(queue.collect(), 1);
+        assert_eq!(queue.drain().collect::<Vec<_>>(), vec![1, 3]);
+    }
+}
@@ -1,48 +1,149 @@
 #![allow(private_bounds)]
 #![allow(private_interfaces)]
 
-use std::{num::NonZero, ops::Deref, path::Path, sync::Arc};
+use std::{any::Any, num::NonZero, ops::Deref, path::Path, sync::Arc};
 
 use oxidx::dx::{self, IAdapter3, IDevice};
+use parking_lot::{Mutex, RwLock};
 
 use super::{
-    commands::{CommandAllocator, CommandQueue, Compute, Direct, Transfer, WorkerType},
+    cache::DiskCache,
+    capabilities::{
+        AdapterLuid, CrossNodeSharingTier, DeviceCapabilities, FeatureLevel, ShaderModel,
+    },
+    commands::{
+        CommandAllocator, CommandQueue, Compute, Direct, FrameContexts, Transfer, WorkerType,
+    },
+    deferred::{DeferredQueue, DeferredRelease, DeferredView, FenceSignal},
     fence::{Fence, LocalFence, SharedFence},
-    heaps::MemoryHeap,
+    heaps::{
+        MemoryBudget, MemoryHeap, MemoryKind, MemoryReport, MemorySegment, MemoryTrack,
+        MemoryTracker, MemoryUsage, TransientImageDesc, TransientPool, VideoMemoryInfo,
+    },
     queries::{QueryHeap, QueryHeapType},
     resources::{
         BufferResource, BufferResourceDesc, ImageResource, ImageResourceDesc, Resource,
-        ShareableBuffer, ShareableImage, SharedResource,
+        ShareableBuffer, ShareableImage, SharedResource, SharedResourceRing, ViewAccess,
     },
     swapchain::Swapchain,
     types::{
         BufferCopyableFootprints, MemoryHeapType, MipInfo, SwapchainDesc, TextureCopyableFootprints,
     },
-    views::ViewAllocator,
-    BindingType, Graphics, GraphicsPipelineDesc, Pipeline, PipelineLayout, Pixel, ResourceStates,
-    Sampler, SamplerDesc, Shader, ShaderType, StaticSampler, Vertex,
+    views::{GpuView, ViewAllocator, ViewType},
+    BindingType, ComputePipelineDesc, Graphics, GraphicsPipelineDesc, Pipeline, PipelineError,
+    PipelineLayout, PipelineLayoutError, Pixel, ReloadError, ResourceStates, Sampler, SamplerDesc,
+    Shader, ShaderBindings, ShaderError, ShaderOptions, ShaderRegistry, ShaderType, StaticSampler,
+    Vertex,
 };
 
+const FEATURE_LEVELS: [dx::FeatureLevel; 5] = [
+    dx::FeatureLevel::Level11,
+    dx::FeatureLevel::Level11_1,
+    dx::FeatureLevel::Level12,
+    dx::FeatureLevel::Level12_1,
+    dx::FeatureLevel::Level12_2,
+];
+
+const SHADER_MODELS: [dx::ShaderModel; 8] = [
+    dx::ShaderModel::SM6_7,
+    dx::ShaderModel::SM6_6,
+    dx::ShaderModel::SM6_5,
+    dx::ShaderModel::SM6_4,
+    dx::ShaderModel::SM6_3,
+    dx::ShaderModel::SM6_2,
+    dx::ShaderModel::SM6_1,
+    dx::ShaderModel::SM6_0,
+];
+
 #[derive(Clone, Debug)]
 pub struct Device(Arc<DeviceInner>);
 
 impl Device {
     pub fn new(factory: dx::Factory4, adapter: dx::Adapter3) -> Self {
-        let name = adapter.get_desc1().unwrap().description().to_string();
-
         let raw: dx::Device = dx::create_device(Some(&adapter), dx::FeatureLevel::Level11).unwrap();
-
-        let mut feature = dx::features::OptionsFeature::default();
-        raw.check_feature_support(&mut feature).unwrap();
+        let capabilities = Self::query_capabilities(&adapter.get_desc1().unwrap(), &raw);
 
         Self(Arc::new(DeviceInner {
-            name,
             factory,
             adapter,
             raw,
-            is_cross_adapter_texture_supported: feature.cross_adapter_row_major_texture_supported(),
+            memory: Default::default(),
+            deferred: Default::default(),
+            queue_fences: Default::default(),
+            shaders: Default::default(),
+            cache: Default::default(),
+            capabilities,
         }))
     }
+
+    pub(super) fn query_capabilities(
+        desc: &dx::AdapterDesc1,
+        raw: &dx::Device,
+    ) -> DeviceCapabilities {
+        let luid = desc.adapter_luid();
+
+        let mut options = dx::features::OptionsFeature::default();
+        if raw.check_feature_support(&mut options).is_err() {
+            options = Default::default();
+        }
+
+        let mut options3 = dx::features::Options3Feature::default();
+        if raw.check_feature_support(&mut options3).is_err() {
+            options3 = Default::default();
+        }
+
+        let mut architecture = dx::features::ArchitectureFeature::new(0);
+        if raw.check_feature_support(&mut architecture).is_err() {
+            architecture = dx::features::ArchitectureFeature::new(0);
+        }
+
+        // Older runtimes reject the whole query if any level in the list is unknown to them.
+        let feature_level = (1..=FEATURE_LEVELS.len())
+            .rev()
+            .find_map(|count| {
+                let mut levels = dx::features::FeatureLevelsFeature::new(&FEATURE_LEVELS[..count]);
+                raw.check_feature_support(&mut levels)
+                    .ok()
+                    .map(|_| FeatureLevel::from_raw(levels.max_supported_feature_level() as u32))
+            })
+            .unwrap_or(FeatureLevel::new(11, 0));
+
+        let shader_model = SHADER_MODELS
+            .iter()
+            .find_map(|&model| {
+                let mut shader_model = dx::features::ShaderModelFeature::new(model);
+                raw.check_feature_support(&mut shader_model)
+                    .ok()
+                    .map(|_| ShaderModel::from_raw(shader_model.highest_shader_model() as u32))
+            })
+            .unwrap_or(ShaderModel::new(5, 1));
+
+        let mut root_signature =
+            dx::features::RootSignatureFeature::new(dx::RootSignatureVersion::V1_1);
+        let root_signature_1_1 = raw.check_feature_support(&mut root_signature).is_ok()
+            && root_signature.highest_version() == dx::RootSignatureVersion::V1_1;
+
+        DeviceCapabilities {
+            adapter: desc.description().to_string(),
+            luid: AdapterLuid {
+                low: luid.low_part(),
+                high: luid.high_part(),
+            },
+            feature_level,
+            shader_model,
+            root_signature_1_1,
+            resource_binding_tier: options.resource_binding_tier() as u8,
+            resource_heap_tier: options.resource_heap_tier() as u8,
+            cross_node_sharing_tier: CrossNodeSharingTier::from_raw(
+                options.cross_node_sharing_tier() as u32,
+            ),
+            cross_adapter_row_major_texture: options.cross_adapter_row_major_texture_supported(),
+            copy_queue_timestamps: options3.copy_queue_timestamp_queries_supported(),
+            uma: architecture.uma(),
+            cache_coherent_uma: architecture.cache_coherent_uma(),
+            dedicated_video_memory: desc.dedicated_video_memory(),
+        }
+    }
 }
 
 impl Deref for Device {
@@ -53,20 +154,144 @@ impl Deref for Device {
     }
 }
 
+impl PartialEq for Device {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Device {}
+
 #[derive(Debug)]
 pub struct DeviceInner {
-    name: String,
     pub(super) factory: dx::Factory4,
     adapter: dx::Adapter3,
     pub(super) raw: dx::Device,
+    pub(super) memory: Arc<MemoryTracker>,
+    deferred: Mutex<DeferredQueue<DeferredRelease>>,
+    queue_fences: Mutex<Vec<Fence>>,
+    shaders: Mutex<Option<ShaderRegistry>>,
+    cache: RwLock<Option<Arc<DiskCache>>>,
 
-    is_cross_adapter_texture_supported: bool,
+    capabilities: DeviceCapabilities,
 }
 
 impl Device {
     pub(super) fn create_command_allocator<T: WorkerType>(&self) -> CommandAllocator<T> {
         CommandAllocator::inner_new(&self.raw, T::RAW_TYPE)
     }
+
+    pub(super) fn track_resource(
+        &self,
+        desc: &dx::ResourceDesc,
+        mtype: MemoryHeapType,
+        kind: MemoryKind,
+    ) -> MemoryTrack {
+        let info = self
+            .raw
+            .get_resource_allocation_info(0, std::slice::from_ref(desc));
+
+        self.memory
+            .track(mtype, kind, info.size_in_bytes() as usize)
+    }
+
+    pub(super) fn register_queue_fence(&self, fence: &Fence) {
+        self.queue_fences.lock().push(fence.observer());
+    }
+}
+
+impl Device {
+    pub fn release_after<R: Any + Send + Sync>(
+        &self,
+        object: R,
+        signals: impl IntoIterator<Item = FenceSignal>,
+    ) {
+        let released = self.deferred.lock().push(Box::new(object), signals);
+
+        drop(released);
+    }
+
+    pub fn release<R: Any + Send + Sync>(&self, object: R) {
+        let signals = self
+            .queue_fences
+            .lock()
+            .iter()
+            .map(|fence| FenceSignal::new(fence.clone(), fence.get_current_value()))
+            .collect::<Vec<_>>();
+
+        self.release_after(object, signals);
+    }
+
+    pub fn release_view<V: ViewType + Send + Sync + 'static>(
+        &self,
+        allocator: &ViewAllocator,
+        view: GpuView<V>,
+    ) {
+        self.release(DeferredView::new(allocator.clone(), view));
+    }
+
+    pub fn collect_garbage(&self) -> usize {
+        let completed = self.deferred.lock().take_completed();
+
+        completed.len()
+    }
+
+    pub fn pending_releases(&self) -> usize {
+        self.deferred.lock().len()
+    }
+}
+
+impl Device {
+    pub fn name(&self) -> &str {
+        &self.capabilities.adapter
+    }
+
+    pub fn capabilities(&self) -> &DeviceCapabilities {
+        &self.capabilities
+    }
+
+    pub fn memory_usage(&self, mtype: MemoryHeapType) -> MemoryUsage {
+        self.memory.usage(mtype)
+    }
+
+    pub fn memory_report(&self) -> MemoryReport {
+        self.memory.report()
+    }
+
+    pub fn set_memory_budget(&self, mtype: MemoryHeapType, budget: Option<MemoryBudget>) {
+        self.memory.set_budget(mtype, budget);
+    }
+
+    pub fn query_video_memory_info(&self, segment: MemorySegment) -> VideoMemoryInfo {
+        let group = match segment {
+            MemorySegment::Local => dx::MemorySegmentGroup::Local,
+            MemorySegment::NonLocal => dx::MemorySegmentGroup::NonLocal,
+        };
+
+        let info = self.adapter.query_video_memory_info(0, group).unwrap();
+
+        VideoMemoryInfo {
+            budget: info.budget() as usize,
+            current_usage: info.current_usage() as usize,
+            available_for_reservation: info.available_for_reservation() as usize,
+            current_reservation: info.current_reservation() as usize,
+        }
+    }
+
+    pub fn set_segment_budget(&self, segment: MemorySegment, budget: Option<MemoryBudget>) {
+        self.memory.set_segment_budget(segment, budget);
+    }
+
+    pub fn update_memory_budget(&self, thresholds: &[f32]) {
+        for segment in [MemorySegment::Local, MemorySegment::NonLocal] {
+            let info = self.query_video_memory_info(segment);
+
+            self.memory.set_segment_budget(
+                segment,
+                Some(MemoryBudget::new(info.budget).with_thresholds(thresholds)),
+            );
+        }
+    }
 }
 
 impl Device {
@@ -82,6 +307,15 @@ impl Device {
         CommandQueue::inner_new(self.clone(), fence)
     }
 
+    pub fn create_frame_contexts<T: WorkerType>(
+        &self,
+        queue: &CommandQueue<T>,
+        frames_in_flight: usize,
+        upload_size: usize,
+    ) -> FrameContexts<T> {
+        FrameContexts::inner_new(self, queue.clone(), frames_in_flight, upload_size)
+    }
+
     pub fn create_descriptor_allocator(
         &self,
         rtv_size: usize,
@@ -117,46 +351,88 @@ impl Device {
         &self,
         heap: &MemoryHeap,
         desc: R::Desc,
-        offset: usize,
         access: R::Access,
         initial_state: ResourceStates,
-    ) -> R {
-        heap.create_placed_buffer(desc, offset, access, initial_state)
+    ) -> Option<R> {
+        heap.create_placed_buffer(desc, access, initial_state)
     }
 
     pub fn create_placed_image<R: ImageResource>(
         &self,
         heap: &MemoryHeap,
         desc: R::Desc,
-        offset: usize,
         access: R::Access,
         initial_state: ResourceStates,
-    ) -> R {
-        heap.create_placed_texture(desc, offset, access, initial_state)
+    ) -> Option<R> {
+        heap.create_placed_texture(desc, access, initial_state)
+    }
+
+    pub fn create_transient_pool(
+        &self,
+        descs: &[TransientImageDesc],
+        access: ViewAccess,
+    ) -> TransientPool {
+        TransientPool::inner_new(self, descs, access)
     }
 
     pub fn create_shared_buffer<R: ShareableBuffer>(
         &self,
         heap: &MemoryHeap,
-        offset: usize,
         desc: R::Desc,
         access: R::Access,
         local_state: ResourceStates,
         share_state: ResourceStates,
-    ) -> SharedResource<R> {
-        SharedResource::inner_new_buffer(heap, offset, desc, access, local_state, share_state)
+    ) -> Option<SharedResource<R>> {
+        SharedResource::inner_new_buffer(heap, desc, access, local_state, share_state)
     }
 
     pub fn create_shared_image<R: ShareableImage>(
         &self,
         heap: &MemoryHeap,
-        offset: usize,
         desc: R::Desc,
         access: R::Access,
         local_state: ResourceStates,
         share_state: ResourceStates,
-    ) -> SharedResource<R> {
-        SharedResource::inner_new_image(heap, offset, desc, access, local_state, share_state)
+    ) -> Option<SharedResource<R>> {
+        SharedResource::inner_new_image(heap, desc, access, local_state, share_state)
+    }
+
+    pub fn create_shared_buffer_ring<R: ShareableBuffer>(
+        &self,
+        producer: (&MemoryHeap, R::Access),
+        consumer: (&MemoryHeap, R::Access),
+        count: usize,
+        desc: R::Desc,
+        local_state: ResourceStates,
+        share_state: ResourceStates,
+    ) -> Option<SharedResourceRing<R>> {
+        SharedResourceRing::inner_new_buffers(
+            producer,
+            consumer,
+            count,
+            desc,
+            local_state,
+            share_state,
+        )
+    }
+
+    pub fn create_shared_image_ring<R: ShareableImage>(
+        &self,
+        producer: (&MemoryHeap, R::Access),
+        consumer: (&MemoryHeap, R::Access),
+        count: usize,
+        desc: R::Desc,
+        local_state: ResourceStates,
+        share_state: ResourceStates,
+    ) -> Option<SharedResourceRing<R>> {
+        SharedResourceRing::inner_new_images(
+            producer,
+            consumer,
+            count,
+            desc,
+            local_state,
+            share_state,
+        )
     }
 
     pub fn create_swapchain(
@@ -174,19 +450,35 @@ impl Device {
     }
 
     pub fn is_cross_adapter_texture_supported(&self) -> bool {
-        self.is_cross_adapter_texture_supported
+        self.capabilities.cross_adapter_row_major_texture
     }
 
     pub fn create_pipeline_layout(
         &self,
         layout: &[BindingType],
         static_samplers: &[StaticSampler],
-    ) -> PipelineLayout {
+    ) -> Result<PipelineLayout, PipelineLayoutError> {
         PipelineLayout::inner_new(self, layout, static_samplers)
     }
 
+    pub fn create_reflected_pipeline_layout(
+        &self,
+        stages: &[ShaderBindings],
+        static_samplers: &[StaticSampler],
+    ) -> Result<PipelineLayout, PipelineLayoutError> {
+        PipelineLayout::inner_new_reflected(self, stages, static_samplers)
+    }
+
     pub fn create_sampler(&self, allocator: ViewAllocator, desc: &SamplerDesc) -> Sampler {
-        Sampler::inner_new(allocator, desc)
+        Sampler::inner_new(self, allocator, desc)
+    }
+
+    pub fn set_disk_cache(&self, cache: Option<DiskCache>) {
+        *self.cache.write() = cache.map(Arc::new);
+    }
+
+    pub fn disk_cache(&self) -> Option<Arc<DiskCache>> {
+        self.cache.read().clone()
     }
 
     pub fn create_shader<T: ShaderType>(
@@ -195,11 +487,87 @@ impl Device {
         entry_point: impl AsRef<str>,
         defines: &[(&'static str, &'static str)],
     ) -> Shader<T> {
-        Shader::inner_new(path, entry_point, defines)
+        let options = ShaderOptions::legacy().with_defines(defines);
+
+        self.compile_shader(path, entry_point, &options)
+            .unwrap_or_else(|err| panic!("Shader: {}", err))
     }
 
-    pub fn create_graphics_pipeline(&self, desc: &GraphicsPipelineDesc) -> Pipeline<Graphics> {
-        Pipeline::inner_new_graphics(self, desc)
+    pub fn compile_shader<T: ShaderType>(
+        &self,
+        path: impl AsRef<Path>,
+        entry_point: impl AsRef<str>,
+        options: &ShaderOptions,
+    ) -> Result<Shader<T>, ShaderError> {
+        let path = path.as_ref();
+        let entry_point = entry_point.as_ref();
+
+        let registered = self
+            .shaders
+            .lock()
+            .as_ref()
+            .and_then(|shaders| shaders.find_shader(path, entry_point, options));
+
+        if let Some(shader) = registered {
+            return Ok(shader);
+        }
+
+        let shader = Shader::compile(path, entry_point, options, self.disk_cache().as_deref())?;
+
+        Ok(match self.shaders.lock().as_mut() {
+            Some(shaders) => shaders.register_shader(path, entry_point, options, shader),
+            None => shader,
+        })
+    }
+
+    pub fn create_graphics_pipeline(
+        &self,
+        desc: &GraphicsPipelineDesc,
+    ) -> Result<Pipeline<Graphics>, PipelineError> {
+        let pipeline = Pipeline::inner_new_graphics(self, desc)?;
+
+        if let Some(shaders) = self.shaders.lock().as_mut() {
+            shaders.register_graphics(desc, &pipeline);
+        }
+
+        Ok(pipeline)
+    }
+
+    pub fn create_compute_pipeline(
+        &self,
+        desc: &ComputePipelineDesc,
+    ) -> Result<Pipeline<Compute>, PipelineError> {
+        let pipeline = Pipeline::inner_new_compute(self, desc)?;
+
+        if let Some(shaders) = self.shaders.lock().as_mut() {
+            shaders.register_compute(desc, &pipeline);
+        }
+
+        Ok(pipeline)
+    }
+
+    pub fn set_shader_hot_reload(&self, enabled: bool) {
+        let mut shaders = self.shaders.lock();
+
+        if enabled {
+            shaders.get_or_insert_with(Default::default);
+        } else {
+            *shaders = None;
+        }
+    }
+
+    pub fn poll_shaders(&self) -> Vec<ReloadError> {
+        self.shaders
+            .lock()
+            .as_mut()
+            .map_or_else(Vec::new, |shaders| shaders.poll(self))
+    }
+
+    pub fn apply_shader_reloads(&self) -> usize {
+        self.shaders
+            .lock()
+            .as_mut()
+            .map_or(0, |shaders| shaders.apply(self))
     }
 
     pub fn create_vertex_shader(
@@ -208,7 +576,10 @@ impl Device {
         entry_point: impl AsRef<str>,
         defines: &[(&'static str, &'static str)],
     ) -> Shader<Vertex> {
-        Shader::inner_new(path, entry_point, defines)
+        let options = ShaderOptions::legacy().with_defines(defines);
+
+        self.compile_shader(path, entry_point, &options)
+            .unwrap_or_else(|err| panic!("Shader: {}", err))
     }
 
     pub fn create_pixel_shader(
@@ -217,7 +588,22 @@ impl Device {
         entry_point: impl AsRef<str>,
         defines: &[(&'static str, &'static str)],
     ) -> Shader<Pixel> {
-        Shader::inner_new(path, entry_point, defines)
+        let options = ShaderOptions::legacy().with_defines(defines);
+
+        self.compile_shader(path, entry_point, &options)
+            .unwrap_or_else(|err| panic!("Shader: {}", err))
+    }
+
+    pub fn create_compute_shader(
+        &self,
+        path: impl AsRef<Path>,
+        entry_point: impl AsRef<str>,
+        defines: &[(&'static str, &'static str)],
+    ) -> Shader<Compute> {
+        let options = ShaderOptions::legacy().with_defines(defines);
+
+        self.compile_shader(path, entry_point, &options)
+            .unwrap_or_else(|err| panic!("Shader: {}", err))
     }
 
     pub fn get_buffer_copyable_footprints<T: BufferResourceDesc>(
@@ -1,8 +1,11 @@
-use std::sync::{atomic::AtomicU64, Arc};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 use oxidx::dx::{self, IDevice, IFence};
 
-use super::device::Device;
+use super::{device::Device, fence_wait::FenceValue};
 
 #[derive(Debug, Clone)]
 pub enum Fence {
@@ -45,6 +48,46 @@ impl Fence {
             Fence::Shared(fence) => fence.get_raw(),
         }
     }
+
+    pub fn get_signaled_value(&self) -> u64 {
+        match self {
+            Fence::Local(fence) => fence.get_current_value(),
+            Fence::Shared(fence) => fence.get_signaled_value(),
+        }
+    }
+
+    pub(super) fn mark_signaled(&self, value: u64) {
+        if let Fence::Shared(fence) = self {
+            fence.mark_signaled(value);
+        }
+    }
+
+    pub(super) fn debug_validate_wait(&self, value: u64) {
+        debug_assert!(
+            value <= self.get_signaled_value(),
+            "Fence: waiting on value {} that was never signaled (last signaled {})",
+            value,
+            self.get_signaled_value()
+        );
+    }
+
+    fn value(&self) -> &Arc<AtomicU64> {
+        match self {
+            Fence::Local(fence) => &fence.value,
+            Fence::Shared(fence) => &fence.value,
+        }
+    }
+
+    pub(super) fn timeline_id(&self) -> usize {
+        Arc::as_ptr(self.value()) as usize
+    }
+
+    pub(super) fn observer(&self) -> Fence {
+        Fence::Local(LocalFence {
+            raw: self.get_raw().clone(),
+            value: Arc::clone(self.value()),
+        })
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -97,6 +140,7 @@ pub struct SharedFence {
     owner: Device,
     fence: dx::Fence,
     value: Arc<AtomicU64>,
+    signaled: Arc<AtomicU64>,
 }
 
 impl SharedFence {
@@ -113,9 +157,14 @@ impl SharedFence {
             owner,
             fence,
             value: Default::default(),
+            signaled: Default::default(),
         }
     }
 
+    pub fn owner(&self) -> &Device {
+        &self.owner
+    }
+
     pub(super) fn get_raw(&self) -> &dx::Fence {
         &self.fence
     }
@@ -137,6 +186,40 @@ impl SharedFence {
     pub fn get_current_value(&self) -> u64 {
         self.value.load(std::sync::atomic::Ordering::Relaxed)
     }
+
+    pub fn get_signaled_value(&self) -> u64 {
+        self.signaled.load(Ordering::Acquire)
+    }
+
+    pub(super) fn mark_signaled(&self, value: u64) {
+        self.signaled.fetch_max(value, Ordering::Release);
+    }
+
+    pub(super) fn signal_handle(&self, value: u64) -> SharedSignal {
+        self.mark_signaled(value);
+
+        SharedSignal {
+            producer: self.owner.clone(),
+            value: FenceValue::new(Arc::as_ptr(&self.value) as usize, value),
+        }
+    }
+
+    pub(super) fn validate_wait(&self, signal: &SharedSignal) -> u64 {
+        assert_eq!(
+            signal.value.timeline(),
+            Arc::as_ptr(&self.value) as usize,
+            "SharedFence: signal was produced by a different fence"
+        );
+
+        debug_assert!(
+            signal.value.value() <= self.get_signaled_value(),
+            "SharedFence: waiting on value {} that was never signaled (last signaled {})",
+            signal.value.value(),
+            self.get_signaled_value()
+        );
+
+        signal.value.value()
+    }
 }
 
 impl SharedFence {
@@ -153,6 +236,7 @@ impl SharedFence {
             owner: device,
             fence,
             value: Arc::clone(&self.value),
+            signaled: Arc::clone(&self.signaled),
         }
     }
 }
@@ -162,3 +246,23 @@ impl From<SharedFence> for Fence {
         Fence::Shared(value)
     }
 }
+
+#[derive(Clone, Debug)]
+pub struct SharedSignal {
+    producer: Device,
+    value: FenceValue,
+}
+
+impl SharedSignal {
+    pub fn producer(&self) -> &Device {
+        &self.producer
+    }
+
+    pub fn value(&self) -> FenceValue {
+        self.value
+    }
+
+    pub fn is_complete(&self, fence: &SharedFence) -> bool {
+        fence.get_completed_value() >= fence.validate_wait(self)
+    }
+}
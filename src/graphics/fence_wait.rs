@@ -0,0 +1,565 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc, OnceLock,
+    },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use oxidx::dx;
+use parking_lot::{Condvar, Mutex, MutexGuard};
+
+use super::{deferred::CompletedValue, fence::Fence};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FenceValue {
+    timeline: usize,
+    value: u64,
+}
+
+impl FenceValue {
+    pub(crate) fn new(timeline: usize, value: u64) -> Self {
+        Self { timeline, value }
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn timeline(&self) -> usize {
+        self.timeline
+    }
+
+    pub fn is_same_timeline(&self, other: &FenceValue) -> bool {
+        self.timeline == other.timeline
+    }
+}
+
+pub(crate) fn token<F: TimelineFence>(fence: &F, value: u64) -> FenceValue {
+    FenceValue::new(fence.timeline(), value)
+}
+
+impl PartialOrd for FenceValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.is_same_timeline(other)
+            .then(|| self.value.cmp(&other.value))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenceError {
+    Timeout { pending: usize, elapsed: Duration },
+    WrongTimeline { expected: usize, found: usize },
+    NoFences,
+}
+
+impl fmt::Display for FenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenceError::Timeout { pending, elapsed } => write!(
+                f,
+                "fence wait timed out after {:?} with {} pending fence(s)",
+                elapsed, pending
+            ),
+            FenceError::WrongTimeline { expected, found } => write!(
+                f,
+                "fence value from timeline {:#x} used with timeline {:#x}",
+                found, expected
+            ),
+            FenceError::NoFences => write!(f, "no fences to wait on"),
+        }
+    }
+}
+
+impl std::error::Error for FenceError {}
+
+pub trait TimelineFence: CompletedValue + Clone + Send + Sync + 'static {
+    fn timeline(&self) -> usize;
+
+    fn block(waits: &[(&Self, u64)], timeout: Option<Duration>);
+
+    fn check(&self, value: FenceValue) -> Result<u64, FenceError> {
+        if value.timeline == self.timeline() {
+            Ok(value.value)
+        } else {
+            Err(FenceError::WrongTimeline {
+                expected: self.timeline(),
+                found: value.timeline,
+            })
+        }
+    }
+
+    fn is_complete(&self, value: FenceValue) -> Result<bool, FenceError> {
+        Ok(self.completed_value() >= self.check(value)?)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WaitMode {
+    Any,
+    All,
+}
+
+fn wait_fences<F: TimelineFence>(
+    waits: &[(&F, FenceValue)],
+    mode: WaitMode,
+    timeout: Option<Duration>,
+) -> Result<usize, FenceError> {
+    let raw = waits
+        .iter()
+        .map(|(fence, value)| fence.check(*value).map(|v| (*fence, v)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let start = Instant::now();
+
+    loop {
+        let pending = raw
+            .iter()
+            .copied()
+            .filter(|(fence, value)| fence.completed_value() < *value)
+            .collect::<Vec<_>>();
+
+        match mode {
+            WaitMode::Any => {
+                if let Some(index) = raw
+                    .iter()
+                    .position(|(fence, value)| fence.completed_value() >= *value)
+                {
+                    return Ok(index);
+                }
+            }
+            WaitMode::All => {
+                if pending.is_empty() {
+                    return Ok(raw.len());
+                }
+            }
+        }
+
+        if pending.is_empty() {
+            return Ok(raw.len());
+        }
+
+        let elapsed = start.elapsed();
+        let remaining = match timeout {
+            Some(timeout) if elapsed >= timeout => {
+                return Err(FenceError::Timeout {
+                    pending: pending.len(),
+                    elapsed,
+                })
+            }
+            Some(timeout) => Some(timeout - elapsed),
+            None => None,
+        };
+
+        F::block(&pending, remaining);
+    }
+}
+
+pub fn wait_all<F: TimelineFence>(
+    waits: &[(&F, FenceValue)],
+    timeout: Option<Duration>,
+) -> Result<(), FenceError> {
+    wait_fences(waits, WaitMode::All, timeout).map(|_| ())
+}
+
+pub fn wait_any<F: TimelineFence>(
+    waits: &[(&F, FenceValue)],
+    timeout: Option<Duration>,
+) -> Result<usize, FenceError> {
+    if waits.is_empty() {
+        return Err(FenceError::NoFences);
+    }
+
+    wait_fences(waits, WaitMode::Any, timeout)
+}
+
+pub fn wait_one<F: TimelineFence>(
+    fence: &F,
+    value: FenceValue,
+    timeout: Option<Duration>,
+) -> Result<(), FenceError> {
+    wait_all(&[(fence, value)], timeout)
+}
+
+#[derive(Debug)]
+struct CachedEvent(dx::Event);
+
+impl Drop for CachedEvent {
+    fn drop(&mut self) {
+        self.0.close().unwrap();
+    }
+}
+
+thread_local! {
+    static WAIT_EVENT: CachedEvent = CachedEvent(dx::Event::create(false, false).unwrap());
+}
+
+fn timeout_ms(timeout: Option<Duration>) -> u32 {
+    timeout
+        .map(|t| t.as_micros().div_ceil(1000).min(u32::MAX as u128 - 1) as u32)
+        .unwrap_or(u32::MAX)
+}
+
+impl TimelineFence for Fence {
+    fn timeline(&self) -> usize {
+        self.timeline_id()
+    }
+
+    fn block(waits: &[(&Self, u64)], timeout: Option<Duration>) {
+        WAIT_EVENT.with(|event| {
+            for (fence, value) in waits {
+                fence.set_event_on_completion(*value, event.0);
+            }
+
+            event.0.wait(timeout_ms(timeout));
+        });
+    }
+}
+
+impl Fence {
+    pub fn current(&self) -> FenceValue {
+        token(self, self.get_current_value())
+    }
+
+    pub fn completed(&self) -> FenceValue {
+        token(self, self.get_completed_value())
+    }
+
+    pub fn wait(&self, value: FenceValue, timeout: Option<Duration>) -> Result<(), FenceError> {
+        wait_one(self, value, timeout)
+    }
+
+    pub fn wait_async(&self, value: FenceValue) -> Result<FenceFuture<Fence>, FenceError> {
+        FenceFuture::new(self.clone(), value)
+    }
+}
+
+const WAITER_POLL: Duration = Duration::from_millis(1);
+
+trait PendingWait: Send + Sync {
+    fn resolve(&self) -> bool;
+
+    fn block(&self, timeout: Duration);
+}
+
+#[derive(Debug)]
+struct Registration<F: TimelineFence> {
+    fence: F,
+    value: u64,
+    waker: Mutex<Option<Waker>>,
+    cancelled: AtomicBool,
+}
+
+impl<F: TimelineFence> Registration<F> {
+    fn is_complete(&self) -> bool {
+        self.fence.completed_value() >= self.value
+    }
+}
+
+impl<F: TimelineFence> PendingWait for Registration<F> {
+    fn resolve(&self) -> bool {
+        if self.cancelled.load(AtomicOrdering::Acquire) {
+            return true;
+        }
+
+        if !self.is_complete() {
+            return false;
+        }
+
+        if let Some(waker) = self.waker.lock().take() {
+            waker.wake();
+        }
+
+        true
+    }
+
+    fn block(&self, timeout: Duration) {
+        F::block(&[(&self.fence, self.value)], Some(timeout));
+    }
+}
+
+#[derive(Default)]
+struct FenceWaiter {
+    pending: Mutex<Vec<Arc<dyn PendingWait>>>,
+    added: Condvar,
+}
+
+impl FenceWaiter {
+    fn get() -> &'static FenceWaiter {
+        static WAITER: OnceLock<FenceWaiter> = OnceLock::new();
+
+        WAITER.get_or_init(|| {
+            std::thread::Builder::new()
+                .name("fence-waiter".to_string())
+                .spawn(|| FenceWaiter::get().run())
+                .expect("FenceWaiter: failed to spawn waiter thread");
+
+            FenceWaiter::default()
+        })
+    }
+
+    fn register(&self, wait: Arc<dyn PendingWait>) {
+        self.pending.lock().push(wait);
+        self.added.notify_one();
+    }
+
+    fn run(&self) {
+        let mut pending = self.pending.lock();
+
+        loop {
+            pending.retain(|wait| !wait.resolve());
+
+            match pending.first().cloned() {
+                Some(first) => MutexGuard::unlocked(&mut pending, || first.block(WAITER_POLL)),
+                None => self.added.wait(&mut pending),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FenceFuture<F: TimelineFence> {
+    wait: Arc<Registration<F>>,
+    registered: bool,
+}
+
+impl<F: TimelineFence> FenceFuture<F> {
+    pub fn new(fence: F, value: FenceValue) -> Result<Self, FenceError> {
+        let value = fence.check(value)?;
+
+        Ok(Self {
+            wait: Arc::new(Registration {
+                fence,
+                value,
+                waker: Default::default(),
+                cancelled: AtomicBool::new(false),
+            }),
+            registered: false,
+        })
+    }
+}
+
+impl<F: TimelineFence> Future for FenceFuture<F> {
+    type Output = FenceValue;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if this.wait.is_complete() {
+            return Poll::Ready(token(&this.wait.fence, this.wait.value));
+        }
+
+        *this.wait.waker.lock() = Some(cx.waker().clone());
+
+        if !this.registered {
+            this.registered = true;
+            FenceWaiter::get().register(this.wait.clone());
+        }
+
+        if this.wait.is_complete() {
+            return Poll::Ready(token(&this.wait.fence, this.wait.value));
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<F: TimelineFence> Drop for FenceFuture<F> {
+    fn drop(&mut self) {
+        self.wait.cancelled.store(true, AtomicOrdering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        task::{Context, Poll, Wake},
+        thread::Thread,
+        time::{Duration, Instant},
+    };
+
+    use super::{token, wait_all, wait_any, wait_one, FenceError, FenceFuture, TimelineFence};
+    use crate::graphics::deferred::CompletedValue;
+
+    #[derive(Clone, Debug, Default)]
+    struct FakeFence(Arc<AtomicU64>);
+
+    impl FakeFence {
+        fn complete(&self, value: u64) {
+            self.0.store(value, Ordering::Release);
+        }
+    }
+
+    impl CompletedValue for FakeFence {
+        fn completed_value(&self) -> u64 {
+            self.0.load(Ordering::Acquire)
+        }
+    }
+
+    impl TimelineFence for FakeFence {
+        fn timeline(&self) -> usize {
+            Arc::as_ptr(&self.0) as usize
+        }
+
+        fn block(_waits: &[(&Self, u64)], timeout: Option<Duration>) {
+            std::thread::sleep(
+                timeout
+                    .unwrap_or(Duration::MAX)
+                    .min(Duration::from_millis(1)),
+            );
+        }
+    }
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            std::thread::park();
+        }
+    }
+
+    #[test]
+    fn test_tokens_compare_within_timeline() {
+        let first = FakeFence::default();
+        let second = FakeFence::default();
+
+        assert!(token(&first, 1) < token(&first, 2));
+        assert_eq!(token(&first, 1).partial_cmp(&token(&second, 2)), None);
+        assert!(matches!(
+            second.check(token(&first, 1)),
+            Err(FenceError::WrongTimeline { .. })
+        ));
+    }
+
+    #[test]
+    fn test_wait_times_out() {
+        let fence = FakeFence::default();
+
+        let result = wait_one(&fence, token(&fence, 1), Some(Duration::from_millis(5)));
+
+        assert!(matches!(
+            result,
+            Err(FenceError::Timeout { pending: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_wait_any_returns_completed_index() {
+        let first = FakeFence::default();
+        let second = FakeFence::default();
+        second.complete(3);
+
+        let index = wait_any(
+            &[(&first, token(&first, 1)), (&second, token(&second, 3))],
+            Some(Duration::from_millis(5)),
+        );
+
+        assert_eq!(index, Ok(1));
+    }
+
+    #[test]
+    fn test_wait_all_waits_for_every_fence() {
+        let first = FakeFence::default();
+        let second = FakeFence::default();
+        second.complete(3);
+
+        let result = wait_all(
+            &[(&first, token(&first, 1)), (&second, token(&second, 3))],
+            Some(Duration::from_millis(5)),
+        );
+        assert!(matches!(
+            result,
+            Err(FenceError::Timeout { pending: 1, .. })
+        ));
+
+        let signaler = first.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(2));
+            signaler.complete(1);
+        });
+
+        let result = wait_all(
+            &[(&first, token(&first, 1)), (&second, token(&second, 3))],
+            Some(Duration::from_secs(5)),
+        );
+        handle.join().unwrap();
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_future_resolves_after_signal() {
+        let fence = FakeFence::default();
+        let future = FenceFuture::new(fence.clone(), token(&fence, 2)).unwrap();
+
+        let signaler = fence.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(2));
+            signaler.complete(2);
+        });
+
+        assert_eq!(block_on(future), token(&fence, 2));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_wait_any_rejects_empty_waits() {
+        let result = wait_any::<FakeFence>(&[], Some(Duration::from_millis(5)));
+
+        assert_eq!(result, Err(FenceError::NoFences));
+    }
+
+    #[test]
+    fn test_future_rejects_wrong_timeline() {
+        let first = FakeFence::default();
+        let second = FakeFence::default();
+
+        assert!(matches!(
+            FenceFuture::new(second, token(&first, 1)),
+            Err(FenceError::WrongTimeline { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dropped_future_is_unregistered() {
+        let fence = FakeFence::default();
+        let mut future = Box::pin(FenceFuture::new(fence.clone(), token(&fence, 1)).unwrap());
+
+        let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+
+        let wait = Arc::downgrade(&future.wait);
+        drop(future);
+
+        let start = Instant::now();
+        while wait.strong_count() > 0 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
@@ -0,0 +1,587 @@
+use smallvec::SmallVec;
+
+use crate::graphics::ResourceStates;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueueType {
+    Direct,
+    Compute,
+    Transfer,
+}
+
+impl QueueType {
+    pub fn supports(&self, state: ResourceStates) -> bool {
+        match self {
+            QueueType::Direct => true,
+            QueueType::Compute => !state.intersects(
+                ResourceStates::RenderTarget
+                    | ResourceStates::DepthWrite
+                    | ResourceStates::DepthRead
+                    | ResourceStates::PixelShaderResource
+                    | ResourceStates::IndexBuffer,
+            ),
+            QueueType::Transfer => {
+                (ResourceStates::CopySrc | ResourceStates::CopyDst).contains(state)
+            }
+        }
+    }
+
+    pub(crate) fn index(&self) -> usize {
+        match self {
+            QueueType::Direct => 0,
+            QueueType::Compute => 1,
+            QueueType::Transfer => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphResource(pub(crate) usize);
+
+#[derive(Clone, Debug)]
+pub struct ResourceNode {
+    pub name: String,
+    pub initial_state: ResourceStates,
+    pub final_state: Option<ResourceStates>,
+    pub output: bool,
+}
+
+impl ResourceNode {
+    pub fn new(name: impl Into<String>, initial_state: ResourceStates) -> Self {
+        Self {
+            name: name.into(),
+            initial_state,
+            final_state: None,
+            output: false,
+        }
+    }
+
+    pub fn as_output(mut self, final_state: Option<ResourceStates>) -> Self {
+        self.output = true;
+        self.final_state = final_state;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PassNode {
+    pub name: String,
+    pub queue: QueueType,
+    pub reads: SmallVec<[(GraphResource, ResourceStates); 4]>,
+    pub writes: SmallVec<[(GraphResource, ResourceStates); 4]>,
+    pub side_effects: bool,
+}
+
+impl PassNode {
+    pub fn new(name: impl Into<String>, queue: QueueType) -> Self {
+        Self {
+            name: name.into(),
+            queue,
+            reads: Default::default(),
+            writes: Default::default(),
+            side_effects: false,
+        }
+    }
+
+    pub fn read(mut self, resource: GraphResource, state: ResourceStates) -> Self {
+        self.reads.push((resource, state));
+        self
+    }
+
+    pub fn write(mut self, resource: GraphResource, state: ResourceStates) -> Self {
+        self.writes.push((resource, state));
+        self
+    }
+
+    pub fn with_side_effects(mut self) -> Self {
+        self.side_effects = true;
+        self
+    }
+
+    fn states(&self) -> SmallVec<[(GraphResource, ResourceStates); 4]> {
+        let mut states: SmallVec<[(GraphResource, ResourceStates); 4]> = Default::default();
+
+        for (resource, state) in self.reads.iter() {
+            match states.iter_mut().find(|(r, _)| r == resource) {
+                Some((_, s)) => *s |= *state,
+                None => states.push((*resource, *state)),
+            }
+        }
+
+        for (resource, state) in self.writes.iter() {
+            match states.iter_mut().find(|(r, _)| r == resource) {
+                Some((_, s)) => *s = *state,
+                None => states.push((*resource, *state)),
+            }
+        }
+
+        states
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphBarrier {
+    pub resource: GraphResource,
+    pub before: ResourceStates,
+    pub after: ResourceStates,
+}
+
+#[derive(Clone, Debug)]
+pub struct CompiledPass {
+    pub pass: usize,
+    pub queue: QueueType,
+    pub states: SmallVec<[(GraphResource, ResourceStates); 4]>,
+    pub waits: SmallVec<[usize; 2]>,
+    pub signal: bool,
+    pub barriers: SmallVec<[GraphBarrier; 4]>,
+    pub post_barriers: SmallVec<[GraphBarrier; 4]>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CompiledGraph {
+    pub passes: Vec<CompiledPass>,
+    pub culled: Vec<usize>,
+    pub final_states: Vec<ResourceStates>,
+}
+
+impl CompiledGraph {
+    pub fn new(resources: &[ResourceNode], passes: &[PassNode]) -> Self {
+        let alive = Self::cull(resources, passes);
+        let culled = (0..passes.len()).filter(|p| !alive[*p]).collect();
+
+        let order = (0..passes.len()).filter(|p| alive[*p]).collect::<Vec<_>>();
+        let deps = Self::dependencies(resources.len(), passes, &order);
+
+        let mut compiled = order
+            .iter()
+            .map(|pass| CompiledPass {
+                pass: *pass,
+                queue: passes[*pass].queue,
+                states: passes[*pass].states(),
+                waits: Default::default(),
+                signal: false,
+                barriers: Default::default(),
+                post_barriers: Default::default(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut sync = deps;
+        let mut states = resources
+            .iter()
+            .map(|r| r.initial_state)
+            .collect::<Vec<_>>();
+        let mut last_user: Vec<Option<usize>> = vec![None; resources.len()];
+
+        for index in 0..compiled.len() {
+            let queue = compiled[index].queue;
+
+            for (resource, after) in compiled[index].states.clone() {
+                let before = states[resource.0];
+
+                if before != after {
+                    let barrier = GraphBarrier {
+                        resource,
+                        before,
+                        after,
+                    };
+
+                    Self::place_barrier(
+                        &mut compiled,
+                        index,
+                        last_user[resource.0],
+                        barrier,
+                        &resources[resource.0].name,
+                    );
+
+                    if let Some(user) = last_user[resource.0] {
+                        if !sync[index].contains(&user) {
+                            sync[index].push(user);
+                        }
+                    }
+                }
+
+                assert!(
+                    queue.supports(after),
+                    "RenderGraph: pass {} uses {} in {:?} which isn't supported on {:?} queue",
+                    passes[compiled[index].pass].name,
+                    resources[resource.0].name,
+                    after,
+                    queue
+                );
+
+                states[resource.0] = after;
+                last_user[resource.0] = Some(index);
+            }
+        }
+
+        for (index, resource) in resources.iter().enumerate() {
+            let (Some(after), Some(user)) = (resource.final_state, last_user[index]) else {
+                continue;
+            };
+
+            if states[index] == after {
+                continue;
+            }
+
+            let barrier = GraphBarrier {
+                resource: GraphResource(index),
+                before: states[index],
+                after,
+            };
+
+            assert!(
+                compiled[user].queue.supports(barrier.before)
+                    && compiled[user].queue.supports(after),
+                "RenderGraph: final transition of {} to {:?} can't be placed on {:?} queue",
+                resource.name,
+                after,
+                compiled[user].queue
+            );
+
+            compiled[user].post_barriers.push(barrier);
+            states[index] = after;
+        }
+
+        Self::synchronize(&mut compiled, &sync);
+
+        Self {
+            passes: compiled,
+            culled,
+            final_states: states,
+        }
+    }
+
+    pub fn pass(&self, pass: usize) -> Option<&CompiledPass> {
+        self.passes.iter().find(|p| p.pass == pass)
+    }
+
+    pub(super) fn cull(resources: &[ResourceNode], passes: &[PassNode]) -> Vec<bool> {
+        let mut alive = passes
+            .iter()
+            .map(|pass| {
+                pass.side_effects
+                    || pass
+                        .writes
+                        .iter()
+                        .any(|(resource, _)| resources[resource.0].output)
+            })
+            .collect::<Vec<_>>();
+
+        let mut last_writer: Vec<Option<usize>> = vec![None; resources.len()];
+        let mut producers: Vec<SmallVec<[usize; 4]>> = vec![Default::default(); passes.len()];
+
+        for (index, pass) in passes.iter().enumerate() {
+            for (resource, _) in pass.reads.iter() {
+                if let Some(writer) = last_writer[resource.0] {
+                    producers[index].push(writer);
+                }
+            }
+
+            for (resource, _) in pass.writes.iter() {
+                last_writer[resource.0] = Some(index);
+            }
+        }
+
+        for index in (0..passes.len()).rev() {
+            if alive[index] {
+                for producer in producers[index].iter() {
+                    alive[*producer] = true;
+                }
+            }
+        }
+
+        alive
+    }
+
+    fn dependencies(
+        resource_count: usize,
+        passes: &[PassNode],
+        order: &[usize],
+    ) -> Vec<SmallVec<[usize; 4]>> {
+        let mut last_writer: Vec<Option<usize>> = vec![None; resource_count];
+        let mut readers: Vec<SmallVec<[usize; 4]>> = vec![Default::default(); resource_count];
+        let mut deps: Vec<SmallVec<[usize; 4]>> = vec![Default::default(); order.len()];
+
+        for (index, pass) in order.iter().map(|p| &passes[*p]).enumerate() {
+            let mut add = |dep: usize| {
+                if dep != index && !deps[index].contains(&dep) {
+                    deps[index].push(dep);
+                }
+            };
+
+            for (resource, _) in pass.reads.iter() {
+                if let Some(writer) = last_writer[resource.0] {
+                    add(writer);
+                }
+            }
+
+            for (resource, _) in pass.writes.iter() {
+                readers[resource.0].iter().copied().for_each(&mut add);
+
+                if let Some(writer) = last_writer[resource.0] {
+                    add(writer);
+                }
+            }
+
+            for (resource, _) in pass.reads.iter() {
+                readers[resource.0].push(index);
+            }
+
+            for (resource, _) in pass.writes.iter() {
+                last_writer[resource.0] = Some(index);
+                readers[resource.0].clear();
+            }
+        }
+
+        deps
+    }
+
+    fn place_barrier(
+        compiled: &mut [CompiledPass],
+        index: usize,
+        last_user: Option<usize>,
+        barrier: GraphBarrier,
+        name: &str,
+    ) {
+        let supports =
+            |queue: QueueType| queue.supports(barrier.before) && queue.supports(barrier.after);
+
+        if supports(compiled[index].queue) {
+            compiled[index].barriers.push(barrier);
+        } else if let Some(user) = last_user.filter(|user| supports(compiled[*user].queue)) {
+            compiled[user].post_barriers.push(barrier);
+        } else {
+            panic!(
+                "RenderGraph: transition of {} from {:?} to {:?} can't be placed on any queue",
+                name, barrier.before, barrier.after
+            );
+        }
+    }
+
+    fn synchronize(compiled: &mut [CompiledPass], sync: &[SmallVec<[usize; 4]>]) {
+        let mut clocks: [[Option<usize>; 3]; 3] = [[None; 3]; 3];
+        let mut pass_clocks: Vec<[Option<usize>; 3]> = Vec::with_capacity(compiled.len());
+
+        for index in 0..compiled.len() {
+            let queue = compiled[index].queue.index();
+
+            let mut deps = sync[index].clone();
+            deps.sort_unstable_by(|a, b| b.cmp(a));
+
+            for dep in deps {
+                let dep_queue = compiled[dep].queue.index();
+
+                if dep_queue == queue || clocks[queue][dep_queue] >= Some(dep) {
+                    continue;
+                }
+
+                compiled[index].waits.push(dep);
+                compiled[dep].signal = true;
+
+                for (clock, other) in clocks[queue].iter_mut().zip(pass_clocks[dep].iter()) {
+                    *clock = (*clock).max(*other);
+                }
+            }
+
+            clocks[queue][queue] = Some(index);
+            pass_clocks.push(clocks[queue]);
+        }
+
+        for pass in compiled.iter_mut() {
+            pass.waits.sort_unstable();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::ResourceStates;
+
+    use super::{CompiledGraph, GraphBarrier, GraphResource, PassNode, QueueType, ResourceNode};
+
+    #[test]
+    fn test_culls_unused_passes() {
+        let resources = [
+            ResourceNode::new("shadow", ResourceStates::Common),
+            ResourceNode::new("debug", ResourceStates::Common),
+            ResourceNode::new("color", ResourceStates::Common).as_output(None),
+        ];
+        let (shadow, debug, color) = (GraphResource(0), GraphResource(1), GraphResource(2));
+
+        let passes = [
+            PassNode::new("shadow", QueueType::Direct).write(shadow, ResourceStates::DepthWrite),
+            PassNode::new("debug", QueueType::Direct).write(debug, ResourceStates::RenderTarget),
+            PassNode::new("main", QueueType::Direct)
+                .read(shadow, ResourceStates::PixelShaderResource)
+                .write(color, ResourceStates::RenderTarget),
+            PassNode::new("upload", QueueType::Transfer)
+                .write(debug, ResourceStates::CopyDst)
+                .with_side_effects(),
+        ];
+
+        let graph = CompiledGraph::new(&resources, &passes);
+
+        assert_eq!(graph.culled, vec![1]);
+        assert_eq!(
+            graph.passes.iter().map(|p| p.pass).collect::<Vec<_>>(),
+            vec![0, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_places_barriers() {
+        let resources = [
+            ResourceNode::new("depth", ResourceStates::Common),
+            ResourceNode::new("color", ResourceStates::Common)
+                .as_output(Some(ResourceStates::Present)),
+        ];
+        let (depth, color) = (GraphResource(0), GraphResource(1));
+
+        let passes = [
+            PassNode::new("shadow", QueueType::Direct).write(depth, ResourceStates::DepthWrite),
+            PassNode::new("main", QueueType::Direct)
+                .read(depth, ResourceStates::PixelShaderResource)
+                .write(color, ResourceStates::RenderTarget),
+        ];
+
+        let graph = CompiledGraph::new(&resources, &passes);
+
+        assert_eq!(
+            graph.passes[0].barriers.as_slice(),
+            &[GraphBarrier {
+                resource: depth,
+                before: ResourceStates::Common,
+                after: ResourceStates::DepthWrite,
+            }]
+        );
+        assert_eq!(graph.passes[1].barriers.len(), 2);
+        assert_eq!(
+            graph.passes[1].post_barriers.as_slice(),
+            &[GraphBarrier {
+                resource: color,
+                before: ResourceStates::RenderTarget,
+                after: ResourceStates::Present,
+            }]
+        );
+        assert!(graph.passes.iter().all(|p| p.waits.is_empty() && !p.signal));
+        assert_eq!(graph.final_states[1], ResourceStates::Present);
+    }
+
+    #[test]
+    fn test_combines_read_states() {
+        let resources = [
+            ResourceNode::new("texture", ResourceStates::Common),
+            ResourceNode::new("color", ResourceStates::Common).as_output(None),
+        ];
+        let (texture, color) = (GraphResource(0), GraphResource(1));
+
+        let passes = [PassNode::new("main", QueueType::Direct)
+            .read(texture, ResourceStates::PixelShaderResource)
+            .read(texture, ResourceStates::NonPixelShaderResource)
+            .write(color, ResourceStates::RenderTarget)];
+
+        let graph = CompiledGraph::new(&resources, &passes);
+
+        assert_eq!(
+            graph.passes[0].barriers[0].after,
+            ResourceStates::PixelShaderResource | ResourceStates::NonPixelShaderResource
+        );
+    }
+
+    #[test]
+    fn test_cross_queue_sync() {
+        let resources = [
+            ResourceNode::new("staging", ResourceStates::Common),
+            ResourceNode::new("particles", ResourceStates::Common),
+            ResourceNode::new("color", ResourceStates::Common).as_output(None),
+        ];
+        let (staging, particles, color) = (GraphResource(0), GraphResource(1), GraphResource(2));
+
+        let passes = [
+            PassNode::new("upload", QueueType::Transfer).write(staging, ResourceStates::CopyDst),
+            PassNode::new("simulate", QueueType::Compute)
+                .read(staging, ResourceStates::NonPixelShaderResource)
+                .write(particles, ResourceStates::UnorderedAccess),
+            PassNode::new("draw", QueueType::Direct)
+                .read(staging, ResourceStates::NonPixelShaderResource)
+                .read(particles, ResourceStates::NonPixelShaderResource)
+                .write(color, ResourceStates::RenderTarget),
+        ];
+
+        let graph = CompiledGraph::new(&resources, &passes);
+
+        assert!(graph.passes[0].signal);
+        assert_eq!(graph.passes[1].waits.as_slice(), &[0]);
+        assert!(graph.passes[1].signal);
+
+        assert_eq!(graph.passes[2].waits.as_slice(), &[1]);
+        assert!(!graph.passes[2].signal);
+    }
+
+    #[test]
+    fn test_hoists_unsupported_transitions() {
+        let resources = [
+            ResourceNode::new("color", ResourceStates::Common),
+            ResourceNode::new("readback", ResourceStates::Common),
+        ];
+        let (color, readback) = (GraphResource(0), GraphResource(1));
+
+        let passes = [
+            PassNode::new("main", QueueType::Direct).write(color, ResourceStates::RenderTarget),
+            PassNode::new("copy", QueueType::Transfer)
+                .read(color, ResourceStates::CopySrc)
+                .write(readback, ResourceStates::CopyDst)
+                .with_side_effects(),
+        ];
+
+        let graph = CompiledGraph::new(&resources, &passes);
+
+        assert_eq!(
+            graph.passes[0].post_barriers.as_slice(),
+            &[GraphBarrier {
+                resource: color,
+                before: ResourceStates::RenderTarget,
+                after: ResourceStates::CopySrc,
+            }]
+        );
+        assert_eq!(graph.passes[1].barriers.len(), 1);
+        assert_eq!(graph.passes[1].barriers[0].resource, readback);
+        assert_eq!(graph.passes[1].waits.as_slice(), &[0]);
+    }
+
+    #[test]
+    fn test_war_between_queues() {
+        let resources = [
+            ResourceNode::new("buffer", ResourceStates::CopyDst),
+            ResourceNode::new("color", ResourceStates::Common).as_output(None),
+        ];
+        let (buffer, color) = (GraphResource(0), GraphResource(1));
+
+        let passes = [
+            PassNode::new("upload", QueueType::Transfer).write(buffer, ResourceStates::CopyDst),
+            PassNode::new("draw", QueueType::Direct)
+                .read(buffer, ResourceStates::CopyDst)
+                .write(color, ResourceStates::RenderTarget),
+            PassNode::new("reupload", QueueType::Transfer)
+                .write(buffer, ResourceStates::CopyDst)
+                .with_side_effects(),
+        ];
+
+        let graph = CompiledGraph::new(&resources, &passes);
+
+        assert_eq!(graph.passes[1].waits.as_slice(), &[0]);
+        assert_eq!(graph.passes[2].waits.as_slice(), &[1]);
+        assert!(graph.passes[1].signal);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unsupported_state_on_queue() {
+        let resources = [ResourceNode::new("color", ResourceStates::Common).as_output(None)];
+
+        let passes = [PassNode::new("main", QueueType::Compute)
+            .write(GraphResource(0), ResourceStates::RenderTarget)];
+
+        CompiledGraph::new(&resources, &passes);
+    }
+}
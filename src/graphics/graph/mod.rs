@@ -0,0 +1,9 @@
+mod compiler;
+mod multi_gpu;
+mod multi_gpu_graph;
+mod render_graph;
+
+pub use compiler::*;
+pub use multi_gpu::*;
+pub use multi_gpu_graph::*;
+pub use render_graph::*;
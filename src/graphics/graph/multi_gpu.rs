@@ -0,0 +1,575 @@
+use std::collections::HashMap;
+
+use crate::graphics::ResourceStates;
+
+use super::{CompiledGraph, GraphResource, PassNode, QueueType, ResourceNode};
+
+#[derive(Clone, Debug)]
+pub struct GpuNode {
+    pub name: String,
+    pub cross_adapter_textures: bool,
+}
+
+impl GpuNode {
+    pub fn new(name: impl Into<String>, cross_adapter_textures: bool) -> Self {
+        Self {
+            name: name.into(),
+            cross_adapter_textures,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiGpuResourceNode {
+    pub node: ResourceNode,
+    pub owner: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiGpuPassNode {
+    pub node: PassNode,
+    pub gpu: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GpuResourceSlot {
+    Local(GraphResource),
+    Cross(GraphResource),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScheduledPass {
+    User(usize),
+    Push(usize),
+    Pull(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrossTransfer {
+    pub resource: GraphResource,
+    pub from: usize,
+    pub to: usize,
+    pub push: usize,
+    pub pull: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RemoteWait {
+    pub gpu: usize,
+    pub pass: usize,
+    pub wait_gpu: usize,
+    pub wait_pass: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct GpuSchedule {
+    pub passes: Vec<ScheduledPass>,
+    pub resources: Vec<GpuResourceSlot>,
+    pub graph: CompiledGraph,
+}
+
+#[derive(Clone, Debug)]
+pub struct CompiledMultiGpuGraph {
+    pub gpus: Vec<GpuSchedule>,
+    pub schedule: Vec<(usize, usize)>,
+    pub transfers: Vec<CrossTransfer>,
+    pub remote_waits: Vec<RemoteWait>,
+    pub culled: Vec<usize>,
+}
+
+#[derive(Default)]
+struct GpuBuilder {
+    nodes: Vec<ResourceNode>,
+    slots: Vec<GpuResourceSlot>,
+    lookup: HashMap<GpuResourceSlot, GraphResource>,
+    passes: Vec<PassNode>,
+    scheduled: Vec<ScheduledPass>,
+}
+
+impl GpuBuilder {
+    fn slot(
+        &mut self,
+        resources: &[MultiGpuResourceNode],
+        gpu: usize,
+        slot: GpuResourceSlot,
+    ) -> GraphResource {
+        *self.lookup.entry(slot).or_insert_with(|| {
+            let node = match slot {
+                GpuResourceSlot::Local(resource) => {
+                    let source = &resources[resource.0];
+
+                    if source.owner == gpu {
+                        source.node.clone()
+                    } else {
+                        ResourceNode::new(source.node.name.clone(), source.node.initial_state)
+                    }
+                }
+                GpuResourceSlot::Cross(resource) => ResourceNode::new(
+                    format!("{} (cross)", resources[resource.0].node.name),
+                    ResourceStates::Common,
+                ),
+            };
+
+            self.nodes.push(node);
+            self.slots.push(slot);
+
+            GraphResource(self.nodes.len() - 1)
+        })
+    }
+
+    fn push(&mut self, pass: PassNode, scheduled: ScheduledPass) -> usize {
+        self.passes.push(pass);
+        self.scheduled.push(scheduled);
+        self.passes.len() - 1
+    }
+}
+
+struct MultiGpuCompiler<'a> {
+    gpus: &'a [GpuNode],
+    resources: &'a [MultiGpuResourceNode],
+    builders: Vec<GpuBuilder>,
+    schedule: Vec<(usize, usize)>,
+    transfers: Vec<CrossTransfer>,
+    remote_waits: Vec<RemoteWait>,
+    valid: Vec<Vec<bool>>,
+    latest: Vec<usize>,
+    last_pull: Vec<Option<(usize, usize)>>,
+}
+
+impl MultiGpuCompiler<'_> {
+    fn local(&mut self, gpu: usize, resource: GraphResource) -> GraphResource {
+        self.builders[gpu].slot(self.resources, gpu, GpuResourceSlot::Local(resource))
+    }
+
+    fn cross(&mut self, gpu: usize, resource: GraphResource) -> GraphResource {
+        self.builders[gpu].slot(self.resources, gpu, GpuResourceSlot::Cross(resource))
+    }
+
+    fn push(&mut self, gpu: usize, pass: PassNode, scheduled: ScheduledPass) -> usize {
+        let index = self.builders[gpu].push(pass, scheduled);
+        self.schedule.push((gpu, index));
+        index
+    }
+
+    fn transfer(&mut self, resource: GraphResource, to: usize) {
+        let from = self.latest[resource.0];
+        let transfer = self.transfers.len();
+        let name = &self.resources[resource.0].node.name;
+
+        let mut push =
+            PassNode::new(format!("push {}", name), QueueType::Transfer).with_side_effects();
+        let mut pull =
+            PassNode::new(format!("pull {}", name), QueueType::Transfer).with_side_effects();
+
+        push = if self.gpus[from].cross_adapter_textures {
+            push.read(self.local(from, resource), ResourceStates::Common)
+        } else {
+            push.read(self.local(from, resource), ResourceStates::CopySrc)
+                .write(self.cross(from, resource), ResourceStates::CopyDst)
+        };
+
+        pull = if self.gpus[to].cross_adapter_textures {
+            pull.write(self.local(to, resource), ResourceStates::Common)
+        } else {
+            pull.read(self.cross(to, resource), ResourceStates::CopySrc)
+                .write(self.local(to, resource), ResourceStates::CopyDst)
+        };
+
+        let push = self.push(from, push, ScheduledPass::Push(transfer));
+
+        if let Some((gpu, pass)) = self.last_pull[resource.0].filter(|(gpu, _)| *gpu != from) {
+            self.remote_waits.push(RemoteWait {
+                gpu: from,
+                pass: push,
+                wait_gpu: gpu,
+                wait_pass: pass,
+            });
+        }
+
+        let pull = self.push(to, pull, ScheduledPass::Pull(transfer));
+
+        self.remote_waits.push(RemoteWait {
+            gpu: to,
+            pass: pull,
+            wait_gpu: from,
+            wait_pass: push,
+        });
+
+        self.last_pull[resource.0] = Some((to, pull));
+        self.valid[resource.0][to] = true;
+
+        self.transfers.push(CrossTransfer {
+            resource,
+            from,
+            to,
+            push,
+            pull,
+        });
+    }
+}
+
+impl CompiledMultiGpuGraph {
+    pub fn new(
+        gpus: &[GpuNode],
+        resources: &[MultiGpuResourceNode],
+        passes: &[MultiGpuPassNode],
+    ) -> Self {
+        let alive = CompiledGraph::cull(
+            &resources.iter().map(|r| r.node.clone()).collect::<Vec<_>>(),
+            &passes.iter().map(|p| p.node.clone()).collect::<Vec<_>>(),
+        );
+        let culled = (0..passes.len()).filter(|p| !alive[*p]).collect();
+
+        let mut compiler = MultiGpuCompiler {
+            gpus,
+            resources,
+            builders: gpus.iter().map(|_| GpuBuilder::default()).collect(),
+            schedule: vec![],
+            transfers: vec![],
+            remote_waits: vec![],
+            valid: resources
+                .iter()
+                .map(|r| (0..gpus.len()).map(|gpu| gpu == r.owner).collect())
+                .collect(),
+            latest: resources.iter().map(|r| r.owner).collect(),
+            last_pull: vec![None; resources.len()],
+        };
+
+        for (index, pass) in passes.iter().enumerate().filter(|(i, _)| alive[*i]) {
+            let gpu = pass.gpu;
+            assert!(
+                gpu < gpus.len(),
+                "RenderGraph: pass {} uses unknown gpu",
+                pass.node.name
+            );
+
+            for (resource, _) in pass.node.reads.iter() {
+                if !compiler.valid[resource.0][gpu] {
+                    compiler.transfer(*resource, gpu);
+                }
+            }
+
+            let mut node =
+                PassNode::new(pass.node.name.clone(), pass.node.queue).with_side_effects();
+
+            for (resource, state) in pass.node.reads.iter() {
+                node = node.read(compiler.local(gpu, *resource), *state);
+            }
+
+            for (resource, state) in pass.node.writes.iter() {
+                node = node.write(compiler.local(gpu, *resource), *state);
+
+                compiler.valid[resource.0].fill(false);
+                compiler.valid[resource.0][gpu] = true;
+                compiler.latest[resource.0] = gpu;
+            }
+
+            compiler.push(gpu, node, ScheduledPass::User(index));
+        }
+
+        for (index, resource) in resources.iter().enumerate() {
+            if resource.node.output && !compiler.valid[index][resource.owner] {
+                compiler.transfer(GraphResource(index), resource.owner);
+            }
+        }
+
+        let gpus = compiler
+            .builders
+            .into_iter()
+            .map(|builder| GpuSchedule {
+                graph: CompiledGraph::new(&builder.nodes, &builder.passes),
+                passes: builder.scheduled,
+                resources: builder.slots,
+            })
+            .collect();
+
+        Self {
+            gpus,
+            schedule: compiler.schedule,
+            transfers: compiler.transfers,
+            remote_waits: compiler.remote_waits,
+            culled,
+        }
+    }
+
+    pub fn remote_waits_for(&self, gpu: usize, pass: usize) -> impl Iterator<Item = &RemoteWait> {
+        self.remote_waits
+            .iter()
+            .filter(move |wait| wait.gpu == gpu && wait.pass == pass)
+    }
+
+    pub fn is_remote_signal(&self, gpu: usize, pass: usize) -> bool {
+        self.remote_waits
+            .iter()
+            .any(|wait| wait.wait_gpu == gpu && wait.wait_pass == pass)
+    }
+
+    pub fn resource_gpus(&self, resource: GraphResource) -> impl Iterator<Item = usize> + '_ {
+        self.gpus
+            .iter()
+            .enumerate()
+            .filter(move |(_, gpu)| gpu.resources.contains(&GpuResourceSlot::Local(resource)))
+            .map(|(index, _)| index)
+    }
+
+    pub fn resource_home(&self, resource: GraphResource, owner: usize) -> Option<usize> {
+        let mut gpus = self.resource_gpus(resource).peekable();
+        let first = gpus.peek().copied()?;
+
+        Some(if gpus.any(|gpu| gpu == owner) {
+            owner
+        } else {
+            first
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::ResourceStates;
+
+    use super::{
+        CompiledMultiGpuGraph, CrossTransfer, GpuNode, GpuResourceSlot, MultiGpuPassNode,
+        MultiGpuResourceNode, RemoteWait, ScheduledPass,
+    };
+    use crate::graphics::graph::{GraphResource, PassNode, QueueType, ResourceNode};
+
+    fn resource(name: &str, owner: usize) -> MultiGpuResourceNode {
+        MultiGpuResourceNode {
+            node: ResourceNode::new(name, ResourceStates::Common),
+            owner,
+        }
+    }
+
+    fn pass(node: PassNode, gpu: usize) -> MultiGpuPassNode {
+        MultiGpuPassNode { node, gpu }
+    }
+
+    #[test]
+    fn test_single_gpu_has_no_transfers() {
+        let gpus = [GpuNode::new("primary", false)];
+        let mut color = resource("color", 0);
+        color.node = color.node.as_output(None);
+
+        let passes = [pass(
+            PassNode::new("main", QueueType::Direct)
+                .write(GraphResource(0), ResourceStates::RenderTarget),
+            0,
+        )];
+
+        let graph = CompiledMultiGpuGraph::new(&gpus, &[color], &passes);
+
+        assert!(graph.transfers.is_empty());
+        assert!(graph.remote_waits.is_empty());
+        assert_eq!(graph.schedule, vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_resource_used_only_by_non_owner_lives_there() {
+        let gpus = [
+            GpuNode::new("primary", false),
+            GpuNode::new("secondary", false),
+        ];
+        let scratch = resource("scratch", 0);
+
+        let passes = [pass(
+            PassNode::new("offload", QueueType::Compute)
+                .write(GraphResource(0), ResourceStates::UnorderedAccess)
+                .with_side_effects(),
+            1,
+        )];
+
+        let graph = CompiledMultiGpuGraph::new(&gpus, &[scratch], &passes);
+
+        assert!(graph.transfers.is_empty());
+        assert_eq!(
+            graph.resource_gpus(GraphResource(0)).collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert_eq!(graph.resource_home(GraphResource(0), 0), Some(1));
+    }
+
+    #[test]
+    fn test_cross_gpu_read_inserts_transfer() {
+        let gpus = [
+            GpuNode::new("primary", false),
+            GpuNode::new("secondary", false),
+        ];
+        let shadow = GraphResource(0);
+        let color = GraphResource(1);
+
+        let mut resources = [resource("shadow", 1), resource("color", 0)];
+        resources[1].node = resources[1].node.clone().as_output(None);
+
+        let passes = [
+            pass(
+                PassNode::new("shadow", QueueType::Direct)
+                    .write(shadow, ResourceStates::DepthWrite),
+                1,
+            ),
+            pass(
+                PassNode::new("main", QueueType::Direct)
+                    .read(shadow, ResourceStates::PixelShaderResource)
+                    .write(color, ResourceStates::RenderTarget),
+                0,
+            ),
+        ];
+
+        let graph = CompiledMultiGpuGraph::new(&gpus, &resources, &passes);
+
+        assert_eq!(
+            graph.transfers,
+            vec![CrossTransfer {
+                resource: shadow,
+                from: 1,
+                to: 0,
+                push: 1,
+                pull: 0,
+            }]
+        );
+        assert_eq!(graph.schedule, vec![(1, 0), (1, 1), (0, 0), (0, 1)]);
+        assert_eq!(
+            graph.remote_waits,
+            vec![RemoteWait {
+                gpu: 0,
+                pass: 0,
+                wait_gpu: 1,
+                wait_pass: 1,
+            }]
+        );
+        assert!(graph.is_remote_signal(1, 1));
+
+        let secondary = &graph.gpus[1];
+        assert_eq!(
+            secondary.passes,
+            vec![ScheduledPass::User(0), ScheduledPass::Push(0)]
+        );
+        assert_eq!(
+            secondary.graph.passes[0].post_barriers[0].after,
+            ResourceStates::CopySrc
+        );
+        assert_eq!(secondary.graph.passes[1].waits.as_slice(), &[0]);
+
+        let primary = &graph.gpus[0];
+        assert_eq!(
+            primary.resources,
+            vec![
+                GpuResourceSlot::Cross(shadow),
+                GpuResourceSlot::Local(shadow),
+                GpuResourceSlot::Local(color),
+            ]
+        );
+        assert_eq!(primary.graph.passes[1].waits.as_slice(), &[0]);
+        assert_eq!(graph.resource_gpus(shadow).collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_up_to_date_copy_is_reused() {
+        let gpus = [
+            GpuNode::new("primary", false),
+            GpuNode::new("secondary", false),
+        ];
+        let data = GraphResource(0);
+
+        let passes = [
+            pass(
+                PassNode::new("produce", QueueType::Compute)
+                    .write(data, ResourceStates::UnorderedAccess),
+                0,
+            ),
+            pass(
+                PassNode::new("first", QueueType::Direct)
+                    .read(data, ResourceStates::PixelShaderResource)
+                    .with_side_effects(),
+                1,
+            ),
+            pass(
+                PassNode::new("second", QueueType::Direct)
+                    .read(data, ResourceStates::PixelShaderResource)
+                    .with_side_effects(),
+                1,
+            ),
+        ];
+
+        let graph = CompiledMultiGpuGraph::new(&gpus, &[resource("data", 0)], &passes);
+
+        assert_eq!(graph.transfers.len(), 1);
+    }
+
+    #[test]
+    fn test_ping_pong_waits_for_previous_pull() {
+        let gpus = [
+            GpuNode::new("primary", false),
+            GpuNode::new("secondary", false),
+        ];
+        let data = GraphResource(0);
+
+        let mut data_node = resource("data", 0);
+        data_node.node = data_node.node.as_output(None);
+
+        let passes = [
+            pass(
+                PassNode::new("a", QueueType::Direct).write(data, ResourceStates::RenderTarget),
+                0,
+            ),
+            pass(
+                PassNode::new("b", QueueType::Direct)
+                    .read(data, ResourceStates::PixelShaderResource)
+                    .write(data, ResourceStates::RenderTarget),
+                1,
+            ),
+        ];
+
+        let graph = CompiledMultiGpuGraph::new(&gpus, &[data_node], &passes);
+
+        assert_eq!(graph.transfers.len(), 2);
+        assert_eq!(graph.transfers[1].from, 1);
+        assert_eq!(graph.transfers[1].to, 0);
+
+        let second_push = graph.transfers[1].push;
+        let first_pull = graph.transfers[0].pull;
+
+        assert!(!graph.remote_waits.contains(&RemoteWait {
+            gpu: 1,
+            pass: second_push,
+            wait_gpu: 1,
+            wait_pass: first_pull,
+        }));
+        assert!(graph
+            .remote_waits_for(0, graph.transfers[1].pull)
+            .any(|w| w.wait_gpu == 1 && w.wait_pass == second_push));
+    }
+
+    #[test]
+    fn test_cross_adapter_textures_skip_copies() {
+        let gpus = [
+            GpuNode::new("primary", true),
+            GpuNode::new("secondary", true),
+        ];
+        let data = GraphResource(0);
+
+        let passes = [
+            pass(
+                PassNode::new("a", QueueType::Direct).write(data, ResourceStates::RenderTarget),
+                0,
+            ),
+            pass(
+                PassNode::new("b", QueueType::Direct)
+                    .read(data, ResourceStates::PixelShaderResource)
+                    .with_side_effects(),
+                1,
+            ),
+        ];
+
+        let graph = CompiledMultiGpuGraph::new(&gpus, &[resource("data", 0)], &passes);
+
+        assert!(graph.gpus.iter().all(|gpu| gpu
+            .resources
+            .iter()
+            .all(|s| matches!(s, GpuResourceSlot::Local(_)))));
+        assert_eq!(
+            graph.gpus[0].graph.passes[0].post_barriers[0].after,
+            ResourceStates::Common
+        );
+    }
+}
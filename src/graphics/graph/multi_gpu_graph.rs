@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+
+use oxidx::dx::{self, IDevice};
+
+use crate::graphics::{
+    commands::{Compute, Direct, Transfer, WorkerThread},
+    device::Device,
+    fence::SharedFence,
+    resources::{
+        Image, ImageDesc, ImageResourceDesc, ShareableImageDesc, SharedResource, TrackedResource,
+        ViewAccess,
+    },
+    MemoryHeapType, ResourceStates,
+};
+
+use super::{
+    CompiledMultiGpuGraph, GpuNode, GpuResourceSlot, GraphQueues, GraphResource, MultiGpuPassNode,
+    MultiGpuResourceNode, PassNode, QueueType, RenderGraph, ResourceNode, ScheduledPass,
+};
+
+type GpuPassFn<'a, T> = Box<dyn FnOnce(&WorkerThread<T>, &GpuResources<'_>) + 'a>;
+
+enum GpuPassExecutor<'a> {
+    Direct(GpuPassFn<'a, Direct>),
+    Compute(GpuPassFn<'a, Compute>),
+    Transfer(GpuPassFn<'a, Transfer>),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GpuContext<'q> {
+    pub device: &'q Device,
+    pub queues: GraphQueues<'q>,
+    pub access: &'q ViewAccess,
+}
+
+#[derive(Clone, Debug)]
+struct GpuImageDesc {
+    desc: ImageDesc,
+    initial_state: ResourceStates,
+}
+
+#[derive(Clone, Debug)]
+enum GpuImage {
+    Committed(Image),
+    Shared(SharedResource<Image>),
+}
+
+impl GpuImage {
+    fn local(&self) -> &Image {
+        match self {
+            GpuImage::Committed(image) => image,
+            GpuImage::Shared(shared) => shared.local_resource(),
+        }
+    }
+
+    fn get(&self, slot: GpuResourceSlot) -> &Image {
+        match slot {
+            GpuResourceSlot::Local(_) => self.local(),
+            GpuResourceSlot::Cross(_) => self.shared().cross_resource(),
+        }
+    }
+
+    fn shared(&self) -> &SharedResource<Image> {
+        match self {
+            GpuImage::Shared(shared) => shared,
+            GpuImage::Committed(_) => {
+                panic!("MultiGpuRenderGraph: image is not shared between gpus")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MultiGpuResourcePool {
+    images: HashMap<String, Vec<Option<GpuImage>>>,
+    fences: Vec<Vec<SharedFence>>,
+}
+
+impl MultiGpuResourcePool {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn image(&self, name: &str, gpu: usize) -> Option<&Image> {
+        self.images
+            .get(name)
+            .and_then(|images| images.get(gpu))
+            .and_then(|image| image.as_ref())
+            .map(|image| image.local())
+    }
+
+    fn prepare_fences(&mut self, contexts: &[GpuContext<'_>]) {
+        if self.fences.len() == contexts.len() {
+            return;
+        }
+
+        self.fences = contexts
+            .iter()
+            .enumerate()
+            .map(|(index, owner)| {
+                let fence = owner.device.create_shared_fence();
+
+                contexts
+                    .iter()
+                    .enumerate()
+                    .map(|(other_index, other)| {
+                        if index == other_index {
+                            fence.clone()
+                        } else {
+                            fence.connect(other.device.clone())
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+
+    fn prepare_image(
+        &mut self,
+        contexts: &[GpuContext<'_>],
+        node: &ResourceNode,
+        home: usize,
+        desc: &GpuImageDesc,
+        gpus: &[usize],
+    ) {
+        let shared = gpus.len() > 1;
+
+        let ready = self.images.get(&node.name).is_some_and(|images| {
+            gpus.iter().all(|gpu| match images.get(*gpu) {
+                Some(Some(GpuImage::Shared(_))) => true,
+                Some(Some(GpuImage::Committed(_))) => !shared,
+                _ => false,
+            })
+        });
+
+        if ready {
+            return;
+        }
+
+        let mut images = vec![None; contexts.len()];
+
+        if !shared {
+            let context = &contexts[home];
+
+            images[home] = Some(GpuImage::Committed(
+                context.device.create_commited_resource(
+                    desc.desc.clone(),
+                    context.access.clone(),
+                    desc.initial_state,
+                ),
+            ));
+        } else {
+            let context = &contexts[home];
+
+            let cross_desc = desc
+                .desc
+                .clone()
+                .with_flags(dx::ResourceFlags::AllowCrossAdapter)
+                .with_layout(dx::TextureLayout::RowMajor);
+            let info = context
+                .device
+                .raw
+                .get_resource_allocation_info(0, &[cross_desc.into()]);
+
+            let heap = context
+                .device
+                .create_heap(info.size_in_bytes() as usize, MemoryHeapType::Shared);
+            let resource = context
+                .device
+                .create_shared_image(
+                    &heap,
+                    desc.desc.clone(),
+                    context.access.clone(),
+                    desc.initial_state,
+                    ResourceStates::Common,
+                )
+                .expect("MultiGpuRenderGraph: failed to place shared image");
+
+            for gpu in gpus.iter().filter(|gpu| **gpu != home) {
+                let other = &contexts[*gpu];
+
+                images[*gpu] = Some(GpuImage::Shared(resource.connect_texture(
+                    &heap.connect(other.device.clone()),
+                    other.access.clone(),
+                    desc.initial_state,
+                    ResourceStates::Common,
+                )));
+            }
+
+            images[home] = Some(GpuImage::Shared(resource));
+        }
+
+        self.images.insert(node.name.clone(), images);
+    }
+}
+
+pub struct GpuResources<'r> {
+    gpu: usize,
+    names: &'r [ResourceNode],
+    pool: &'r MultiGpuResourcePool,
+}
+
+impl GpuResources<'_> {
+    pub fn gpu(&self) -> usize {
+        self.gpu
+    }
+
+    pub fn image(&self, resource: GraphResource) -> &Image {
+        self.pool.images[&self.names[resource.0].name][self.gpu]
+            .as_ref()
+            .expect("MultiGpuRenderGraph: image is not available on this gpu")
+            .local()
+    }
+}
+
+#[derive(Default)]
+pub struct MultiGpuRenderGraph<'a> {
+    gpus: Vec<GpuNode>,
+    resources: Vec<MultiGpuResourceNode>,
+    descs: Vec<GpuImageDesc>,
+    passes: Vec<MultiGpuPassNode>,
+    executors: Vec<GpuPassExecutor<'a>>,
+}
+
+impl<'a> MultiGpuRenderGraph<'a> {
+    pub fn new(devices: &[Device]) -> Self {
+        Self {
+            gpus: devices
+                .iter()
+                .map(|device| {
+                    GpuNode::new(
+                        device.name().to_string(),
+                        device.is_cross_adapter_texture_supported(),
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn create_image(
+        &mut self,
+        name: impl Into<String>,
+        desc: ImageDesc,
+        owner: usize,
+        initial_state: ResourceStates,
+    ) -> GraphResource {
+        assert!(
+            owner < self.gpus.len(),
+            "MultiGpuRenderGraph: unknown owner gpu {}",
+            owner
+        );
+
+        self.resources.push(MultiGpuResourceNode {
+            node: ResourceNode::new(name, initial_state),
+            owner,
+        });
+        self.descs.push(GpuImageDesc {
+            desc,
+            initial_state,
+        });
+
+        GraphResource(self.resources.len() - 1)
+    }
+
+    pub fn export(&mut self, resource: GraphResource, final_state: Option<ResourceStates>) {
+        let node = &mut self.resources[resource.0].node;
+        *node = node.clone().as_output(final_state);
+    }
+
+    pub fn add_direct_pass(
+        &mut self,
+        gpu: usize,
+        name: impl Into<String>,
+        setup: impl FnOnce(PassNode) -> PassNode,
+        execute: impl FnOnce(&WorkerThread<Direct>, &GpuResources<'_>) + 'a,
+    ) {
+        self.add_pass(gpu, setup(PassNode::new(name, QueueType::Direct)));
+        self.executors
+            .push(GpuPassExecutor::Direct(Box::new(execute)));
+    }
+
+    pub fn add_compute_pass(
+        &mut self,
+        gpu: usize,
+        name: impl Into<String>,
+        setup: impl FnOnce(PassNode) -> PassNode,
+        execute: impl FnOnce(&WorkerThread<Compute>, &GpuResources<'_>) + 'a,
+    ) {
+        self.add_pass(gpu, setup(PassNode::new(name, QueueType::Compute)));
+        self.executors
+            .push(GpuPassExecutor::Compute(Box::new(execute)));
+    }
+
+    pub fn add_transfer_pass(
+        &mut self,
+        gpu: usize,
+        name: impl Into<String>,
+        setup: impl FnOnce(PassNode) -> PassNode,
+        execute: impl FnOnce(&WorkerThread<Transfer>, &GpuResources<'_>) + 'a,
+    ) {
+        self.add_pass(gpu, setup(PassNode::new(name, QueueType::Transfer)));
+        self.executors
+            .push(GpuPassExecutor::Transfer(Box::new(execute)));
+    }
+
+    pub fn compile(&self) -> CompiledMultiGpuGraph {
+        CompiledMultiGpuGraph::new(&self.gpus, &self.resources, &self.passes)
+    }
+
+    pub fn execute(
+        self,
+        contexts: &[GpuContext<'_>],
+        pool: &mut MultiGpuResourcePool,
+    ) -> CompiledMultiGpuGraph {
+        assert_eq!(
+            contexts.len(),
+            self.gpus.len(),
+            "MultiGpuRenderGraph: expected one context per gpu"
+        );
+
+        let compiled = self.compile();
+
+        pool.prepare_fences(contexts);
+
+        for (index, resource) in self.resources.iter().enumerate() {
+            let resource_index = GraphResource(index);
+
+            if let Some(home) = compiled.resource_home(resource_index, resource.owner) {
+                let gpus = compiled.resource_gpus(resource_index).collect::<Vec<_>>();
+
+                pool.prepare_image(contexts, &resource.node, home, &self.descs[index], &gpus);
+            }
+        }
+
+        let pool = &*pool;
+        let names = self
+            .resources
+            .iter()
+            .map(|r| r.node.clone())
+            .collect::<Vec<_>>();
+
+        let tracked = compiled
+            .gpus
+            .iter()
+            .enumerate()
+            .map(|(gpu, schedule)| {
+                schedule
+                    .resources
+                    .iter()
+                    .map(|slot| {
+                        let resource = match slot {
+                            GpuResourceSlot::Local(r) | GpuResourceSlot::Cross(r) => r,
+                        };
+                        let image = pool.images[&names[resource.0].name][gpu]
+                            .as_ref()
+                            .unwrap()
+                            .get(*slot);
+
+                        TrackedResource::new(image)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut executors = self.executors.into_iter().map(Some).collect::<Vec<_>>();
+        let mut pending = vec![[false; 3]; contexts.len()];
+        let mut signals = HashMap::new();
+
+        for (gpu, index) in compiled.schedule.iter().copied() {
+            let schedule = &compiled.gpus[gpu];
+            let pass = &schedule.graph.passes[index];
+            let queues = &contexts[gpu].queues;
+            let queue = pass.queue.index();
+
+            let remote = compiled.remote_waits_for(gpu, index).collect::<Vec<_>>();
+
+            if !pass.waits.is_empty() || !remote.is_empty() {
+                if pending[gpu][queue] {
+                    queues.execute(pass.queue);
+                    pending[gpu][queue] = false;
+                }
+
+                for wait in pass.waits.iter() {
+                    queues.wait(pass.queue, schedule.graph.passes[*wait].queue);
+                }
+
+                for wait in remote {
+                    queues.wait_shared(
+                        pass.queue,
+                        &pool.fences[wait.wait_gpu][gpu],
+                        &signals[&(wait.wait_gpu, wait.wait_pass)],
+                    );
+                }
+            }
+
+            let resources = GpuResources {
+                gpu,
+                names: &names,
+                pool,
+            };
+
+            match schedule.passes[index] {
+                ScheduledPass::User(user) => match executors[user].take().unwrap() {
+                    GpuPassExecutor::Direct(f) => {
+                        RenderGraph::record(&tracked[gpu], queues.direct, pass, |w| {
+                            f(w, &resources)
+                        })
+                    }
+                    GpuPassExecutor::Compute(f) => {
+                        RenderGraph::record(&tracked[gpu], queues.compute, pass, |w| {
+                            f(w, &resources)
+                        })
+                    }
+                    GpuPassExecutor::Transfer(f) => {
+                        RenderGraph::record(&tracked[gpu], queues.transfer, pass, |w| {
+                            f(w, &resources)
+                        })
+                    }
+                },
+                ScheduledPass::Push(transfer) => {
+                    let image = &pool.images[&names[compiled.transfers[transfer].resource.0].name];
+                    let shared = image[gpu].as_ref().unwrap().shared();
+
+                    RenderGraph::record(&tracked[gpu], queues.transfer, pass, |w| {
+                        w.push_shared_texture(shared)
+                    });
+                }
+                ScheduledPass::Pull(transfer) => {
+                    let image = &pool.images[&names[compiled.transfers[transfer].resource.0].name];
+                    let shared = image[gpu].as_ref().unwrap().shared();
+
+                    RenderGraph::record(&tracked[gpu], queues.transfer, pass, |w| {
+                        w.pull_shared_texture(shared)
+                    });
+                }
+            }
+
+            pending[gpu][queue] = true;
+
+            let remote_signal = compiled.is_remote_signal(gpu, index);
+
+            if pass.signal || remote_signal {
+                queues.execute(pass.queue);
+                pending[gpu][queue] = false;
+            }
+
+            if remote_signal {
+                let signal = queues.signal_shared(pass.queue, &pool.fences[gpu][gpu]);
+                signals.insert((gpu, index), signal);
+            }
+        }
+
+        for (gpu, context) in contexts.iter().enumerate() {
+            for queue in [QueueType::Direct, QueueType::Compute, QueueType::Transfer] {
+                if pending[gpu][queue.index()] {
+                    context.queues.execute(queue);
+                }
+            }
+        }
+
+        compiled
+    }
+
+    fn add_pass(&mut self, gpu: usize, node: PassNode) {
+        assert!(
+            gpu < self.gpus.len(),
+            "MultiGpuRenderGraph: unknown gpu {}",
+            gpu
+        );
+
+        self.passes.push(MultiGpuPassNode { node, gpu });
+    }
+}
@@ -0,0 +1,219 @@
+use crate::graphics::{
+    commands::{CommandQueue, Compute, Direct, Transfer, WorkerThread, WorkerType},
+    fence_wait::FenceValue,
+    resources::{BufferResource, ImageResource, Resource, TrackedResource},
+    ResourceStates, SharedFence, SharedSignal,
+};
+
+use super::{CompiledGraph, CompiledPass, GraphResource, PassNode, QueueType, ResourceNode};
+
+enum PassExecutor<'a> {
+    Direct(Box<dyn FnOnce(&WorkerThread<Direct>) + 'a>),
+    Compute(Box<dyn FnOnce(&WorkerThread<Compute>) + 'a>),
+    Transfer(Box<dyn FnOnce(&WorkerThread<Transfer>) + 'a>),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GraphQueues<'q> {
+    pub direct: &'q CommandQueue<Direct>,
+    pub compute: &'q CommandQueue<Compute>,
+    pub transfer: &'q CommandQueue<Transfer>,
+}
+
+impl GraphQueues<'_> {
+    pub(super) fn execute(&self, queue: QueueType) -> FenceValue {
+        match queue {
+            QueueType::Direct => self.direct.execute(),
+            QueueType::Compute => self.compute.execute(),
+            QueueType::Transfer => self.transfer.execute(),
+        }
+    }
+
+    pub(super) fn wait(&self, queue: QueueType, other: QueueType) {
+        match other {
+            QueueType::Direct => self.wait_on(queue, self.direct),
+            QueueType::Compute => self.wait_on(queue, self.compute),
+            QueueType::Transfer => self.wait_on(queue, self.transfer),
+        }
+    }
+
+    pub(super) fn signal_shared(&self, queue: QueueType, fence: &SharedFence) -> SharedSignal {
+        match queue {
+            QueueType::Direct => self.direct.signal_shared(fence),
+            QueueType::Compute => self.compute.signal_shared(fence),
+            QueueType::Transfer => self.transfer.signal_shared(fence),
+        }
+    }
+
+    pub(super) fn wait_shared(&self, queue: QueueType, fence: &SharedFence, signal: &SharedSignal) {
+        match queue {
+            QueueType::Direct => self.direct.wait_shared(fence, signal),
+            QueueType::Compute => self.compute.wait_shared(fence, signal),
+            QueueType::Transfer => self.transfer.wait_shared(fence, signal),
+        }
+    }
+
+    fn wait_on<OT: WorkerType>(&self, queue: QueueType, other: &CommandQueue<OT>) {
+        match queue {
+            QueueType::Direct => self.direct.wait_other_queue_on_gpu(other),
+            QueueType::Compute => self.compute.wait_other_queue_on_gpu(other),
+            QueueType::Transfer => self.transfer.wait_other_queue_on_gpu(other),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    resources: Vec<ResourceNode>,
+    tracked: Vec<TrackedResource>,
+    passes: Vec<PassNode>,
+    executors: Vec<PassExecutor<'a>>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn import_image<R: ImageResource>(
+        &mut self,
+        name: impl Into<String>,
+        image: &R,
+    ) -> GraphResource {
+        self.import(name, image)
+    }
+
+    pub fn import_buffer<R: BufferResource>(
+        &mut self,
+        name: impl Into<String>,
+        buffer: &R,
+    ) -> GraphResource {
+        self.import(name, buffer)
+    }
+
+    pub fn export(&mut self, resource: GraphResource, final_state: Option<ResourceStates>) {
+        let node = &mut self.resources[resource.0];
+        *node = node.clone().as_output(final_state);
+    }
+
+    pub fn add_direct_pass(
+        &mut self,
+        name: impl Into<String>,
+        setup: impl FnOnce(PassNode) -> PassNode,
+        execute: impl FnOnce(&WorkerThread<Direct>) + 'a,
+    ) {
+        self.passes
+            .push(setup(PassNode::new(name, QueueType::Direct)));
+        self.executors.push(PassExecutor::Direct(Box::new(execute)));
+    }
+
+    pub fn add_compute_pass(
+        &mut self,
+        name: impl Into<String>,
+        setup: impl FnOnce(PassNode) -> PassNode,
+        execute: impl FnOnce(&WorkerThread<Compute>) + 'a,
+    ) {
+        self.passes
+            .push(setup(PassNode::new(name, QueueType::Compute)));
+        self.executors
+            .push(PassExecutor::Compute(Box::new(execute)));
+    }
+
+    pub fn add_transfer_pass(
+        &mut self,
+        name: impl Into<String>,
+        setup: impl FnOnce(PassNode) -> PassNode,
+        execute: impl FnOnce(&WorkerThread<Transfer>) + 'a,
+    ) {
+        self.passes
+            .push(setup(PassNode::new(name, QueueType::Transfer)));
+        self.executors
+            .push(PassExecutor::Transfer(Box::new(execute)));
+    }
+
+    pub fn compile(&self) -> CompiledGraph {
+        CompiledGraph::new(&self.resources, &self.passes)
+    }
+
+    pub fn execute(self, queues: GraphQueues<'_>) -> CompiledGraph {
+        let compiled = self.compile();
+
+        let mut executors = self.executors.into_iter().map(Some).collect::<Vec<_>>();
+        let mut pending = [false; 3];
+
+        for pass in compiled.passes.iter() {
+            let queue = pass.queue.index();
+
+            if !pass.waits.is_empty() {
+                if pending[queue] {
+                    queues.execute(pass.queue);
+                    pending[queue] = false;
+                }
+
+                for wait in pass.waits.iter() {
+                    queues.wait(pass.queue, compiled.passes[*wait].queue);
+                }
+            }
+
+            match executors[pass.pass].take().unwrap() {
+                PassExecutor::Direct(f) => Self::record(&self.tracked, queues.direct, pass, f),
+                PassExecutor::Compute(f) => Self::record(&self.tracked, queues.compute, pass, f),
+                PassExecutor::Transfer(f) => Self::record(&self.tracked, queues.transfer, pass, f),
+            }
+
+            pending[queue] = true;
+
+            if pass.signal {
+                queues.execute(pass.queue);
+                pending[queue] = false;
+            }
+        }
+
+        for queue in [QueueType::Direct, QueueType::Compute, QueueType::Transfer] {
+            if pending[queue.index()] {
+                queues.execute(queue);
+            }
+        }
+
+        compiled
+    }
+
+    fn import<R: Resource>(&mut self, name: impl Into<String>, resource: &R) -> GraphResource {
+        let tracked = TrackedResource::new(resource);
+
+        self.resources
+            .push(ResourceNode::new(name, tracked.state.get(0)));
+        self.tracked.push(tracked);
+
+        GraphResource(self.resources.len() - 1)
+    }
+
+    pub(super) fn record<T: WorkerType>(
+        tracked: &[TrackedResource],
+        queue: &CommandQueue<T>,
+        pass: &CompiledPass,
+        execute: impl FnOnce(&WorkerThread<T>),
+    ) {
+        let worker = queue.get_worker_thread(None);
+
+        {
+            let mut tracker = worker.tracker.lock();
+
+            for (resource, state) in pass.states.iter() {
+                tracker.transition(&tracked[resource.0], None, *state);
+            }
+        }
+
+        execute(&worker);
+
+        {
+            let mut tracker = worker.tracker.lock();
+
+            for barrier in pass.post_barriers.iter() {
+                tracker.transition(&tracked[barrier.resource.0], None, barrier.after);
+            }
+        }
+
+        queue.push_worker(worker);
+    }
+}
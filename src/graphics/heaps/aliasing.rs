@@ -0,0 +1,259 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransientRequest {
+    pub size: usize,
+    pub alignment: usize,
+    pub first_pass: usize,
+    pub last_pass: usize,
+}
+
+impl TransientRequest {
+    pub fn new(size: usize, alignment: usize, first_pass: usize, last_pass: usize) -> Self {
+        assert!(alignment.is_power_of_two());
+        assert!(first_pass <= last_pass);
+
+        Self {
+            size,
+            alignment,
+            first_pass,
+            last_pass,
+        }
+    }
+
+    pub fn is_alive_with(&self, other: &Self) -> bool {
+        self.first_pass <= other.last_pass && other.first_pass <= self.last_pass
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AliasingBarrier {
+    pub pass: usize,
+    pub before: Option<usize>,
+    pub after: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct AliasingPlan {
+    offsets: Vec<usize>,
+    heap_size: usize,
+    barriers: Vec<AliasingBarrier>,
+}
+
+impl AliasingPlan {
+    pub fn new(requests: &[TransientRequest]) -> Self {
+        let mut order = (0..requests.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| {
+            requests[*b]
+                .size
+                .cmp(&requests[*a].size)
+                .then(requests[*a].first_pass.cmp(&requests[*b].first_pass))
+        });
+
+        let mut offsets = vec![0; requests.len()];
+        let mut placed: Vec<usize> = Vec::with_capacity(requests.len());
+        let mut heap_size = 0;
+
+        for index in order {
+            let request = &requests[index];
+
+            let mut occupied = placed
+                .iter()
+                .filter(|other| requests[**other].is_alive_with(request))
+                .map(|other| offsets[*other]..(offsets[*other] + requests[*other].size))
+                .collect::<Vec<_>>();
+            occupied.sort_by_key(|range| range.start);
+
+            let mut offset = 0;
+            for range in occupied {
+                if offset + request.size <= range.start {
+                    break;
+                }
+
+                offset = offset.max(range.end.next_multiple_of(request.alignment));
+            }
+
+            offsets[index] = offset;
+            heap_size = heap_size.max(offset + request.size);
+            placed.push(index);
+        }
+
+        let mut barriers = (0..requests.len())
+            .map(|after| {
+                let request = &requests[after];
+                let memory = offsets[after]..(offsets[after] + request.size);
+
+                let mut predecessors = (0..requests.len()).filter(|other| {
+                    requests[*other].last_pass < request.first_pass
+                        && Self::is_overlapped(
+                            &memory,
+                            &(offsets[*other]..(offsets[*other] + requests[*other].size)),
+                        )
+                });
+
+                let before = match (predecessors.next(), predecessors.next()) {
+                    (Some(before), None) => Some(before),
+                    _ => None,
+                };
+
+                AliasingBarrier {
+                    pass: request.first_pass,
+                    before,
+                    after,
+                }
+            })
+            .collect::<Vec<_>>();
+        barriers.sort_by_key(|barrier| (barrier.pass, barrier.after));
+
+        Self {
+            offsets,
+            heap_size,
+            barriers,
+        }
+    }
+
+    pub fn offset(&self, index: usize) -> usize {
+        self.offsets[index]
+    }
+
+    pub fn heap_size(&self) -> usize {
+        self.heap_size
+    }
+
+    pub fn barriers(&self) -> &[AliasingBarrier] {
+        &self.barriers
+    }
+
+    pub fn barriers_for_pass(&self, pass: usize) -> impl Iterator<Item = &AliasingBarrier> {
+        self.barriers
+            .iter()
+            .filter(move |barrier| barrier.pass == pass)
+    }
+
+    fn is_overlapped(a: &Range<usize>, b: &Range<usize>) -> bool {
+        a.start < b.end && b.start < a.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{AliasingPlan, TransientRequest};
+
+    const KB64: usize = 64 * 1024;
+
+    #[test]
+    fn test_empty_plan() {
+        let plan = AliasingPlan::new(&[]);
+
+        assert_eq!(plan.heap_size(), 0);
+        assert!(plan.barriers().is_empty());
+        assert_eq!(plan.barriers_for_pass(0).count(), 0);
+    }
+
+    #[test]
+    fn test_disjoint_lifetimes_alias() {
+        let requests = [
+            TransientRequest::new(4 * KB64, KB64, 0, 1),
+            TransientRequest::new(4 * KB64, KB64, 2, 3),
+        ];
+
+        let plan = AliasingPlan::new(&requests);
+
+        assert_eq!(plan.offset(0), 0);
+        assert_eq!(plan.offset(1), 0);
+        assert_eq!(plan.heap_size(), 4 * KB64);
+
+        let barriers = plan.barriers_for_pass(2).collect::<Vec<_>>();
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].before, Some(0));
+        assert_eq!(barriers[0].after, 1);
+    }
+
+    #[test]
+    fn test_overlapping_lifetimes_do_not_alias() {
+        let requests = [
+            TransientRequest::new(4 * KB64, KB64, 0, 2),
+            TransientRequest::new(2 * KB64, KB64, 1, 3),
+            TransientRequest::new(2 * KB64, KB64, 3, 4),
+        ];
+
+        let plan = AliasingPlan::new(&requests);
+
+        assert_eq!(plan.offset(0), 0);
+        assert_eq!(plan.offset(1), 4 * KB64);
+        assert_eq!(plan.offset(2), 0);
+        assert_eq!(plan.heap_size(), 6 * KB64);
+
+        let barrier = plan.barriers_for_pass(3).next().unwrap();
+        assert_eq!(barrier.before, Some(0));
+        assert_eq!(barrier.after, 2);
+    }
+
+    #[test]
+    fn test_respects_alignment() {
+        let requests = [
+            TransientRequest::new(KB64, KB64, 0, 1),
+            TransientRequest::new(KB64, 4 * KB64, 0, 1),
+        ];
+
+        let plan = AliasingPlan::new(&requests);
+
+        assert_eq!(plan.offset(1) % (4 * KB64), 0);
+        assert!(plan.offset(0) + KB64 <= plan.offset(1) || plan.offset(1) + KB64 <= plan.offset(0));
+    }
+
+    #[test]
+    fn test_multiple_predecessors_use_null_before() {
+        let requests = [
+            TransientRequest::new(KB64, KB64, 0, 0),
+            TransientRequest::new(KB64, KB64, 0, 0),
+            TransientRequest::new(2 * KB64, KB64, 1, 1),
+        ];
+
+        let plan = AliasingPlan::new(&requests);
+
+        let barrier = plan.barriers_for_pass(1).next().unwrap();
+        assert_eq!(barrier.after, 2);
+        assert_eq!(barrier.before, None);
+    }
+
+    #[test]
+    fn test_random_plans_have_no_conflicts() {
+        for seed in 0..32 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let requests = (0..rng.gen_range(1..24))
+                .map(|_| {
+                    let first_pass = rng.gen_range(0..16);
+                    TransientRequest::new(
+                        rng.gen_range(1..16) * KB64,
+                        KB64 << rng.gen_range(0..3),
+                        first_pass,
+                        first_pass + rng.gen_range(0..6),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let plan = AliasingPlan::new(&requests);
+
+            for (i, a) in requests.iter().enumerate() {
+                assert_eq!(plan.offset(i) % a.alignment, 0);
+                assert!(plan.offset(i) + a.size <= plan.heap_size());
+
+                for (j, b) in requests.iter().enumerate().skip(i + 1) {
+                    if a.is_alive_with(b) {
+                        assert!(
+                            plan.offset(i) + a.size <= plan.offset(j)
+                                || plan.offset(j) + b.size <= plan.offset(i)
+                        );
+                    }
+                }
+            }
+
+            assert_eq!(plan.barriers().len(), requests.len());
+            assert!(plan.heap_size() <= requests.iter().map(|r| r.size + r.alignment).sum());
+        }
+    }
+}
@@ -1,8 +1,73 @@
-use super::MemoryHeap;
+use std::sync::Arc;
 
-#[derive(Debug)]
+use parking_lot::Mutex;
+
+use super::{BlockId, MemoryHeap, MemoryTrack, TlsfAllocator};
+
+#[derive(Clone, Debug)]
 pub struct Allocation {
     pub(crate) heap: MemoryHeap,
     pub(crate) offset: usize,
     pub(crate) size: usize,
+    pub(crate) block: Arc<AllocationBlock>,
+}
+
+impl Allocation {
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn heap(&self) -> &MemoryHeap {
+        &self.heap
+    }
+
+    pub(crate) fn sub_allocation(&self, offset: usize, size: usize) -> Allocation {
+        assert!(offset + size <= self.size);
+
+        Allocation {
+            heap: self.heap.clone(),
+            offset: self.offset + offset,
+            size,
+            block: Arc::clone(&self.block),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct AllocationBlock {
+    pub(crate) allocator: Arc<Mutex<TlsfAllocator>>,
+    pub(crate) id: BlockId,
+    pub(crate) memory: MemoryTrack,
+}
+
+impl Drop for AllocationBlock {
+    fn drop(&mut self) {
+        self.allocator.lock().free(self.id);
+    }
+}
+
+#[derive(Debug)]
+pub enum ResourceMemory {
+    Committed(MemoryTrack),
+    Placed(Allocation),
+}
+
+impl ResourceMemory {
+    pub fn size(&self) -> usize {
+        match self {
+            ResourceMemory::Committed(track) => track.size(),
+            ResourceMemory::Placed(allocation) => allocation.size,
+        }
+    }
+
+    pub fn allocation(&self) -> Option<&Allocation> {
+        match self {
+            ResourceMemory::Committed(_) => None,
+            ResourceMemory::Placed(allocation) => Some(allocation),
+        }
+    }
 }
@@ -0,0 +1,471 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::Mutex;
+use smallvec::SmallVec;
+
+use crate::graphics::types::MemoryHeapType;
+
+pub(crate) const HEAP_TYPES: [MemoryHeapType; 4] = [
+    MemoryHeapType::Gpu,
+    MemoryHeapType::Cpu,
+    MemoryHeapType::Readback,
+    MemoryHeapType::Shared,
+];
+
+const SEGMENTS: [MemorySegment; 2] = [MemorySegment::Local, MemorySegment::NonLocal];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemorySegment {
+    Local,
+    NonLocal,
+}
+
+impl MemorySegment {
+    pub fn of(mtype: MemoryHeapType) -> Self {
+        match mtype {
+            MemoryHeapType::Gpu | MemoryHeapType::Shared => MemorySegment::Local,
+            MemoryHeapType::Cpu | MemoryHeapType::Readback => MemorySegment::NonLocal,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryKind {
+    Committed,
+    Heap,
+    Placed,
+    Staging,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub committed: usize,
+    pub heaps: usize,
+    pub placed: usize,
+    pub staging: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.committed + self.heaps + self.staging
+    }
+}
+
+impl std::ops::Add for MemoryUsage {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            committed: self.committed + rhs.committed,
+            heaps: self.heaps + rhs.heaps,
+            placed: self.placed + rhs.placed,
+            staging: self.staging + rhs.staging,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryBudget {
+    pub limit: usize,
+    pub thresholds: SmallVec<[f32; 4]>,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            thresholds: SmallVec::from_slice(&[0.75, 0.9, 1.0]),
+        }
+    }
+
+    pub fn with_thresholds(mut self, thresholds: &[f32]) -> Self {
+        self.thresholds = SmallVec::from_slice(thresholds);
+        self.thresholds.sort_by(f32::total_cmp);
+        self
+    }
+
+    fn level(&self, usage: usize) -> usize {
+        self.thresholds
+            .iter()
+            .take_while(|t| usage as f64 >= self.limit as f64 * **t as f64)
+            .count()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryTracker {
+    usage: [[AtomicUsize; 4]; 4],
+    budgets: Mutex<[Option<MemoryBudget>; 4]>,
+    levels: [AtomicUsize; 4],
+    segment_budgets: Mutex<[Option<MemoryBudget>; 2]>,
+    segment_levels: [AtomicUsize; 2],
+}
+
+impl MemoryTracker {
+    pub fn track(
+        self: &Arc<Self>,
+        mtype: MemoryHeapType,
+        kind: MemoryKind,
+        size: usize,
+    ) -> MemoryTrack {
+        self.usage[Self::type_index(mtype)][kind as usize].fetch_add(size, Ordering::Relaxed);
+        self.check_budget(mtype);
+        self.check_segment_budget(MemorySegment::of(mtype));
+
+        MemoryTrack {
+            tracker: Arc::clone(self),
+            mtype,
+            kind,
+            size,
+        }
+    }
+
+    pub fn usage(&self, mtype: MemoryHeapType) -> MemoryUsage {
+        let usage = &self.usage[Self::type_index(mtype)];
+
+        MemoryUsage {
+            committed: usage[MemoryKind::Committed as usize].load(Ordering::Relaxed),
+            heaps: usage[MemoryKind::Heap as usize].load(Ordering::Relaxed),
+            placed: usage[MemoryKind::Placed as usize].load(Ordering::Relaxed),
+            staging: usage[MemoryKind::Staging as usize].load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn budget(&self, mtype: MemoryHeapType) -> Option<MemoryBudget> {
+        self.budgets.lock()[Self::type_index(mtype)].clone()
+    }
+
+    pub fn set_budget(&self, mtype: MemoryHeapType, budget: Option<MemoryBudget>) {
+        self.budgets.lock()[Self::type_index(mtype)] = budget;
+        self.levels[Self::type_index(mtype)].store(0, Ordering::Relaxed);
+        self.check_budget(mtype);
+    }
+
+    pub fn segment_usage(&self, segment: MemorySegment) -> MemoryUsage {
+        HEAP_TYPES
+            .iter()
+            .filter(|mtype| MemorySegment::of(**mtype) == segment)
+            .map(|mtype| self.usage(*mtype))
+            .fold(MemoryUsage::default(), |acc, usage| acc + usage)
+    }
+
+    pub fn segment_budget(&self, segment: MemorySegment) -> Option<MemoryBudget> {
+        self.segment_budgets.lock()[segment as usize].clone()
+    }
+
+    pub fn set_segment_budget(&self, segment: MemorySegment, budget: Option<MemoryBudget>) {
+        self.segment_budgets.lock()[segment as usize] = budget;
+        self.segment_levels[segment as usize].store(0, Ordering::Relaxed);
+        self.check_segment_budget(segment);
+    }
+
+    pub fn report(&self) -> MemoryReport {
+        MemoryReport {
+            entries: HEAP_TYPES
+                .iter()
+                .map(|mtype| (*mtype, self.usage(*mtype), self.budget(*mtype)))
+                .collect(),
+            segments: SEGMENTS
+                .iter()
+                .map(|segment| {
+                    (
+                        *segment,
+                        self.segment_usage(*segment),
+                        self.segment_budget(*segment),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn release(&self, mtype: MemoryHeapType, kind: MemoryKind, size: usize) {
+        self.usage[Self::type_index(mtype)][kind as usize].fetch_sub(size, Ordering::Relaxed);
+        self.check_budget(mtype);
+        self.check_segment_budget(MemorySegment::of(mtype));
+    }
+
+    fn check_budget(&self, mtype: MemoryHeapType) -> Option<f32> {
+        let index = Self::type_index(mtype);
+        let budgets = self.budgets.lock();
+        let budget = budgets[index].as_ref()?;
+
+        let usage = self.usage(mtype).total();
+        let level = budget.level(usage);
+        let old = self.levels[index].swap(level, Ordering::Relaxed);
+
+        if level > old {
+            let threshold = budget.thresholds[level - 1];
+
+            tracing::warn!(
+                "Memory usage of {:?} heaps crossed {:.0}% of budget: {} of {} bytes",
+                mtype,
+                threshold * 100.0,
+                usage,
+                budget.limit
+            );
+
+            Some(threshold)
+        } else {
+            None
+        }
+    }
+
+    fn check_segment_budget(&self, segment: MemorySegment) -> Option<f32> {
+        let budgets = self.segment_budgets.lock();
+        let budget = budgets[segment as usize].as_ref()?;
+
+        let usage = self.segment_usage(segment).total();
+        let level = budget.level(usage);
+        let old = self.segment_levels[segment as usize].swap(level, Ordering::Relaxed);
+
+        if level > old {
+            let threshold = budget.thresholds[level - 1];
+
+            tracing::warn!(
+                "Memory usage of the {:?} segment crossed {:.0}% of budget: {} of {} bytes",
+                segment,
+                threshold * 100.0,
+                usage,
+                budget.limit
+            );
+
+            Some(threshold)
+        } else {
+            None
+        }
+    }
+
+    fn type_index(mtype: MemoryHeapType) -> usize {
+        match mtype {
+            MemoryHeapType::Gpu => 0,
+            MemoryHeapType::Cpu => 1,
+            MemoryHeapType::Readback => 2,
+            MemoryHeapType::Shared => 3,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MemoryTrack {
+    tracker: Arc<MemoryTracker>,
+    mtype: MemoryHeapType,
+    kind: MemoryKind,
+    size: usize,
+}
+
+impl MemoryTrack {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn memory_type(&self) -> MemoryHeapType {
+        self.mtype
+    }
+
+    pub fn kind(&self) -> MemoryKind {
+        self.kind
+    }
+}
+
+impl Drop for MemoryTrack {
+    fn drop(&mut self) {
+        self.tracker.release(self.mtype, self.kind, self.size);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VideoMemoryInfo {
+    pub budget: usize,
+    pub current_usage: usize,
+    pub available_for_reservation: usize,
+    pub current_reservation: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct MemoryReport {
+    pub entries: SmallVec<[(MemoryHeapType, MemoryUsage, Option<MemoryBudget>); 4]>,
+    pub segments: SmallVec<[(MemorySegment, MemoryUsage, Option<MemoryBudget>); 2]>,
+}
+
+impl MemoryReport {
+    pub fn usage(&self, mtype: MemoryHeapType) -> MemoryUsage {
+        self.entries
+            .iter()
+            .find(|(t, _, _)| *t == mtype)
+            .map(|(_, usage, _)| *usage)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MB: f64 = 1024.0 * 1024.0;
+
+        writeln!(
+            f,
+            "{:<10} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            "Heap", "Committed", "Heaps", "Placed", "Staging", "Total", "Budget"
+        )?;
+
+        let heaps = self
+            .entries
+            .iter()
+            .map(|(mtype, usage, budget)| (format!("{:?}", mtype), usage, budget));
+        let segments = self
+            .segments
+            .iter()
+            .map(|(segment, usage, budget)| (format!("{:?}", segment), usage, budget));
+
+        for (name, usage, budget) in heaps.chain(segments) {
+            let budget = budget
+                .as_ref()
+                .map(|b| format!("{:.2} MB", b.limit as f64 / MB))
+                .unwrap_or_else(|| "-".to_string());
+
+            writeln!(
+                f,
+                "{:<10} {:>9.2} MB {:>9.2} MB {:>9.2} MB {:>9.2} MB {:>9.2} MB {:>12}",
+                name,
+                usage.committed as f64 / MB,
+                usage.heaps as f64 / MB,
+                usage.placed as f64 / MB,
+                usage.staging as f64 / MB,
+                usage.total() as f64 / MB,
+                budget
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::graphics::types::MemoryHeapType;
+
+    use super::{MemoryBudget, MemoryKind, MemorySegment, MemoryTracker};
+
+    #[test]
+    fn test_track_and_release() {
+        let tracker = Arc::new(MemoryTracker::default());
+
+        let a = tracker.track(MemoryHeapType::Gpu, MemoryKind::Committed, 100);
+        let b = tracker.track(MemoryHeapType::Gpu, MemoryKind::Heap, 1000);
+        let c = tracker.track(MemoryHeapType::Gpu, MemoryKind::Placed, 500);
+        let d = tracker.track(MemoryHeapType::Cpu, MemoryKind::Staging, 10);
+
+        let usage = tracker.usage(MemoryHeapType::Gpu);
+        assert_eq!(usage.committed, 100);
+        assert_eq!(usage.placed, 500);
+        assert_eq!(usage.total(), 1100);
+        assert_eq!(tracker.usage(MemoryHeapType::Cpu).staging, 10);
+
+        drop((a, b, c, d));
+
+        assert_eq!(tracker.usage(MemoryHeapType::Gpu).total(), 0);
+        assert_eq!(tracker.usage(MemoryHeapType::Cpu).total(), 0);
+    }
+
+    #[test]
+    fn test_budget_thresholds() {
+        let tracker = Arc::new(MemoryTracker::default());
+        tracker.set_budget(
+            MemoryHeapType::Gpu,
+            Some(MemoryBudget::new(1000).with_thresholds(&[0.5, 0.9])),
+        );
+
+        let a = tracker.track(MemoryHeapType::Gpu, MemoryKind::Committed, 400);
+        assert_eq!(tracker.check_budget(MemoryHeapType::Gpu), None);
+
+        let b = tracker.track(MemoryHeapType::Gpu, MemoryKind::Committed, 100);
+        assert_eq!(
+            tracker.levels[0].load(std::sync::atomic::Ordering::Relaxed),
+            1
+        );
+
+        drop(b);
+        assert_eq!(
+            tracker.levels[0].load(std::sync::atomic::Ordering::Relaxed),
+            0
+        );
+
+        let _c = tracker.track(MemoryHeapType::Gpu, MemoryKind::Heap, 300);
+        let _d = tracker.track(MemoryHeapType::Gpu, MemoryKind::Placed, 300);
+        assert_eq!(
+            tracker.levels[0].load(std::sync::atomic::Ordering::Relaxed),
+            1
+        );
+
+        drop(a);
+        let _e = tracker.track(MemoryHeapType::Gpu, MemoryKind::Committed, 650);
+        assert_eq!(
+            tracker.levels[0].load(std::sync::atomic::Ordering::Relaxed),
+            2
+        );
+    }
+
+    #[test]
+    fn test_segment_budget_sums_heap_types() {
+        let tracker = Arc::new(MemoryTracker::default());
+        tracker.set_segment_budget(
+            MemorySegment::Local,
+            Some(MemoryBudget::new(1000).with_thresholds(&[0.5, 0.9])),
+        );
+
+        let _gpu = tracker.track(MemoryHeapType::Gpu, MemoryKind::Committed, 300);
+        assert_eq!(
+            tracker.segment_levels[0].load(std::sync::atomic::Ordering::Relaxed),
+            0
+        );
+
+        let shared = tracker.track(MemoryHeapType::Shared, MemoryKind::Heap, 300);
+        assert_eq!(tracker.segment_usage(MemorySegment::Local).total(), 600);
+        assert_eq!(
+            tracker.segment_levels[0].load(std::sync::atomic::Ordering::Relaxed),
+            1
+        );
+
+        let _cpu = tracker.track(MemoryHeapType::Cpu, MemoryKind::Staging, 800);
+        assert_eq!(
+            tracker.segment_levels[0].load(std::sync::atomic::Ordering::Relaxed),
+            1
+        );
+
+        drop(shared);
+        assert_eq!(
+            tracker.segment_levels[0].load(std::sync::atomic::Ordering::Relaxed),
+            0
+        );
+    }
+
+    #[test]
+    fn test_check_budget_reports_crossing_once() {
+        let tracker = Arc::new(MemoryTracker::default());
+        let _a = tracker.track(MemoryHeapType::Cpu, MemoryKind::Staging, 800);
+
+        tracker.budgets.lock()[1] = Some(MemoryBudget::new(1000));
+
+        assert_eq!(tracker.check_budget(MemoryHeapType::Cpu), Some(0.75));
+        assert_eq!(tracker.check_budget(MemoryHeapType::Cpu), None);
+    }
+
+    #[test]
+    fn test_report() {
+        let tracker = Arc::new(MemoryTracker::default());
+        let _a = tracker.track(MemoryHeapType::Readback, MemoryKind::Staging, 64);
+
+        let report = tracker.report();
+        assert_eq!(report.entries.len(), 4);
+        assert_eq!(report.segments.len(), 2);
+        assert_eq!(report.usage(MemoryHeapType::Readback).staging, 64);
+        assert!(report.to_string().contains("Readback"));
+    }
+}
@@ -1,4 +1,5 @@
 use oxidx::dx::{self, IDevice};
+use parking_lot::Mutex;
 use std::{ops::Deref, sync::Arc};
 
 use crate::graphics::{
@@ -8,7 +9,10 @@ use crate::graphics::{
     ResourceStates,
 };
 
-use super::Allocation;
+use super::{
+    Allocation, AllocationBlock, HeapStats, MemoryKind, MemoryTrack, TlsfAllocator,
+    DEFAULT_PLACEMENT_ALIGNMENT, MSAA_PLACEMENT_ALIGNMENT,
+};
 
 #[derive(Clone, Debug)]
 pub struct MemoryHeap(Arc<MemoryHeapInner>);
@@ -19,10 +23,26 @@ pub struct MemoryHeapInner {
     pub(crate) heap: dx::Heap,
     pub(crate) size: usize,
     pub(crate) mtype: MemoryHeapType,
+    pub(crate) alignment: usize,
+    pub(crate) allocator: Arc<Mutex<TlsfAllocator>>,
+    pub(crate) memory: Option<MemoryTrack>,
 }
 
 impl MemoryHeap {
     pub(crate) fn inner_new(device: Device, size: usize, mtype: MemoryHeapType) -> Self {
+        let size = size.next_multiple_of(DEFAULT_PLACEMENT_ALIGNMENT);
+
+        let (alignment, raw_alignment) = match mtype {
+            MemoryHeapType::Gpu | MemoryHeapType::Shared => (
+                MSAA_PLACEMENT_ALIGNMENT,
+                dx::HeapAlignment::MsaaResourcePlacement,
+            ),
+            MemoryHeapType::Cpu | MemoryHeapType::Readback => (
+                DEFAULT_PLACEMENT_ALIGNMENT,
+                dx::HeapAlignment::ResourcePlacement,
+            ),
+        };
+
         let desc = dx::HeapDesc::new(
             size,
             dx::HeapProperties::new(
@@ -31,61 +51,123 @@ impl MemoryHeap {
                 dx::MemoryPool::Unknown,
             ),
         )
-        .with_alignment(dx::HeapAlignment::ResourcePlacement)
+        .with_alignment(raw_alignment)
         .with_flags(mtype.flags());
 
         let heap = device.raw.create_heap(&desc).unwrap();
+        let memory = device.memory.track(mtype, MemoryKind::Heap, size);
 
         Self(Arc::new(MemoryHeapInner {
             device,
             heap,
             size,
             mtype,
+            alignment,
+            allocator: Arc::new(Mutex::new(TlsfAllocator::new(
+                size,
+                DEFAULT_PLACEMENT_ALIGNMENT,
+            ))),
+            memory: Some(memory),
         }))
     }
 
+    pub(crate) fn allocate_for(&self, desc: &dx::ResourceDesc) -> Option<Allocation> {
+        let info = self
+            .device
+            .raw
+            .get_resource_allocation_info(0, std::slice::from_ref(desc));
+
+        self.allocate(info.size_in_bytes() as usize, info.alignment() as usize)
+    }
+
+    pub(crate) fn share_allocation(&self, allocation: &Allocation) -> Allocation {
+        assert!(Arc::ptr_eq(&self.allocator, &allocation.block.allocator));
+
+        Allocation {
+            heap: self.clone(),
+            offset: allocation.offset,
+            size: allocation.size,
+            block: Arc::clone(&allocation.block),
+        }
+    }
+
     pub(crate) fn create_placed_buffer<R: BufferResource>(
         &self,
         desc: R::Desc,
-        offset: usize,
         access: R::Access,
         initial_state: ResourceStates,
-    ) -> R {
-        R::from_raw_placed(
-            self,
-            desc,
-            access,
-            initial_state,
-            Allocation {
-                heap: self.clone(),
-                offset,
-                size: self.size,
-            },
-        )
+    ) -> Option<R> {
+        let allocation = self.allocate_for(&desc.clone().into())?;
+
+        Some(self.place_buffer(desc, allocation, access, initial_state))
     }
 
     pub(crate) fn create_placed_texture<R: ImageResource>(
         &self,
         desc: R::Desc,
-        offset: usize,
+        access: R::Access,
+        initial_state: ResourceStates,
+    ) -> Option<R> {
+        let allocation = self.allocate_for(&desc.clone().into())?;
+
+        Some(self.place_texture(desc, allocation, access, initial_state))
+    }
+
+    pub(crate) fn place_buffer<R: BufferResource>(
+        &self,
+        desc: R::Desc,
+        allocation: Allocation,
         access: R::Access,
         initial_state: ResourceStates,
     ) -> R {
-        R::from_raw_placed(
-            self,
-            desc,
-            access,
-            initial_state,
-            Allocation {
-                heap: self.clone(),
-                offset,
-                size: self.size,
-            },
-        )
+        assert!(Arc::ptr_eq(&self.0, &allocation.heap.0));
+
+        R::from_raw_placed(self, desc, access, initial_state, allocation)
+    }
+
+    pub(crate) fn place_texture<R: ImageResource>(
+        &self,
+        desc: R::Desc,
+        allocation: Allocation,
+        access: R::Access,
+        initial_state: ResourceStates,
+    ) -> R {
+        assert!(Arc::ptr_eq(&self.0, &allocation.heap.0));
+
+        R::from_raw_placed(self, desc, access, initial_state, allocation)
     }
 }
 
 impl MemoryHeap {
+    pub fn allocate(&self, size: usize, alignment: usize) -> Option<Allocation> {
+        assert!(
+            alignment <= self.alignment,
+            "MemoryHeap: alignment {} is bigger than heap alignment {}",
+            alignment,
+            self.alignment
+        );
+
+        let block = self.allocator.lock().allocate(size, alignment)?;
+
+        Some(Allocation {
+            heap: self.clone(),
+            offset: block.offset,
+            size: block.size,
+            block: Arc::new(AllocationBlock {
+                allocator: Arc::clone(&self.allocator),
+                id: block.id,
+                memory: self
+                    .device
+                    .memory
+                    .track(self.mtype, MemoryKind::Placed, block.size),
+            }),
+        })
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        self.allocator.lock().stats()
+    }
+
     pub fn connect(&self, device: Device) -> MemoryHeap {
         assert!(self.mtype == MemoryHeapType::Shared);
 
@@ -102,6 +184,9 @@ impl MemoryHeap {
             heap,
             size: self.size,
             mtype: MemoryHeapType::Shared,
+            alignment: self.alignment,
+            allocator: Arc::clone(&self.allocator),
+            memory: None,
         }))
     }
 }
@@ -1,5 +1,15 @@
+mod aliasing;
 mod allocation;
+mod budget;
 mod heap;
+mod tlsf;
+mod transient;
+mod upload_ring;
 
+pub use aliasing::*;
 pub use allocation::*;
+pub use budget::*;
 pub use heap::*;
+pub use tlsf::*;
+pub use transient::*;
+pub use upload_ring::*;
@@ -0,0 +1,536 @@
+pub const DEFAULT_PLACEMENT_ALIGNMENT: usize = 64 * 1024;
+pub const MSAA_PLACEMENT_ALIGNMENT: usize = 4 * 1024 * 1024;
+
+const SL_BITS: u32 = 4;
+const SL_COUNT: usize = 1 << SL_BITS;
+const FL_COUNT: usize = (usize::BITS - SL_BITS + 1) as usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockId(u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TlsfBlock {
+    pub id: BlockId,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    pub total_size: usize,
+    pub used_size: usize,
+    pub free_size: usize,
+    pub allocation_count: usize,
+    pub free_block_count: usize,
+    pub largest_free_block: usize,
+}
+
+impl HeapStats {
+    pub fn fragmentation(&self) -> f32 {
+        if self.free_size == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free_block as f32 / self.free_size as f32
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Block {
+    offset: usize,
+    size: usize,
+    free: bool,
+
+    prev_phys: Option<u32>,
+    next_phys: Option<u32>,
+
+    prev_free: Option<u32>,
+    next_free: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct TlsfAllocator {
+    size: usize,
+    granularity: usize,
+
+    blocks: Vec<Block>,
+    unused_blocks: Vec<u32>,
+
+    fl_bitmap: u64,
+    sl_bitmap: [u32; FL_COUNT],
+    heads: [[Option<u32>; SL_COUNT]; FL_COUNT],
+
+    used_size: usize,
+    allocation_count: usize,
+}
+
+impl TlsfAllocator {
+    pub fn new(size: usize, granularity: usize) -> Self {
+        assert!(granularity.is_power_of_two());
+
+        let size = size - size % granularity;
+
+        let mut allocator = Self {
+            size,
+            granularity,
+            blocks: vec![],
+            unused_blocks: vec![],
+            fl_bitmap: 0,
+            sl_bitmap: [0; FL_COUNT],
+            heads: [[None; SL_COUNT]; FL_COUNT],
+            used_size: 0,
+            allocation_count: 0,
+        };
+
+        if size > 0 {
+            let index = allocator.new_block(Block {
+                offset: 0,
+                size,
+                free: true,
+                prev_phys: None,
+                next_phys: None,
+                prev_free: None,
+                next_free: None,
+            });
+            allocator.insert_free(index);
+        }
+
+        allocator
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn granularity(&self) -> usize {
+        self.granularity
+    }
+
+    pub fn allocate(&mut self, size: usize, alignment: usize) -> Option<TlsfBlock> {
+        assert!(alignment.is_power_of_two());
+
+        if size == 0 || size > self.size {
+            return None;
+        }
+
+        let alignment = alignment.max(self.granularity);
+        let size = size.next_multiple_of(self.granularity);
+
+        let index = self
+            .find_suitable(size + alignment - self.granularity)
+            .or_else(|| self.scan_suitable(size, alignment))?;
+
+        self.remove_free(index);
+
+        let offset = self.blocks[index as usize].offset;
+        let aligned = offset.next_multiple_of(alignment);
+
+        let index = if aligned > offset {
+            let (front, back) = self.split(index, aligned - offset);
+            self.insert_free(front);
+            back
+        } else {
+            index
+        };
+
+        if self.blocks[index as usize].size > size {
+            let (front, back) = self.split(index, size);
+            self.insert_free(back);
+            self.blocks[front as usize].free = false;
+            self.commit(front)
+        } else {
+            self.blocks[index as usize].free = false;
+            self.commit(index)
+        }
+    }
+
+    pub fn free(&mut self, id: BlockId) {
+        let index = id.0;
+        let block = &mut self.blocks[index as usize];
+
+        assert!(
+            !block.free && block.size != 0,
+            "TlsfAllocator: double free of block {}",
+            index
+        );
+
+        block.free = true;
+        self.used_size -= block.size;
+        self.allocation_count -= 1;
+
+        let mut index = index;
+
+        if let Some(prev) = self.blocks[index as usize].prev_phys {
+            if self.blocks[prev as usize].free {
+                self.remove_free(prev);
+                index = self.merge(prev, index);
+            }
+        }
+
+        if let Some(next) = self.blocks[index as usize].next_phys {
+            if self.blocks[next as usize].free {
+                self.remove_free(next);
+                index = self.merge(index, next);
+            }
+        }
+
+        self.insert_free(index);
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        let mut free_block_count = 0;
+        let mut largest_free_block = 0;
+
+        for fl in 0..FL_COUNT {
+            for sl in 0..SL_COUNT {
+                let mut cursor = self.heads[fl][sl];
+
+                while let Some(index) = cursor {
+                    let block = &self.blocks[index as usize];
+                    free_block_count += 1;
+                    largest_free_block = largest_free_block.max(block.size);
+                    cursor = block.next_free;
+                }
+            }
+        }
+
+        HeapStats {
+            total_size: self.size,
+            used_size: self.used_size,
+            free_size: self.size - self.used_size,
+            allocation_count: self.allocation_count,
+            free_block_count,
+            largest_free_block,
+        }
+    }
+}
+
+impl TlsfAllocator {
+    fn commit(&mut self, index: u32) -> Option<TlsfBlock> {
+        let block = &self.blocks[index as usize];
+
+        self.used_size += block.size;
+        self.allocation_count += 1;
+
+        Some(TlsfBlock {
+            id: BlockId(index),
+            offset: block.offset,
+            size: block.size,
+        })
+    }
+
+    fn mapping(&self, size: usize) -> (usize, usize) {
+        let units = size / self.granularity;
+
+        if units < SL_COUNT {
+            (0, units)
+        } else {
+            let log2 = usize::BITS - 1 - units.leading_zeros();
+            let fl = (log2 - SL_BITS + 1) as usize;
+            let sl = (units >> (log2 - SL_BITS)) - SL_COUNT;
+
+            (fl, sl)
+        }
+    }
+
+    fn find_suitable(&self, size: usize) -> Option<u32> {
+        let units = size / self.granularity;
+
+        let rounded = if units < SL_COUNT {
+            units
+        } else {
+            let log2 = usize::BITS - 1 - units.leading_zeros();
+            units.checked_add((1 << (log2 - SL_BITS)) - 1)?
+        };
+
+        let (fl, sl) = self.mapping(rounded.checked_mul(self.granularity)?);
+
+        if fl >= FL_COUNT {
+            return None;
+        }
+
+        let sl_map = self.sl_bitmap[fl] & (u32::MAX << sl);
+
+        let (fl, sl_map) = if sl_map != 0 {
+            (fl, sl_map)
+        } else {
+            let fl_map = self.fl_bitmap & u64::MAX.checked_shl(fl as u32 + 1).unwrap_or(0);
+
+            if fl_map == 0 {
+                return None;
+            }
+
+            let fl = fl_map.trailing_zeros() as usize;
+            (fl, self.sl_bitmap[fl])
+        };
+
+        self.heads[fl][sl_map.trailing_zeros() as usize]
+    }
+
+    fn scan_suitable(&self, size: usize, alignment: usize) -> Option<u32> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .find(|(_, block)| {
+                let padding = block.offset.next_multiple_of(alignment) - block.offset;
+                block.free && block.size >= padding + size
+            })
+            .map(|(i, _)| i as u32)
+    }
+
+    fn new_block(&mut self, block: Block) -> u32 {
+        if let Some(index) = self.unused_blocks.pop() {
+            self.blocks[index as usize] = block;
+            index
+        } else {
+            self.blocks.push(block);
+            (self.blocks.len() - 1) as u32
+        }
+    }
+
+    fn split(&mut self, index: u32, size: usize) -> (u32, u32) {
+        let block = &mut self.blocks[index as usize];
+        debug_assert!(block.size > size);
+
+        let rest = Block {
+            offset: block.offset + size,
+            size: block.size - size,
+            free: block.free,
+            prev_phys: Some(index),
+            next_phys: block.next_phys,
+            prev_free: None,
+            next_free: None,
+        };
+        block.size = size;
+
+        let rest = self.new_block(rest);
+
+        if let Some(next) = self.blocks[rest as usize].next_phys {
+            self.blocks[next as usize].prev_phys = Some(rest);
+        }
+        self.blocks[index as usize].next_phys = Some(rest);
+
+        (index, rest)
+    }
+
+    fn merge(&mut self, front: u32, back: u32) -> u32 {
+        let back_block = &mut self.blocks[back as usize];
+        let (size, next_phys) = (back_block.size, back_block.next_phys);
+
+        back_block.free = false;
+        back_block.size = 0;
+        self.unused_blocks.push(back);
+
+        let block = &mut self.blocks[front as usize];
+        block.size += size;
+        block.next_phys = next_phys;
+
+        if let Some(next) = next_phys {
+            self.blocks[next as usize].prev_phys = Some(front);
+        }
+
+        front
+    }
+
+    fn insert_free(&mut self, index: u32) {
+        let (fl, sl) = self.mapping(self.blocks[index as usize].size);
+        let head = self.heads[fl][sl];
+
+        let block = &mut self.blocks[index as usize];
+        block.free = true;
+        block.prev_free = None;
+        block.next_free = head;
+
+        if let Some(head) = head {
+            self.blocks[head as usize].prev_free = Some(index);
+        }
+
+        self.heads[fl][sl] = Some(index);
+        self.fl_bitmap |= 1 << fl;
+        self.sl_bitmap[fl] |= 1 << sl;
+    }
+
+    fn remove_free(&mut self, index: u32) {
+        let (fl, sl) = self.mapping(self.blocks[index as usize].size);
+        let block = &mut self.blocks[index as usize];
+
+        let prev = block.prev_free.take();
+        let next = block.next_free.take();
+
+        if let Some(prev) = prev {
+            self.blocks[prev as usize].next_free = next;
+        } else {
+            self.heads[fl][sl] = next;
+
+            if next.is_none() {
+                self.sl_bitmap[fl] &= !(1 << sl);
+
+                if self.sl_bitmap[fl] == 0 {
+                    self.fl_bitmap &= !(1 << fl);
+                }
+            }
+        }
+
+        if let Some(next) = next {
+            self.blocks[next as usize].prev_free = prev;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{TlsfAllocator, TlsfBlock, DEFAULT_PLACEMENT_ALIGNMENT, MSAA_PLACEMENT_ALIGNMENT};
+
+    fn check_invariants(allocator: &TlsfAllocator, live: &[TlsfBlock], alignments: &[usize]) {
+        let mut sorted = live.to_vec();
+        sorted.sort_by_key(|b| b.offset);
+
+        for pair in sorted.windows(2) {
+            assert!(pair[0].offset + pair[0].size <= pair[1].offset);
+        }
+
+        for (block, alignment) in live.iter().zip(alignments) {
+            assert_eq!(block.offset % alignment, 0);
+            assert!(block.offset + block.size <= allocator.size());
+        }
+
+        let stats = allocator.stats();
+        assert_eq!(stats.used_size, live.iter().map(|b| b.size).sum::<usize>());
+        assert_eq!(stats.allocation_count, live.len());
+        assert_eq!(stats.used_size + stats.free_size, stats.total_size);
+    }
+
+    #[test]
+    fn test_full_heap() {
+        let mut allocator =
+            TlsfAllocator::new(16 * MSAA_PLACEMENT_ALIGNMENT, DEFAULT_PLACEMENT_ALIGNMENT);
+
+        let block = allocator
+            .allocate(16 * MSAA_PLACEMENT_ALIGNMENT, MSAA_PLACEMENT_ALIGNMENT)
+            .unwrap();
+        assert_eq!(block.offset, 0);
+        assert!(allocator.allocate(1, DEFAULT_PLACEMENT_ALIGNMENT).is_none());
+
+        allocator.free(block.id);
+
+        let stats = allocator.stats();
+        assert_eq!(stats.free_block_count, 1);
+        assert_eq!(stats.largest_free_block, 16 * MSAA_PLACEMENT_ALIGNMENT);
+    }
+
+    #[test]
+    fn test_rounds_to_granularity() {
+        let mut allocator = TlsfAllocator::new(1024 * 1024, DEFAULT_PLACEMENT_ALIGNMENT);
+
+        let block = allocator.allocate(1, 1).unwrap();
+        assert_eq!(block.size, DEFAULT_PLACEMENT_ALIGNMENT);
+
+        let block = allocator
+            .allocate(DEFAULT_PLACEMENT_ALIGNMENT + 1, 1)
+            .unwrap();
+        assert_eq!(block.size, 2 * DEFAULT_PLACEMENT_ALIGNMENT);
+        assert_eq!(block.offset, DEFAULT_PLACEMENT_ALIGNMENT);
+    }
+
+    #[test]
+    fn test_msaa_alignment_leaves_reusable_padding() {
+        let mut allocator =
+            TlsfAllocator::new(2 * MSAA_PLACEMENT_ALIGNMENT, DEFAULT_PLACEMENT_ALIGNMENT);
+
+        let small = allocator.allocate(1, DEFAULT_PLACEMENT_ALIGNMENT).unwrap();
+        let msaa = allocator
+            .allocate(MSAA_PLACEMENT_ALIGNMENT, MSAA_PLACEMENT_ALIGNMENT)
+            .unwrap();
+        assert_eq!(msaa.offset, MSAA_PLACEMENT_ALIGNMENT);
+
+        let padding = allocator
+            .allocate(MSAA_PLACEMENT_ALIGNMENT - DEFAULT_PLACEMENT_ALIGNMENT, 1)
+            .unwrap();
+        assert_eq!(padding.offset, DEFAULT_PLACEMENT_ALIGNMENT);
+
+        allocator.free(small.id);
+        allocator.free(msaa.id);
+        allocator.free(padding.id);
+
+        assert_eq!(allocator.stats().free_block_count, 1);
+    }
+
+    #[test]
+    fn test_fragmentation() {
+        let mut allocator =
+            TlsfAllocator::new(4 * DEFAULT_PLACEMENT_ALIGNMENT, DEFAULT_PLACEMENT_ALIGNMENT);
+
+        let blocks = (0..4)
+            .map(|_| allocator.allocate(1, 1).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(allocator.stats().fragmentation(), 0.0);
+
+        allocator.free(blocks[0].id);
+        allocator.free(blocks[2].id);
+
+        let stats = allocator.stats();
+        assert_eq!(stats.free_block_count, 2);
+        assert_eq!(stats.fragmentation(), 0.5);
+        assert!(allocator
+            .allocate(2 * DEFAULT_PLACEMENT_ALIGNMENT, 1)
+            .is_none());
+
+        allocator.free(blocks[1].id);
+        assert!(allocator
+            .allocate(3 * DEFAULT_PLACEMENT_ALIGNMENT, 1)
+            .is_some());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_double_free() {
+        let mut allocator = TlsfAllocator::new(1024, 16);
+
+        let block = allocator.allocate(16, 16).unwrap();
+        allocator.free(block.id);
+        allocator.free(block.id);
+    }
+
+    #[test]
+    fn test_random_alloc_free() {
+        const GRANULARITY: usize = 16;
+        const SIZE: usize = GRANULARITY * 4096;
+
+        for seed in 0..16 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut allocator = TlsfAllocator::new(SIZE, GRANULARITY);
+
+            let mut live = vec![];
+            let mut alignments = vec![];
+
+            for _ in 0..2000 {
+                if live.is_empty() || rng.gen_bool(0.6) {
+                    let size = rng.gen_range(1..=SIZE / 32);
+                    let alignment = GRANULARITY << rng.gen_range(0..6);
+
+                    if let Some(block) = allocator.allocate(size, alignment) {
+                        assert!(block.size >= size);
+                        live.push(block);
+                        alignments.push(alignment);
+                    }
+                } else {
+                    let index = rng.gen_range(0..live.len());
+                    allocator.free(live.swap_remove(index).id);
+                    alignments.swap_remove(index);
+                }
+
+                check_invariants(&allocator, &live, &alignments);
+            }
+
+            for block in live.drain(..) {
+                allocator.free(block.id);
+            }
+
+            let stats = allocator.stats();
+            assert_eq!(stats.free_block_count, 1);
+            assert_eq!(stats.largest_free_block, SIZE);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use oxidx::dx::{self, IDevice};
+use smallvec::SmallVec;
+
+use crate::graphics::{
+    device::Device,
+    resources::{Image, ImageDesc, Resource, ViewAccess},
+    types::MemoryHeapType,
+    ResourceStates,
+};
+
+use super::{AliasingPlan, Allocation, MemoryHeap, TransientRequest};
+
+#[derive(Clone, Debug)]
+pub struct TransientImageDesc {
+    desc: ImageDesc,
+    first_pass: usize,
+    last_pass: usize,
+    state: ResourceStates,
+}
+
+impl TransientImageDesc {
+    pub fn new(desc: ImageDesc, first_pass: usize, last_pass: usize) -> Self {
+        Self {
+            desc,
+            first_pass,
+            last_pass,
+            state: ResourceStates::Common,
+        }
+    }
+
+    pub fn with_state(mut self, state: ResourceStates) -> Self {
+        self.state = state;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct TransientPool {
+    heap: Option<MemoryHeap>,
+    allocation: Option<Allocation>,
+    plan: AliasingPlan,
+    images: Vec<Image>,
+}
+
+impl TransientPool {
+    pub(crate) fn inner_new(
+        device: &Device,
+        descs: &[TransientImageDesc],
+        access: ViewAccess,
+    ) -> Self {
+        let requests = descs
+            .iter()
+            .map(|desc| {
+                let raw_desc: dx::ResourceDesc = desc.desc.clone().into();
+                let info = device
+                    .raw
+                    .get_resource_allocation_info(0, std::slice::from_ref(&raw_desc));
+
+                TransientRequest::new(
+                    info.size_in_bytes() as usize,
+                    info.alignment() as usize,
+                    desc.first_pass,
+                    desc.last_pass,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let plan = AliasingPlan::new(&requests);
+
+        if requests.is_empty() {
+            return Self {
+                heap: None,
+                allocation: None,
+                plan,
+                images: vec![],
+            };
+        }
+
+        let heap = device.create_heap(plan.heap_size(), MemoryHeapType::Gpu);
+        let allocation = heap
+            .allocate(plan.heap_size(), heap.alignment)
+            .expect("TransientPool: heap is too small for aliasing plan");
+
+        let images = descs
+            .iter()
+            .zip(requests.iter())
+            .enumerate()
+            .map(|(i, (desc, request))| {
+                heap.place_texture(
+                    desc.desc.clone(),
+                    allocation.sub_allocation(plan.offset(i), request.size),
+                    access.clone(),
+                    desc.state,
+                )
+            })
+            .collect();
+
+        Self {
+            heap: Some(heap),
+            allocation: Some(allocation),
+            plan,
+            images,
+        }
+    }
+}
+
+impl TransientPool {
+    pub fn image(&self, index: usize) -> &Image {
+        &self.images[index]
+    }
+
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    pub fn plan(&self) -> &AliasingPlan {
+        &self.plan
+    }
+
+    pub fn heap(&self) -> Option<&MemoryHeap> {
+        self.heap.as_ref()
+    }
+
+    pub fn size(&self) -> usize {
+        self.allocation.as_ref().map_or(0, |a| a.size)
+    }
+
+    pub fn aliasing_barriers(&self, pass: usize) -> SmallVec<[dx::ResourceBarrier<'_>; 4]> {
+        self.plan
+            .barriers_for_pass(pass)
+            .map(|barrier| {
+                dx::ResourceBarrier::aliasing(
+                    barrier.before.map(|i| self.images[i].get_raw()),
+                    Some(self.images[barrier.after].get_raw()),
+                )
+            })
+            .collect()
+    }
+}
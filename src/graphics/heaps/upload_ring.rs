@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FrameMark {
+    fence_value: u64,
+    end: usize,
+    bytes: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct UploadRing {
+    capacity: usize,
+    head: usize,
+    tail: usize,
+    used: usize,
+    frame_bytes: usize,
+    frames: VecDeque<FrameMark>,
+}
+
+impl UploadRing {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "UploadRing: capacity must be non zero");
+
+        Self {
+            capacity,
+            head: 0,
+            tail: 0,
+            used: 0,
+            frame_bytes: 0,
+            frames: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn pending_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn allocate(&mut self, size: usize, alignment: usize) -> Option<usize> {
+        assert!(alignment.is_power_of_two());
+
+        if size == 0 || size > self.capacity {
+            return None;
+        }
+
+        if self.used == 0 {
+            self.head = 0;
+            self.tail = 0;
+
+            for frame in &mut self.frames {
+                frame.end = 0;
+            }
+        }
+
+        let start = self.head.next_multiple_of(alignment);
+
+        let offset = if self.used == 0 || self.head > self.tail {
+            if start + size <= self.capacity {
+                start
+            } else if size <= self.tail {
+                0
+            } else {
+                return None;
+            }
+        } else if start + size <= self.tail {
+            start
+        } else {
+            return None;
+        };
+
+        let consumed = if offset < self.head {
+            self.capacity - self.head + size
+        } else {
+            offset + size - self.head
+        };
+
+        self.head = offset + size;
+        self.used += consumed;
+        self.frame_bytes += consumed;
+
+        Some(offset)
+    }
+
+    pub fn end_frame(&mut self, fence_value: u64) {
+        if let Some(last) = self.frames.back() {
+            assert!(
+                last.fence_value <= fence_value,
+                "UploadRing: fence values must be monotonic"
+            );
+        }
+
+        self.frames.push_back(FrameMark {
+            fence_value,
+            end: self.head,
+            bytes: std::mem::take(&mut self.frame_bytes),
+        });
+    }
+
+    pub fn release(&mut self, completed_value: u64) {
+        while let Some(frame) = self
+            .frames
+            .front()
+            .filter(|f| f.fence_value <= completed_value)
+        {
+            if frame.bytes > 0 {
+                self.tail = frame.end;
+            }
+
+            self.used -= frame.bytes;
+            self.frames.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UploadRing;
+
+    #[test]
+    fn test_allocates_linearly_with_alignment() {
+        let mut ring = UploadRing::new(1024);
+
+        assert_eq!(ring.allocate(10, 1), Some(0));
+        assert_eq!(ring.allocate(10, 256), Some(256));
+        assert_eq!(ring.used(), 266);
+    }
+
+    #[test]
+    fn test_full_ring_waits_for_fence() {
+        let mut ring = UploadRing::new(1024);
+
+        assert_eq!(ring.allocate(512, 256), Some(0));
+        ring.end_frame(1);
+        assert_eq!(ring.allocate(512, 256), Some(512));
+        ring.end_frame(2);
+
+        assert_eq!(ring.allocate(256, 256), None);
+
+        ring.release(1);
+        assert_eq!(ring.allocate(256, 256), Some(0));
+        assert_eq!(ring.allocate(512, 256), None);
+    }
+
+    #[test]
+    fn test_wraps_around() {
+        let mut ring = UploadRing::new(1000);
+
+        assert_eq!(ring.allocate(600, 1), Some(0));
+        ring.end_frame(1);
+        assert_eq!(ring.allocate(300, 1), Some(600));
+        ring.end_frame(2);
+
+        ring.release(1);
+
+        assert_eq!(ring.allocate(200, 1), Some(0));
+        assert_eq!(ring.used(), 300 + 100 + 200);
+
+        ring.end_frame(3);
+        ring.release(3);
+
+        assert_eq!(ring.used(), 0);
+        assert_eq!(ring.pending_frames(), 0);
+    }
+
+    #[test]
+    fn test_empty_frames_do_not_move_tail() {
+        let mut ring = UploadRing::new(1000);
+
+        assert_eq!(ring.allocate(600, 1), Some(0));
+        ring.end_frame(1);
+        ring.end_frame(2);
+        ring.release(1);
+
+        assert_eq!(ring.allocate(800, 1), Some(0));
+        ring.end_frame(3);
+        ring.release(2);
+
+        assert_eq!(ring.allocate(100, 1), Some(800));
+        assert_eq!(ring.allocate(150, 1), None);
+    }
+
+    #[test]
+    fn test_never_overlaps_in_flight_frames() {
+        let mut ring = UploadRing::new(4096);
+        let mut live: Vec<(u64, usize, usize)> = vec![];
+
+        for frame in 0..200u64 {
+            if frame >= 2 {
+                ring.release(frame - 2);
+                live.retain(|(f, _, _)| f + 1 > frame - 2);
+            }
+
+            for i in 0..(frame % 5 + 1) {
+                let size = 64 + ((frame * 37 + i * 101) % 700) as usize;
+
+                if let Some(offset) = ring.allocate(size, 64) {
+                    assert_eq!(offset % 64, 0);
+                    assert!(offset + size <= ring.capacity());
+                    assert!(live
+                        .iter()
+                        .all(|(_, o, s)| offset + size <= *o || *o + *s <= offset));
+
+                    live.push((frame, offset, size));
+                }
+            }
+
+            ring.end_frame(frame + 1);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{collections::HashMap, time::Instant};
+
+use crate::graphics::{
+    commands::{CommandQueue, Direct, Transfer, WorkerThread, WorkerType},
+    device::Device,
+    fence::Fence,
+    fence_wait::{token, wait_all},
+    resources::{Image, SharedResource},
+    QueueType,
+};
+
+use super::{
+    FenceId, FramePacer, FramePlan, FrameScheduler, GpuTask, MultiGpuMode, TaskKind, PRIMARY_GPU,
+    SECONDARY_GPU,
+};
+
+#[derive(Clone, Debug)]
+pub struct MultiGpuQueues {
+    pub device: Device,
+    pub direct: CommandQueue<Direct>,
+    pub transfer: CommandQueue<Transfer>,
+}
+
+#[derive(Debug)]
+pub struct MultiGpuExecutor {
+    gpus: [MultiGpuQueues; 2],
+    fences: HashMap<FenceId, [Fence; 2]>,
+    scheduler: FrameScheduler,
+    pacer: FramePacer,
+    epoch: Instant,
+}
+
+impl MultiGpuExecutor {
+    pub fn new(
+        primary: MultiGpuQueues,
+        secondary: MultiGpuQueues,
+        mode: MultiGpuMode,
+        frames_in_flight: usize,
+    ) -> Self {
+        let gpus = [primary, secondary];
+
+        let fences = [PRIMARY_GPU, SECONDARY_GPU]
+            .into_iter()
+            .flat_map(|gpu| {
+                [
+                    FenceId::new(gpu, QueueType::Direct),
+                    FenceId::new(gpu, QueueType::Transfer),
+                ]
+            })
+            .map(|id| {
+                let fence = gpus[id.gpu].device.create_shared_fence();
+                let other = fence.connect(gpus[1 - id.gpu].device.clone());
+
+                let fences = if id.gpu == PRIMARY_GPU {
+                    [fence.into(), other.into()]
+                } else {
+                    [other.into(), fence.into()]
+                };
+
+                (id, fences)
+            })
+            .collect();
+
+        Self {
+            gpus,
+            fences,
+            scheduler: FrameScheduler::new(mode, frames_in_flight),
+            pacer: Default::default(),
+            epoch: Instant::now(),
+        }
+    }
+
+    pub fn with_pacer(mut self, pacer: FramePacer) -> Self {
+        self.pacer = pacer;
+        self
+    }
+
+    pub fn mode(&self) -> MultiGpuMode {
+        self.scheduler.mode()
+    }
+
+    pub fn set_mode(&mut self, mode: MultiGpuMode) {
+        self.scheduler.set_mode(mode);
+        self.pacer.reset();
+    }
+
+    pub fn split(&self) -> f32 {
+        self.scheduler.split()
+    }
+
+    pub fn set_split(&mut self, split: f32) {
+        self.scheduler.set_split(split);
+    }
+
+    pub fn report_split_timings(&mut self, primary: f64, secondary: f64) -> f32 {
+        self.scheduler.report_split_timings(primary, secondary)
+    }
+
+    pub fn render_frame(
+        &mut self,
+        targets: &[[SharedResource<Image>; 2]],
+        mut record: impl FnMut(&GpuTask, &WorkerThread<Direct>),
+    ) -> FramePlan {
+        assert!(
+            targets.len() >= self.scheduler.frames_in_flight(),
+            "MultiGpuExecutor: expected a shared target per frame in flight"
+        );
+
+        let plan = self.scheduler.next_frame();
+
+        let cpu_waits = plan
+            .cpu_waits
+            .iter()
+            .map(|wait| {
+                let fence = &self.fences[&wait.fence][wait.fence.gpu];
+                (fence, token(fence, wait.value))
+            })
+            .collect::<Vec<_>>();
+        wait_all(&cpu_waits, None).unwrap();
+
+        for task in plan.tasks.iter() {
+            let gpu = &self.gpus[task.gpu];
+            let fence = &self.fences[&task.fence()][task.gpu];
+
+            let value = match task.queue {
+                QueueType::Direct => {
+                    self.wait_gpu(&gpu.direct, task);
+
+                    let worker = gpu.direct.get_worker_thread(None);
+                    record(task, &worker);
+                    gpu.direct.push_worker(worker);
+                    gpu.direct.execute();
+
+                    gpu.direct.signal_fence(fence)
+                }
+                QueueType::Transfer => {
+                    self.wait_gpu(&gpu.transfer, task);
+
+                    let shared = &targets[task.slot][task.gpu];
+                    let worker = gpu.transfer.get_worker_thread(None);
+
+                    match task.kind {
+                        TaskKind::Push => worker.push_shared_texture(shared),
+                        TaskKind::Pull => worker.pull_shared_texture(shared),
+                        _ => unreachable!(),
+                    }
+
+                    gpu.transfer.push_worker(worker);
+                    gpu.transfer.execute();
+
+                    gpu.transfer.signal_fence(fence)
+                }
+                QueueType::Compute => unreachable!(),
+            };
+
+            assert_eq!(
+                value.value(),
+                task.signal,
+                "MultiGpuExecutor: fence was signaled outside of the executor"
+            );
+        }
+
+        let now = self.epoch.elapsed().as_secs_f64();
+        let present = self.pacer.present_time(now);
+
+        if present > now {
+            std::thread::sleep(std::time::Duration::from_secs_f64(present - now));
+        }
+
+        plan
+    }
+
+    fn wait_gpu<T: WorkerType>(&self, queue: &CommandQueue<T>, task: &GpuTask) {
+        for wait in task.waits.iter().filter(|w| w.fence != task.fence()) {
+            let fence = &self.fences[&wait.fence][task.gpu];
+            queue
+                .wait_fence_value_gpu(fence, token(fence, wait.value))
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod executor;
+mod scheduler;
+mod timeline;
+
+pub use executor::*;
+pub use scheduler::*;
+pub use timeline::*;
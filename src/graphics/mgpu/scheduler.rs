@@ -0,0 +1,555 @@
+use std::collections::{HashMap, VecDeque};
+
+use smallvec::SmallVec;
+
+use crate::graphics::QueueType;
+
+pub const PRIMARY_GPU: usize = 0;
+pub const SECONDARY_GPU: usize = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MultiGpuMode {
+    Afr,
+    Sfr,
+    ShadowOffload,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskKind {
+    Scene,
+    Region { top: f32, bottom: f32 },
+    Shadows,
+    Push,
+    Pull,
+    Composite,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FenceId {
+    pub gpu: usize,
+    pub queue: QueueType,
+}
+
+impl FenceId {
+    pub fn new(gpu: usize, queue: QueueType) -> Self {
+        Self { gpu, queue }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FenceWait {
+    pub fence: FenceId,
+    pub value: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuTask {
+    pub frame: u64,
+    pub gpu: usize,
+    pub queue: QueueType,
+    pub kind: TaskKind,
+    pub slot: usize,
+    pub waits: SmallVec<[FenceWait; 2]>,
+    pub signal: u64,
+}
+
+impl GpuTask {
+    pub fn fence(&self) -> FenceId {
+        FenceId::new(self.gpu, self.queue)
+    }
+
+    pub fn completion(&self) -> FenceWait {
+        FenceWait {
+            fence: self.fence(),
+            value: self.signal,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FramePlan {
+    pub frame: u64,
+    pub mode: MultiGpuMode,
+    pub slot: usize,
+    pub cpu_waits: SmallVec<[FenceWait; 4]>,
+    pub tasks: Vec<GpuTask>,
+}
+
+impl FramePlan {
+    pub fn task(&self, gpu: usize, kind: impl Fn(&TaskKind) -> bool) -> Option<&GpuTask> {
+        self.tasks.iter().find(|t| t.gpu == gpu && kind(&t.kind))
+    }
+
+    pub fn present(&self) -> &GpuTask {
+        self.tasks
+            .last()
+            .filter(|t| t.kind == TaskKind::Composite)
+            .expect("FramePlan: frame has no composite task")
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SplitBalancer {
+    split: f32,
+    min: f32,
+    max: f32,
+    rate: f32,
+}
+
+impl Default for SplitBalancer {
+    fn default() -> Self {
+        Self {
+            split: 0.5,
+            min: 0.1,
+            max: 0.9,
+            rate: 0.25,
+        }
+    }
+}
+
+impl SplitBalancer {
+    pub fn new(split: f32) -> Self {
+        Self::default().with_split(split)
+    }
+
+    pub fn with_limits(mut self, min: f32, max: f32) -> Self {
+        assert!(0.0 < min && min <= max && max < 1.0);
+
+        self.min = min;
+        self.max = max;
+        self.split = self.split.clamp(min, max);
+        self
+    }
+
+    pub fn with_rate(mut self, rate: f32) -> Self {
+        assert!(0.0 < rate && rate <= 1.0);
+
+        self.rate = rate;
+        self
+    }
+
+    pub fn with_split(mut self, split: f32) -> Self {
+        self.split = split.clamp(self.min, self.max);
+        self
+    }
+
+    pub fn split(&self) -> f32 {
+        self.split
+    }
+
+    pub fn update(&mut self, primary: f64, secondary: f64) -> f32 {
+        if primary <= 0.0 || secondary <= 0.0 {
+            return self.split;
+        }
+
+        let primary_cost = primary / self.split as f64;
+        let secondary_cost = secondary / (1.0 - self.split) as f64;
+        let balanced = (secondary_cost / (primary_cost + secondary_cost)) as f32;
+
+        self.split = (self.split + (balanced - self.split) * self.rate).clamp(self.min, self.max);
+        self.split
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FramePacer {
+    smoothing: f64,
+    interval: Option<f64>,
+    last_ready: Option<f64>,
+    last_present: Option<f64>,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new(0.1)
+    }
+}
+
+impl FramePacer {
+    pub fn new(smoothing: f64) -> Self {
+        assert!(0.0 < smoothing && smoothing <= 1.0);
+
+        Self {
+            smoothing,
+            interval: None,
+            last_ready: None,
+            last_present: None,
+        }
+    }
+
+    pub fn interval(&self) -> Option<f64> {
+        self.interval
+    }
+
+    pub fn present_time(&mut self, ready: f64) -> f64 {
+        if let Some(last) = self.last_ready {
+            let delta = (ready - last).max(0.0);
+
+            self.interval = Some(match self.interval {
+                Some(interval) => interval + (delta - interval) * self.smoothing,
+                None => delta,
+            });
+        }
+        self.last_ready = Some(ready);
+
+        let present = match (self.last_present, self.interval) {
+            (Some(last), Some(interval)) => ready.max(last + interval),
+            _ => ready,
+        };
+        self.last_present = Some(present);
+
+        present
+    }
+
+    pub fn reset(&mut self) {
+        self.interval = None;
+        self.last_ready = None;
+        self.last_present = None;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FrameScheduler {
+    mode: MultiGpuMode,
+    frames_in_flight: usize,
+    frame: u64,
+    values: HashMap<FenceId, u64>,
+    history: VecDeque<SmallVec<[FenceWait; 4]>>,
+    last_pull: Vec<Option<FenceWait>>,
+    balancer: SplitBalancer,
+}
+
+impl FrameScheduler {
+    pub fn new(mode: MultiGpuMode, frames_in_flight: usize) -> Self {
+        assert!(frames_in_flight > 0);
+
+        Self {
+            mode,
+            frames_in_flight,
+            frame: 0,
+            values: HashMap::new(),
+            history: VecDeque::new(),
+            last_pull: vec![None; frames_in_flight],
+            balancer: SplitBalancer::default(),
+        }
+    }
+
+    pub fn with_balancer(mut self, balancer: SplitBalancer) -> Self {
+        self.balancer = balancer;
+        self
+    }
+
+    pub fn mode(&self) -> MultiGpuMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: MultiGpuMode) {
+        self.mode = mode;
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames_in_flight
+    }
+
+    pub fn split(&self) -> f32 {
+        self.balancer.split()
+    }
+
+    pub fn set_split(&mut self, split: f32) {
+        self.balancer = self.balancer.with_split(split);
+    }
+
+    pub fn report_split_timings(&mut self, primary: f64, secondary: f64) -> f32 {
+        self.balancer.update(primary, secondary)
+    }
+
+    pub fn next_frame(&mut self) -> FramePlan {
+        let frame = self.frame;
+        let slot = (frame % self.frames_in_flight as u64) as usize;
+
+        let cpu_waits = if self.history.len() == self.frames_in_flight {
+            self.history.pop_front().unwrap()
+        } else {
+            SmallVec::new()
+        };
+
+        let mut builder = PlanBuilder {
+            scheduler: self,
+            frame,
+            slot,
+            tasks: vec![],
+        };
+
+        match builder.scheduler.mode {
+            MultiGpuMode::Afr => {
+                if frame % 2 == PRIMARY_GPU as u64 {
+                    builder.task(PRIMARY_GPU, QueueType::Direct, TaskKind::Scene, &[]);
+                    builder.task(PRIMARY_GPU, QueueType::Direct, TaskKind::Composite, &[]);
+                } else {
+                    let scene =
+                        builder.task(SECONDARY_GPU, QueueType::Direct, TaskKind::Scene, &[]);
+                    let pull = builder.transfer(scene);
+                    builder.task(PRIMARY_GPU, QueueType::Direct, TaskKind::Composite, &[pull]);
+                }
+            }
+            MultiGpuMode::Sfr => {
+                let split = builder.scheduler.balancer.split();
+
+                builder.task(
+                    PRIMARY_GPU,
+                    QueueType::Direct,
+                    TaskKind::Region {
+                        top: 0.0,
+                        bottom: split,
+                    },
+                    &[],
+                );
+                let region = builder.task(
+                    SECONDARY_GPU,
+                    QueueType::Direct,
+                    TaskKind::Region {
+                        top: split,
+                        bottom: 1.0,
+                    },
+                    &[],
+                );
+                let pull = builder.transfer(region);
+                builder.task(PRIMARY_GPU, QueueType::Direct, TaskKind::Composite, &[pull]);
+            }
+            MultiGpuMode::ShadowOffload => {
+                let shadows =
+                    builder.task(SECONDARY_GPU, QueueType::Direct, TaskKind::Shadows, &[]);
+                let pull = builder.transfer(shadows);
+                builder.task(PRIMARY_GPU, QueueType::Direct, TaskKind::Scene, &[pull]);
+                builder.task(PRIMARY_GPU, QueueType::Direct, TaskKind::Composite, &[]);
+            }
+        }
+
+        let tasks = builder.tasks;
+
+        let mut signaled: SmallVec<[FenceWait; 4]> = SmallVec::new();
+        for task in tasks.iter().rev() {
+            if !signaled.iter().any(|w| w.fence == task.fence()) {
+                signaled.push(task.completion());
+            }
+        }
+        self.history.push_back(signaled);
+
+        self.frame += 1;
+
+        FramePlan {
+            frame,
+            mode: self.mode,
+            slot,
+            cpu_waits,
+            tasks,
+        }
+    }
+}
+
+struct PlanBuilder<'a> {
+    scheduler: &'a mut FrameScheduler,
+    frame: u64,
+    slot: usize,
+    tasks: Vec<GpuTask>,
+}
+
+impl PlanBuilder<'_> {
+    fn task(
+        &mut self,
+        gpu: usize,
+        queue: QueueType,
+        kind: TaskKind,
+        waits: &[FenceWait],
+    ) -> FenceWait {
+        let fence = FenceId::new(gpu, queue);
+        let value = self.scheduler.values.entry(fence).or_default();
+        *value += 1;
+
+        let task = GpuTask {
+            frame: self.frame,
+            gpu,
+            queue,
+            kind,
+            slot: self.slot,
+            waits: waits.iter().copied().collect(),
+            signal: *value,
+        };
+        let completion = task.completion();
+
+        self.tasks.push(task);
+
+        completion
+    }
+
+    fn transfer(&mut self, source: FenceWait) -> FenceWait {
+        let from = source.fence.gpu;
+        let to = PRIMARY_GPU;
+
+        let mut waits: SmallVec<[FenceWait; 2]> = SmallVec::new();
+        waits.push(source);
+        waits.extend(self.scheduler.last_pull[self.slot].filter(|pull| pull.fence.gpu != from));
+
+        let push = self.task(from, QueueType::Transfer, TaskKind::Push, &waits);
+        let pull = self.task(to, QueueType::Transfer, TaskKind::Pull, &[push]);
+
+        self.scheduler.last_pull[self.slot] = Some(pull);
+
+        pull
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::QueueType;
+
+    use super::{
+        FenceId, FramePacer, FrameScheduler, MultiGpuMode, SplitBalancer, TaskKind, PRIMARY_GPU,
+        SECONDARY_GPU,
+    };
+
+    #[test]
+    fn test_afr_alternates_gpus() {
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::Afr, 2);
+
+        let even = scheduler.next_frame();
+        assert_eq!(even.tasks.len(), 2);
+        assert!(even.task(PRIMARY_GPU, |k| *k == TaskKind::Scene).is_some());
+        assert_eq!(even.present().gpu, PRIMARY_GPU);
+
+        let odd = scheduler.next_frame();
+        let scene = odd.task(SECONDARY_GPU, |k| *k == TaskKind::Scene).unwrap();
+        let push = odd.task(SECONDARY_GPU, |k| *k == TaskKind::Push).unwrap();
+        let pull = odd.task(PRIMARY_GPU, |k| *k == TaskKind::Pull).unwrap();
+
+        assert_eq!(push.waits.as_slice(), &[scene.completion()]);
+        assert_eq!(pull.waits.as_slice(), &[push.completion()]);
+        assert_eq!(odd.present().waits.as_slice(), &[pull.completion()]);
+    }
+
+    #[test]
+    fn test_sfr_uses_split_line() {
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::Sfr, 2);
+        scheduler.set_split(0.3);
+
+        let plan = scheduler.next_frame();
+
+        assert_eq!(
+            plan.task(PRIMARY_GPU, |k| matches!(k, TaskKind::Region { .. }))
+                .unwrap()
+                .kind,
+            TaskKind::Region {
+                top: 0.0,
+                bottom: 0.3
+            }
+        );
+        assert_eq!(
+            plan.task(SECONDARY_GPU, |k| matches!(k, TaskKind::Region { .. }))
+                .unwrap()
+                .kind,
+            TaskKind::Region {
+                top: 0.3,
+                bottom: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_shadow_offload_scene_waits_for_shadows() {
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::ShadowOffload, 2);
+
+        let plan = scheduler.next_frame();
+        let pull = plan.task(PRIMARY_GPU, |k| *k == TaskKind::Pull).unwrap();
+        let scene = plan.task(PRIMARY_GPU, |k| *k == TaskKind::Scene).unwrap();
+
+        assert_eq!(scene.waits.as_slice(), &[pull.completion()]);
+        assert!(plan
+            .task(SECONDARY_GPU, |k| *k == TaskKind::Shadows)
+            .is_some());
+    }
+
+    #[test]
+    fn test_cpu_waits_for_frames_in_flight() {
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::ShadowOffload, 2);
+
+        let first = scheduler.next_frame();
+        assert!(scheduler.next_frame().cpu_waits.is_empty());
+
+        let third = scheduler.next_frame();
+
+        for task in first.tasks.iter() {
+            assert!(third
+                .cpu_waits
+                .iter()
+                .any(|w| w.fence == task.fence() && w.value >= task.signal));
+        }
+    }
+
+    #[test]
+    fn test_push_waits_for_previous_pull_of_slot() {
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::Sfr, 2);
+
+        let first = scheduler.next_frame();
+        scheduler.next_frame();
+        let third = scheduler.next_frame();
+
+        let pull = first.task(PRIMARY_GPU, |k| *k == TaskKind::Pull).unwrap();
+        let push = third.task(SECONDARY_GPU, |k| *k == TaskKind::Push).unwrap();
+
+        assert_eq!(first.slot, third.slot);
+        assert!(push.waits.contains(&pull.completion()));
+    }
+
+    #[test]
+    fn test_fence_values_are_monotonic_per_queue() {
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::Afr, 3);
+        let direct = FenceId::new(PRIMARY_GPU, QueueType::Direct);
+
+        let values = (0..6)
+            .flat_map(|_| scheduler.next_frame().tasks)
+            .filter(|t| t.fence() == direct)
+            .map(|t| t.signal)
+            .collect::<Vec<_>>();
+
+        assert_eq!(values[0], 1);
+        assert!(values.windows(2).all(|w| w[1] == w[0] + 1));
+    }
+
+    #[test]
+    fn test_split_balancer_converges() {
+        let mut balancer = SplitBalancer::new(0.5).with_rate(0.5);
+
+        for _ in 0..32 {
+            let split = balancer.split() as f64;
+            balancer.update(8.0 * split, 16.0 * (1.0 - split));
+        }
+
+        assert!((balancer.split() - 2.0 / 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_split_balancer_respects_limits() {
+        let mut balancer = SplitBalancer::new(0.5).with_limits(0.2, 0.8).with_rate(1.0);
+
+        balancer.update(1.0, 1000.0);
+        assert_eq!(balancer.split(), 0.8);
+    }
+
+    #[test]
+    fn test_pacer_spreads_presents() {
+        let mut pacer = FramePacer::new(0.1);
+
+        let presents = (0..64)
+            .map(|i| pacer.present_time((i / 2) as f64 * 10.0 + (i % 2) as f64))
+            .collect::<Vec<_>>();
+
+        assert!(presents
+            .windows(2)
+            .skip(48)
+            .all(|w| (w[1] - w[0] - 5.0).abs() < 1.0));
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use super::{FenceId, FenceWait, FramePacer, FramePlan, GpuTask, TaskKind};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SimulatedCosts {
+    pub cpu: f64,
+    pub scene: [f64; 2],
+    pub shadows: [f64; 2],
+    pub copy: f64,
+    pub composite: f64,
+}
+
+impl SimulatedCosts {
+    pub fn task(&self, task: &GpuTask) -> f64 {
+        match task.kind {
+            TaskKind::Scene => self.scene[task.gpu],
+            TaskKind::Region { top, bottom } => self.scene[task.gpu] * (bottom - top) as f64,
+            TaskKind::Shadows => self.shadows[task.gpu],
+            TaskKind::Push | TaskKind::Pull => self.copy,
+            TaskKind::Composite => self.composite,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulatedFrame {
+    pub frame: u64,
+    pub cpu_start: f64,
+    pub spans: Vec<(f64, f64)>,
+    pub ready: f64,
+    pub present: f64,
+}
+
+impl SimulatedFrame {
+    pub fn duration(&self, plan: &FramePlan, gpu: usize, kind: impl Fn(&TaskKind) -> bool) -> f64 {
+        plan.tasks
+            .iter()
+            .zip(self.spans.iter())
+            .filter(|(task, _)| task.gpu == gpu && kind(&task.kind))
+            .map(|(_, (start, end))| end - start)
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SimulatedTimeline {
+    costs: SimulatedCosts,
+    cpu: f64,
+    queues: HashMap<FenceId, f64>,
+    signals: HashMap<FenceId, Vec<(u64, f64)>>,
+    pacer: FramePacer,
+}
+
+impl SimulatedTimeline {
+    pub fn new(costs: SimulatedCosts) -> Self {
+        Self {
+            costs,
+            cpu: 0.0,
+            queues: HashMap::new(),
+            signals: HashMap::new(),
+            pacer: FramePacer::default(),
+        }
+    }
+
+    pub fn with_pacer(mut self, pacer: FramePacer) -> Self {
+        self.pacer = pacer;
+        self
+    }
+
+    pub fn set_costs(&mut self, costs: SimulatedCosts) {
+        self.costs = costs;
+    }
+
+    pub fn completion(&self, wait: FenceWait) -> f64 {
+        if wait.value == 0 {
+            return 0.0;
+        }
+
+        self.signals
+            .get(&wait.fence)
+            .and_then(|signals| signals.iter().find(|(value, _)| *value >= wait.value))
+            .map(|(_, time)| *time)
+            .expect("SimulatedTimeline: wait on a value that was never signaled")
+    }
+
+    pub fn submit(&mut self, plan: &FramePlan) -> SimulatedFrame {
+        let cpu_start = plan
+            .cpu_waits
+            .iter()
+            .map(|wait| self.completion(*wait))
+            .fold(self.cpu, f64::max);
+        self.cpu = cpu_start + self.costs.cpu;
+
+        let spans = plan
+            .tasks
+            .iter()
+            .map(|task| {
+                let fence = task.fence();
+
+                let start = task
+                    .waits
+                    .iter()
+                    .map(|wait| self.completion(*wait))
+                    .fold(self.cpu, f64::max)
+                    .max(self.queues.get(&fence).copied().unwrap_or_default());
+                let end = start + self.costs.task(task);
+
+                self.queues.insert(fence, end);
+                self.signals
+                    .entry(fence)
+                    .or_default()
+                    .push((task.signal, end));
+
+                (start, end)
+            })
+            .collect::<Vec<_>>();
+
+        let ready = spans.last().map(|(_, end)| *end).unwrap_or(self.cpu);
+
+        SimulatedFrame {
+            frame: plan.frame,
+            cpu_start,
+            spans,
+            ready,
+            present: self.pacer.present_time(ready),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SimulatedCosts, SimulatedTimeline};
+    use crate::graphics::mgpu::{
+        FrameScheduler, MultiGpuMode, TaskKind, PRIMARY_GPU, SECONDARY_GPU,
+    };
+
+    fn costs() -> SimulatedCosts {
+        SimulatedCosts {
+            cpu: 1.0,
+            scene: [10.0, 10.0],
+            shadows: [4.0, 4.0],
+            copy: 1.0,
+            composite: 0.5,
+        }
+    }
+
+    fn average_interval(mode: MultiGpuMode, costs: SimulatedCosts, frames: usize) -> f64 {
+        let mut scheduler = FrameScheduler::new(mode, 3);
+        let mut timeline = SimulatedTimeline::new(costs);
+
+        let presents = (0..frames)
+            .map(|_| timeline.submit(&scheduler.next_frame()).present)
+            .collect::<Vec<_>>();
+
+        (presents[frames - 1] - presents[frames / 2]) / (frames - 1 - frames / 2) as f64
+    }
+
+    #[test]
+    fn test_afr_doubles_throughput() {
+        let afr = average_interval(MultiGpuMode::Afr, costs(), 64);
+        let single = costs().scene[PRIMARY_GPU] + costs().composite;
+
+        assert!(afr < single * 0.7, "afr interval {}", afr);
+    }
+
+    #[test]
+    fn test_afr_presents_are_paced() {
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::Afr, 3);
+        let mut timeline = SimulatedTimeline::new(costs());
+
+        let presents = (0..96)
+            .map(|_| timeline.submit(&scheduler.next_frame()).present)
+            .collect::<Vec<_>>();
+        let intervals = presents.windows(2).skip(64).map(|w| w[1] - w[0]);
+
+        let (min, max) =
+            intervals.fold((f64::MAX, 0.0f64), |(min, max), i| (min.min(i), max.max(i)));
+
+        assert!(max - min < 1.0, "intervals between {} and {}", min, max);
+    }
+
+    #[test]
+    fn test_sfr_split_balances_asymmetric_gpus() {
+        let costs = SimulatedCosts {
+            scene: [8.0, 16.0],
+            ..costs()
+        };
+
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::Sfr, 2);
+        let mut timeline = SimulatedTimeline::new(costs);
+
+        for _ in 0..32 {
+            let plan = scheduler.next_frame();
+            let frame = timeline.submit(&plan);
+
+            let region = |k: &TaskKind| matches!(k, TaskKind::Region { .. });
+            scheduler.report_split_timings(
+                frame.duration(&plan, PRIMARY_GPU, region),
+                frame.duration(&plan, SECONDARY_GPU, region),
+            );
+        }
+
+        assert!((scheduler.split() - 2.0 / 3.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_shadow_offload_overlaps_gpus() {
+        let costs = SimulatedCosts {
+            scene: [10.0, 10.0],
+            shadows: [6.0, 6.0],
+            ..costs()
+        };
+
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::ShadowOffload, 3);
+        let mut timeline = SimulatedTimeline::new(costs);
+
+        let presents = (0..64)
+            .map(|_| timeline.submit(&scheduler.next_frame()).present)
+            .collect::<Vec<_>>();
+        let interval = (presents[63] - presents[32]) / 31.0;
+
+        assert!(interval < costs.scene[PRIMARY_GPU] + costs.shadows[PRIMARY_GPU]);
+        assert!(interval >= costs.scene[PRIMARY_GPU]);
+    }
+
+    #[test]
+    fn test_frames_in_flight_limit_cpu() {
+        let costs = SimulatedCosts {
+            cpu: 0.1,
+            ..costs()
+        };
+
+        let mut scheduler = FrameScheduler::new(MultiGpuMode::ShadowOffload, 2);
+        let mut timeline = SimulatedTimeline::new(costs);
+
+        let frames = (0..8)
+            .map(|_| {
+                let plan = scheduler.next_frame();
+                (timeline.submit(&plan), plan)
+            })
+            .collect::<Vec<_>>();
+
+        for i in 2..frames.len() {
+            let (frame, _) = &frames[i];
+            let (previous, _) = &frames[i - 2];
+
+            assert!(frame.cpu_start >= previous.ready);
+        }
+    }
+}
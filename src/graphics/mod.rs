@@ -1,7 +1,15 @@
+mod adapter;
+mod adapter_policy;
+mod cache;
+mod capabilities;
 mod commands;
+mod deferred;
 mod device;
 mod fence;
+mod fence_wait;
+mod graph;
 mod heaps;
+mod mgpu;
 mod pipelines;
 mod queries;
 mod resources;
@@ -13,10 +21,18 @@ mod views;
 
 mod utils;
 
+pub use adapter::*;
+pub use adapter_policy::*;
+pub use cache::*;
+pub use capabilities::*;
 pub use commands::*;
+pub use deferred::*;
 pub use device::*;
 pub use fence::*;
+pub use fence_wait::*;
+pub use graph::*;
 pub use heaps::*;
+pub use mgpu::*;
 pub use pipelines::*;
 pub use queries::*;
 pub use resources::*;
@@ -0,0 +1,1101 @@
+use std::fmt;
+
+use oxidx::dx;
+use smallvec::SmallVec;
+
+use crate::graphics::{BindingTable, BindingType, DescriptorFlags, StaticSampler};
+
+pub const UNBOUNDED: u32 = u32::MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResourceKind {
+    ConstantBuffer,
+    ShaderResource,
+    UnorderedAccess,
+    Sampler,
+}
+
+impl ResourceKind {
+    fn register(self) -> char {
+        match self {
+            ResourceKind::ConstantBuffer => 'b',
+            ResourceKind::ShaderResource => 't',
+            ResourceKind::UnorderedAccess => 'u',
+            ResourceKind::Sampler => 's',
+        }
+    }
+}
+
+fn range_end(slot: u32, count: u32) -> u64 {
+    if count == UNBOUNDED {
+        u64::MAX
+    } else {
+        slot as u64 + count as u64
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoundResource {
+    pub name: String,
+    pub kind: ResourceKind,
+    pub slot: u32,
+    pub space: u32,
+    pub count: u32,
+    pub comparison: bool,
+}
+
+impl BoundResource {
+    pub fn new(name: impl Into<String>, kind: ResourceKind, slot: u32, space: u32) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            slot,
+            space,
+            count: 1,
+            comparison: false,
+        }
+    }
+
+    pub fn with_count(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+
+    pub fn with_comparison(mut self) -> Self {
+        self.comparison = true;
+        self
+    }
+
+    fn end(&self) -> u64 {
+        range_end(self.slot, self.count)
+    }
+}
+
+impl fmt::Display for BoundResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` ({}{}, space{})",
+            self.name,
+            self.kind.register(),
+            self.slot,
+            self.space
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CbufferVariable {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CbufferLayout {
+    pub name: String,
+    pub size: u32,
+    pub variables: Vec<CbufferVariable>,
+}
+
+impl CbufferLayout {
+    pub fn variable(&self, name: &str) -> Option<&CbufferVariable> {
+        self.variables.iter().find(|v| v.name == name)
+    }
+
+    pub fn root_constants(&self) -> u32 {
+        self.size.div_ceil(4)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderBindings {
+    pub visibility: dx::ShaderVisibility,
+    pub resources: Vec<BoundResource>,
+    pub cbuffers: Vec<CbufferLayout>,
+}
+
+impl ShaderBindings {
+    pub fn new(visibility: dx::ShaderVisibility) -> Self {
+        Self {
+            visibility,
+            resources: vec![],
+            cbuffers: vec![],
+        }
+    }
+
+    pub fn with_resource(mut self, resource: BoundResource) -> Self {
+        self.resources.push(resource);
+        self
+    }
+
+    pub fn with_cbuffer(mut self, cbuffer: CbufferLayout) -> Self {
+        self.cbuffers.push(cbuffer);
+        self
+    }
+
+    pub fn cbuffer(&self, name: &str) -> Option<&CbufferLayout> {
+        self.cbuffers.iter().find(|c| c.name == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayoutBinding {
+    Cbv {
+        slot: u32,
+        space: u32,
+        flags: DescriptorFlags,
+        visibility: dx::ShaderVisibility,
+    },
+    Srv {
+        slot: u32,
+        space: u32,
+        flags: DescriptorFlags,
+        visibility: dx::ShaderVisibility,
+    },
+    Uav {
+        slot: u32,
+        space: u32,
+        flags: DescriptorFlags,
+        visibility: dx::ShaderVisibility,
+    },
+    PushConstant {
+        slot: u32,
+        space: u32,
+        count: u32,
+        visibility: dx::ShaderVisibility,
+    },
+    Table {
+        entries: Vec<BindingTable>,
+        visibility: dx::ShaderVisibility,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CoveredRange {
+    kind: ResourceKind,
+    slot: u32,
+    space: u32,
+    count: u32,
+    root_constants: Option<u32>,
+}
+
+impl LayoutBinding {
+    pub(crate) fn flags(&self) -> DescriptorFlags {
+        match self {
+            LayoutBinding::Cbv { flags, .. }
+            | LayoutBinding::Srv { flags, .. }
+            | LayoutBinding::Uav { flags, .. } => *flags,
+            LayoutBinding::PushConstant { .. } => DescriptorFlags::empty(),
+            LayoutBinding::Table { entries, .. } => entries
+                .iter()
+                .map(|entry| match *entry {
+                    BindingTable::Cbv { flags, .. }
+                    | BindingTable::Srv { flags, .. }
+                    | BindingTable::Uav { flags, .. }
+                    | BindingTable::Sampler { flags, .. } => flags,
+                })
+                .fold(DescriptorFlags::empty(), |acc, flags| acc | flags),
+        }
+    }
+
+    pub fn from_binding_type(binding: &BindingType<'_>) -> Self {
+        match *binding {
+            BindingType::Cbv {
+                slot,
+                space,
+                flags,
+                visibility,
+            } => LayoutBinding::Cbv {
+                slot,
+                space,
+                flags,
+                visibility,
+            },
+            BindingType::Srv {
+                slot,
+                space,
+                flags,
+                visibility,
+            } => LayoutBinding::Srv {
+                slot,
+                space,
+                flags,
+                visibility,
+            },
+            BindingType::Uav {
+                slot,
+                space,
+                flags,
+                visibility,
+            } => LayoutBinding::Uav {
+                slot,
+                space,
+                flags,
+                visibility,
+            },
+            BindingType::PushConstant {
+                slot,
+                space,
+                count,
+                visibility,
+            } => LayoutBinding::PushConstant {
+                slot,
+                space,
+                count,
+                visibility,
+            },
+            BindingType::Table {
+                entries,
+                visibility,
+            } => LayoutBinding::Table {
+                entries: entries.to_vec(),
+                visibility,
+            },
+        }
+    }
+
+    pub fn as_binding_type(&self) -> BindingType<'_> {
+        match *self {
+            LayoutBinding::Cbv {
+                slot,
+                space,
+                flags,
+                visibility,
+            } => BindingType::Cbv {
+                slot,
+                space,
+                flags,
+                visibility,
+            },
+            LayoutBinding::Srv {
+                slot,
+                space,
+                flags,
+                visibility,
+            } => BindingType::Srv {
+                slot,
+                space,
+                flags,
+                visibility,
+            },
+            LayoutBinding::Uav {
+                slot,
+                space,
+                flags,
+                visibility,
+            } => BindingType::Uav {
+                slot,
+                space,
+                flags,
+                visibility,
+            },
+            LayoutBinding::PushConstant {
+                slot,
+                space,
+                count,
+                visibility,
+            } => BindingType::PushConstant {
+                slot,
+                space,
+                count,
+                visibility,
+            },
+            LayoutBinding::Table {
+                ref entries,
+                visibility,
+            } => BindingType::Table {
+                entries,
+                visibility,
+            },
+        }
+    }
+
+    pub fn visibility(&self) -> dx::ShaderVisibility {
+        match *self {
+            LayoutBinding::Cbv { visibility, .. }
+            | LayoutBinding::Srv { visibility, .. }
+            | LayoutBinding::Uav { visibility, .. }
+            | LayoutBinding::PushConstant { visibility, .. }
+            | LayoutBinding::Table { visibility, .. } => visibility,
+        }
+    }
+
+    fn ranges(&self) -> SmallVec<[CoveredRange; 4]> {
+        let root = |kind, slot, space| CoveredRange {
+            kind,
+            slot,
+            space,
+            count: 1,
+            root_constants: None,
+        };
+
+        match *self {
+            LayoutBinding::Cbv { slot, space, .. } => {
+                smallvec::smallvec![root(ResourceKind::ConstantBuffer, slot, space)]
+            }
+            LayoutBinding::Srv { slot, space, .. } => {
+                smallvec::smallvec![root(ResourceKind::ShaderResource, slot, space)]
+            }
+            LayoutBinding::Uav { slot, space, .. } => {
+                smallvec::smallvec![root(ResourceKind::UnorderedAccess, slot, space)]
+            }
+            LayoutBinding::PushConstant {
+                slot, space, count, ..
+            } => smallvec::smallvec![CoveredRange {
+                root_constants: Some(count),
+                ..root(ResourceKind::ConstantBuffer, slot, space)
+            }],
+            LayoutBinding::Table { ref entries, .. } => entries
+                .iter()
+                .map(|entry| {
+                    let (kind, slot, space, count) = match *entry {
+                        BindingTable::Cbv {
+                            slot, space, count, ..
+                        } => (ResourceKind::ConstantBuffer, slot, space, count),
+                        BindingTable::Srv {
+                            slot, space, count, ..
+                        } => (ResourceKind::ShaderResource, slot, space, count),
+                        BindingTable::Uav {
+                            slot, space, count, ..
+                        } => (ResourceKind::UnorderedAccess, slot, space, count),
+                        BindingTable::Sampler {
+                            slot, space, count, ..
+                        } => (ResourceKind::Sampler, slot, space, count),
+                    };
+
+                    CoveredRange {
+                        count,
+                        ..root(kind, slot, space)
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+fn visibility_rank(visibility: dx::ShaderVisibility) -> u8 {
+    match visibility {
+        dx::ShaderVisibility::All => 0,
+        dx::ShaderVisibility::Vertex => 1,
+        dx::ShaderVisibility::Hull => 2,
+        dx::ShaderVisibility::Domain => 3,
+        dx::ShaderVisibility::Geometry => 4,
+        dx::ShaderVisibility::Pixel => 5,
+        _ => 6,
+    }
+}
+
+fn table_entries(resources: &[(BoundResource, dx::ShaderVisibility)]) -> Vec<BindingTable> {
+    let mut ranges: Vec<BoundResource> = vec![];
+
+    for (resource, _) in resources {
+        let last = ranges.last_mut().filter(|last| {
+            last.kind == resource.kind
+                && last.space == resource.space
+                && resource.slot as u64 <= last.end()
+        });
+
+        match last {
+            Some(last) => {
+                let end = last.end().max(resource.end());
+                last.count = if end == u64::MAX {
+                    UNBOUNDED
+                } else {
+                    (end - last.slot as u64) as u32
+                };
+            }
+            None => ranges.push(resource.clone()),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|r| {
+            let (slot, space, count) = (r.slot, r.space, r.count);
+            let (flags, sampler_flags) = if count == UNBOUNDED {
+                (
+                    DescriptorFlags::DescriptorsVolatile | DescriptorFlags::DataVolatile,
+                    DescriptorFlags::DescriptorsVolatile,
+                )
+            } else {
+                (DescriptorFlags::empty(), DescriptorFlags::empty())
+            };
+
+            match r.kind {
+                ResourceKind::ConstantBuffer => BindingTable::Cbv {
+                    slot,
+                    space,
+                    count,
+                    flags,
+                },
+                ResourceKind::ShaderResource => BindingTable::Srv {
+                    slot,
+                    space,
+                    count,
+                    flags,
+                },
+                ResourceKind::UnorderedAccess => BindingTable::Uav {
+                    slot,
+                    space,
+                    count,
+                    flags,
+                },
+                ResourceKind::Sampler => BindingTable::Sampler {
+                    slot,
+                    space,
+                    count,
+                    flags: sampler_flags,
+                },
+            }
+        })
+        .collect()
+}
+
+fn static_sampler_for<'a>(
+    static_samplers: &'a [StaticSampler],
+    resource: &BoundResource,
+) -> Option<&'a StaticSampler> {
+    if resource.kind != ResourceKind::Sampler {
+        return None;
+    }
+
+    static_samplers
+        .iter()
+        .find(|s| s.slot == resource.slot && s.space == resource.space)
+}
+
+pub fn reflect_layout(
+    stages: &[ShaderBindings],
+    static_samplers: &[StaticSampler],
+) -> Vec<LayoutBinding> {
+    let mut merged: Vec<(BoundResource, dx::ShaderVisibility)> = vec![];
+
+    for stage in stages {
+        for resource in &stage.resources {
+            if static_sampler_for(static_samplers, resource)
+                .is_some_and(|s| s.is_visible_to(stage.visibility))
+            {
+                continue;
+            }
+
+            let existing = merged.iter_mut().find(|(r, _)| {
+                r.kind == resource.kind && r.slot == resource.slot && r.space == resource.space
+            });
+
+            match existing {
+                Some((r, visibility)) => {
+                    r.count = if r.count == UNBOUNDED || resource.count == UNBOUNDED {
+                        UNBOUNDED
+                    } else {
+                        r.count.max(resource.count)
+                    };
+
+                    if *visibility != stage.visibility {
+                        *visibility = dx::ShaderVisibility::All;
+                    }
+                }
+                None => merged.push((resource.clone(), stage.visibility)),
+            }
+        }
+    }
+
+    merged.sort_by_key(|(r, visibility)| (visibility_rank(*visibility), r.kind, r.space, r.slot));
+
+    let is_root = |r: &BoundResource| r.kind == ResourceKind::ConstantBuffer && r.count == 1;
+
+    let mut bindings = merged
+        .iter()
+        .filter(|(r, _)| is_root(r))
+        .map(|(r, visibility)| LayoutBinding::Cbv {
+            slot: r.slot,
+            space: r.space,
+            flags: DescriptorFlags::empty(),
+            visibility: *visibility,
+        })
+        .collect::<Vec<_>>();
+
+    for samplers in [false, true] {
+        let tabled = merged
+            .iter()
+            .filter(|(r, _)| !is_root(r) && (r.kind == ResourceKind::Sampler) == samplers)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for group in tabled.chunk_by(|a, b| a.1 == b.1) {
+            bindings.push(LayoutBinding::Table {
+                entries: table_entries(group),
+                visibility: group[0].1,
+            });
+        }
+    }
+
+    bindings
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayoutError {
+    Missing {
+        resource: BoundResource,
+        stage: dx::ShaderVisibility,
+    },
+    NotVisible {
+        resource: BoundResource,
+        stage: dx::ShaderVisibility,
+        visibility: dx::ShaderVisibility,
+    },
+    CountTooSmall {
+        resource: BoundResource,
+        available: u32,
+    },
+    PushConstantTooSmall {
+        resource: BoundResource,
+        required: u32,
+        available: u32,
+    },
+    ComparisonMismatch {
+        resource: BoundResource,
+        stage: dx::ShaderVisibility,
+    },
+    InvalidFlags {
+        parameter: usize,
+        flags: DescriptorFlags,
+    },
+}
+
+impl LayoutError {
+    fn rank(&self) -> u8 {
+        match self {
+            LayoutError::Missing { .. } => 0,
+            LayoutError::NotVisible { .. } => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Missing { resource, stage } => write!(
+                f,
+                "{} used by the {:?} shader is not bound by the layout",
+                resource, stage
+            ),
+            LayoutError::NotVisible {
+                resource,
+                stage,
+                visibility,
+            } => write!(
+                f,
+                "{} is bound with {:?} visibility but used by the {:?} shader",
+                resource, visibility, stage
+            ),
+            LayoutError::CountTooSmall {
+                resource,
+                available,
+            } => {
+                let count = if resource.count == UNBOUNDED {
+                    "an unbounded number of".to_string()
+                } else {
+                    resource.count.to_string()
+                };
+
+                write!(
+                    f,
+                    "{} needs {} descriptors but the layout provides {}",
+                    resource, count, available
+                )
+            }
+            LayoutError::PushConstantTooSmall {
+                resource,
+                required,
+                available,
+            } => write!(
+                f,
+                "{} needs {} root constants but the layout provides {}",
+                resource, required, available
+            ),
+            LayoutError::ComparisonMismatch { resource, stage } => {
+                let (expected, found) = if resource.comparison {
+                    ("a comparison", "a regular")
+                } else {
+                    ("a regular", "a comparison")
+                };
+
+                write!(
+                    f,
+                    "{} is declared as {} sampler in the {:?} shader \
+                     but the static sampler is {} sampler",
+                    resource, expected, stage, found
+                )
+            }
+            LayoutError::InvalidFlags { parameter, flags } => write!(
+                f,
+                "root parameter {} uses an invalid combination of descriptor flags {:?}",
+                parameter, flags
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+fn check_range(
+    range: &CoveredRange,
+    visibility: dx::ShaderVisibility,
+    stage: &ShaderBindings,
+    resource: &BoundResource,
+) -> Result<(), LayoutError> {
+    if visibility != dx::ShaderVisibility::All && visibility != stage.visibility {
+        return Err(LayoutError::NotVisible {
+            resource: resource.clone(),
+            stage: stage.visibility,
+            visibility,
+        });
+    }
+
+    let end = range_end(range.slot, range.count);
+
+    if resource.end() > end {
+        return Err(LayoutError::CountTooSmall {
+            resource: resource.clone(),
+            available: (end - resource.slot as u64).min(UNBOUNDED as u64) as u32,
+        });
+    }
+
+    if let (Some(available), Some(cbuffer)) = (range.root_constants, stage.cbuffer(&resource.name))
+    {
+        if cbuffer.root_constants() > available {
+            return Err(LayoutError::PushConstantTooSmall {
+                resource: resource.clone(),
+                required: cbuffer.root_constants(),
+                available,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn check_static_sampler(
+    sampler: &StaticSampler,
+    stage: &ShaderBindings,
+    resource: &BoundResource,
+) -> Result<(), LayoutError> {
+    if !sampler.is_visible_to(stage.visibility) {
+        return Err(LayoutError::NotVisible {
+            resource: resource.clone(),
+            stage: stage.visibility,
+            visibility: sampler.visibility,
+        });
+    }
+
+    if sampler.is_comparison() != resource.comparison {
+        return Err(LayoutError::ComparisonMismatch {
+            resource: resource.clone(),
+            stage: stage.visibility,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn check_flags(layout: &[LayoutBinding]) -> Result<(), LayoutError> {
+    let data_flags_valid = |flags: DescriptorFlags| {
+        flags
+            .intersection(DescriptorFlags::DATA)
+            .bits()
+            .count_ones()
+            <= 1
+    };
+
+    for (parameter, binding) in layout.iter().enumerate() {
+        let invalid = match binding {
+            LayoutBinding::Cbv { flags, .. }
+            | LayoutBinding::Srv { flags, .. }
+            | LayoutBinding::Uav { flags, .. } => (!data_flags_valid(*flags)
+                || flags.contains(DescriptorFlags::DescriptorsVolatile))
+            .then_some(*flags),
+            LayoutBinding::PushConstant { .. } => None,
+            LayoutBinding::Table { entries, .. } => entries.iter().find_map(|entry| {
+                let (flags, sampler) = match *entry {
+                    BindingTable::Cbv { flags, .. }
+                    | BindingTable::Srv { flags, .. }
+                    | BindingTable::Uav { flags, .. } => (flags, false),
+                    BindingTable::Sampler { flags, .. } => (flags, true),
+                };
+
+                let valid = if sampler {
+                    !flags.intersects(DescriptorFlags::DATA)
+                } else {
+                    data_flags_valid(flags)
+                        && !flags.contains(
+                            DescriptorFlags::DescriptorsVolatile | DescriptorFlags::DataStatic,
+                        )
+                };
+
+                (!valid).then_some(flags)
+            }),
+        };
+
+        if let Some(flags) = invalid {
+            return Err(LayoutError::InvalidFlags { parameter, flags });
+        }
+    }
+
+    Ok(())
+}
+
+pub fn check_layout(
+    layout: &[LayoutBinding],
+    static_samplers: &[StaticSampler],
+    stages: &[ShaderBindings],
+) -> Result<(), Vec<LayoutError>> {
+    let mut errors = vec![];
+
+    for stage in stages {
+        'resources: for resource in &stage.resources {
+            let mut error = LayoutError::Missing {
+                resource: resource.clone(),
+                stage: stage.visibility,
+            };
+
+            if let Some(sampler) = static_sampler_for(static_samplers, resource) {
+                match check_static_sampler(sampler, stage, resource) {
+                    Ok(()) => continue 'resources,
+                    Err(err) => error = err,
+                }
+            }
+
+            for binding in layout {
+                let candidates = binding.ranges().into_iter().filter(|range| {
+                    range.kind == resource.kind
+                        && range.space == resource.space
+                        && range.slot <= resource.slot
+                        && (resource.slot as u64) < range_end(range.slot, range.count)
+                });
+
+                for range in candidates {
+                    match check_range(&range, binding.visibility(), stage, resource) {
+                        Ok(()) => continue 'resources,
+                        Err(err) if err.rank() > error.rank() => error = err,
+                        Err(_) => {}
+                    }
+                }
+            }
+
+            errors.push(error);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oxidx::dx::ShaderVisibility;
+
+    use crate::graphics::{BindingTable, DescriptorFlags, StaticSampler};
+
+    use super::{
+        check_flags, check_layout, reflect_layout, BoundResource, CbufferLayout, LayoutBinding,
+        LayoutError, ResourceKind, ShaderBindings, UNBOUNDED,
+    };
+
+    fn srv(slot: u32, space: u32, count: u32) -> BindingTable {
+        BindingTable::Srv {
+            slot,
+            space,
+            count,
+            flags: DescriptorFlags::empty(),
+        }
+    }
+
+    fn cbv(slot: u32, visibility: ShaderVisibility) -> LayoutBinding {
+        LayoutBinding::Cbv {
+            slot,
+            space: 0,
+            flags: DescriptorFlags::empty(),
+            visibility,
+        }
+    }
+
+    fn shadow_stages() -> Vec<ShaderBindings> {
+        vec![
+            ShaderBindings::new(ShaderVisibility::Vertex)
+                .with_resource(BoundResource::new(
+                    "Camera",
+                    ResourceKind::ConstantBuffer,
+                    0,
+                    0,
+                ))
+                .with_resource(BoundResource::new(
+                    "Object",
+                    ResourceKind::ConstantBuffer,
+                    1,
+                    0,
+                )),
+            ShaderBindings::new(ShaderVisibility::Pixel)
+                .with_resource(BoundResource::new(
+                    "Camera",
+                    ResourceKind::ConstantBuffer,
+                    0,
+                    0,
+                ))
+                .with_resource(BoundResource::new(
+                    "albedo",
+                    ResourceKind::ShaderResource,
+                    0,
+                    0,
+                ))
+                .with_resource(BoundResource::new(
+                    "normal",
+                    ResourceKind::ShaderResource,
+                    1,
+                    0,
+                ))
+                .with_resource(
+                    BoundResource::new("cascades", ResourceKind::ShaderResource, 4, 0)
+                        .with_count(4),
+                )
+                .with_resource(BoundResource::new("linear", ResourceKind::Sampler, 0, 0))
+                .with_resource(
+                    BoundResource::new("shadow", ResourceKind::Sampler, 1, 0).with_comparison(),
+                ),
+        ]
+    }
+
+    #[test]
+    fn test_reflect_layout() {
+        let layout = reflect_layout(&shadow_stages(), &[]);
+
+        assert_eq!(
+            layout,
+            vec![
+                cbv(0, ShaderVisibility::All),
+                cbv(1, ShaderVisibility::Vertex),
+                LayoutBinding::Table {
+                    entries: vec![srv(0, 0, 2), srv(4, 0, 4)],
+                    visibility: ShaderVisibility::Pixel,
+                },
+                LayoutBinding::Table {
+                    entries: vec![BindingTable::Sampler {
+                        slot: 0,
+                        space: 0,
+                        count: 2,
+                        flags: DescriptorFlags::empty(),
+                    }],
+                    visibility: ShaderVisibility::Pixel,
+                },
+            ]
+        );
+
+        assert_eq!(check_layout(&layout, &[], &shadow_stages()), Ok(()));
+    }
+
+    #[test]
+    fn test_reflect_skips_static_samplers() {
+        let static_samplers = [StaticSampler::linear_wrap(0), StaticSampler::shadow_pcf(1)];
+        let layout = reflect_layout(&shadow_stages(), &static_samplers);
+
+        assert_eq!(layout.len(), 3);
+        assert!(layout.iter().all(|binding| match binding {
+            LayoutBinding::Table { entries, .. } => entries
+                .iter()
+                .all(|e| !matches!(e, BindingTable::Sampler { .. })),
+            _ => true,
+        }));
+
+        assert_eq!(
+            check_layout(&layout, &static_samplers, &shadow_stages()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_reflect_merges_unbounded_arrays() {
+        let stages = [ShaderBindings::new(ShaderVisibility::All)
+            .with_resource(
+                BoundResource::new("textures", ResourceKind::ShaderResource, 0, 1)
+                    .with_count(UNBOUNDED),
+            )
+            .with_resource(BoundResource::new(
+                "first",
+                ResourceKind::ShaderResource,
+                0,
+                1,
+            ))];
+
+        assert_eq!(
+            reflect_layout(&stages, &[]),
+            vec![LayoutBinding::Table {
+                entries: vec![BindingTable::Srv {
+                    slot: 0,
+                    space: 1,
+                    count: UNBOUNDED,
+                    flags: DescriptorFlags::DescriptorsVolatile | DescriptorFlags::DataVolatile,
+                }],
+                visibility: ShaderVisibility::All,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_check_layout_reports_mismatches() {
+        let layout = [
+            cbv(0, ShaderVisibility::Vertex),
+            LayoutBinding::PushConstant {
+                slot: 1,
+                space: 0,
+                count: 4,
+                visibility: ShaderVisibility::All,
+            },
+            LayoutBinding::Table {
+                entries: vec![srv(0, 0, 2), srv(4, 0, 2)],
+                visibility: ShaderVisibility::Pixel,
+            },
+        ];
+        let static_samplers = [StaticSampler::linear_clamp(1)];
+
+        let mut stages = shadow_stages();
+        stages[0] = stages[0].clone().with_cbuffer(CbufferLayout {
+            name: "Object".to_string(),
+            size: 64,
+            variables: vec![],
+        });
+
+        let errors = check_layout(&layout, &static_samplers, &stages).unwrap_err();
+
+        assert_eq!(errors.len(), 5);
+        assert!(matches!(
+            &errors[0],
+            LayoutError::PushConstantTooSmall {
+                required: 16,
+                available: 4,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &errors[1],
+            LayoutError::NotVisible {
+                stage: ShaderVisibility::Pixel,
+                visibility: ShaderVisibility::Vertex,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &errors[2],
+            LayoutError::CountTooSmall { available: 2, .. }
+        ));
+        assert!(matches!(&errors[3], LayoutError::Missing { .. }));
+        assert!(matches!(&errors[4], LayoutError::ComparisonMismatch { .. }));
+
+        assert_eq!(
+            errors[2].to_string(),
+            "`cascades` (t4, space0) needs 4 descriptors but the layout provides 2"
+        );
+        assert_eq!(
+            errors[3].to_string(),
+            "`linear` (s0, space0) used by the Pixel shader is not bound by the layout"
+        );
+        assert_eq!(
+            errors[4].to_string(),
+            "`shadow` (s1, space0) is declared as a comparison sampler in the Pixel shader \
+             but the static sampler is a regular sampler"
+        );
+    }
+
+    #[test]
+    fn test_check_flags() {
+        let table = |entries| LayoutBinding::Table {
+            entries,
+            visibility: ShaderVisibility::All,
+        };
+
+        let valid = [
+            LayoutBinding::Cbv {
+                slot: 0,
+                space: 0,
+                flags: DescriptorFlags::DataStatic,
+                visibility: ShaderVisibility::All,
+            },
+            table(vec![BindingTable::Srv {
+                slot: 0,
+                space: 0,
+                count: 8,
+                flags: DescriptorFlags::DescriptorsVolatile | DescriptorFlags::DataVolatile,
+            }]),
+            table(vec![BindingTable::Sampler {
+                slot: 0,
+                space: 0,
+                count: 1,
+                flags: DescriptorFlags::DescriptorsVolatile,
+            }]),
+        ];
+        assert_eq!(check_flags(&valid), Ok(()));
+
+        let conflicting = DescriptorFlags::DataStatic | DescriptorFlags::DataVolatile;
+        assert_eq!(
+            check_flags(&[
+                valid[0].clone(),
+                table(vec![
+                    srv(0, 0, 1),
+                    BindingTable::Uav {
+                        slot: 0,
+                        space: 0,
+                        count: 1,
+                        flags: conflicting,
+                    }
+                ])
+            ]),
+            Err(LayoutError::InvalidFlags {
+                parameter: 1,
+                flags: conflicting,
+            })
+        );
+
+        assert!(check_flags(&[table(vec![BindingTable::Sampler {
+            slot: 0,
+            space: 0,
+            count: 1,
+            flags: DescriptorFlags::DataStatic,
+        }])])
+        .is_err());
+        assert!(check_flags(&[LayoutBinding::Srv {
+            slot: 0,
+            space: 0,
+            flags: DescriptorFlags::DescriptorsVolatile,
+            visibility: ShaderVisibility::All,
+        }])
+        .is_err());
+
+        let volatile_static = DescriptorFlags::DescriptorsVolatile | DescriptorFlags::DataStatic;
+        assert_eq!(
+            check_flags(&[table(vec![BindingTable::Cbv {
+                slot: 0,
+                space: 0,
+                count: 1,
+                flags: volatile_static,
+            }])]),
+            Err(LayoutError::InvalidFlags {
+                parameter: 0,
+                flags: volatile_static,
+            })
+        );
+    }
+
+    #[test]
+    fn test_binding_type_round_trip() {
+        let layout = reflect_layout(&shadow_stages(), &[]);
+        let round_trip = layout
+            .iter()
+            .map(|binding| LayoutBinding::from_binding_type(&binding.as_binding_type()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(round_trip, layout);
+    }
+}
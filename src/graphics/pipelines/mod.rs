@@ -1,7 +1,15 @@
+mod layout_reflection;
 mod pipeline;
 mod pipeline_layout;
+mod pipeline_state;
 mod pipeline_type;
+mod static_sampler;
+mod vertex_format;
 
+pub use layout_reflection::*;
 pub use pipeline::*;
 pub use pipeline_layout::*;
+pub use pipeline_state::*;
 pub use pipeline_type::*;
+pub use static_sampler::*;
+pub use vertex_format::*;
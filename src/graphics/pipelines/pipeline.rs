@@ -1,28 +1,204 @@
-use std::{marker::PhantomData, sync::Arc};
+use std::{fmt, marker::PhantomData, ops::Deref, sync::Arc};
 
 use oxidx::dx::{self, IDevice};
+use parking_lot::RwLock;
 
-use crate::graphics::{Device, GraphicsPipelineDesc};
+use crate::graphics::{
+    check_input_signature, CacheKey, CacheKind, Compute, ComputePipelineDesc, Device,
+    GraphicsPipelineDesc, InputSignatureError, LayoutError, PipelineStateError, ShaderError,
+};
 
 use super::{Graphics, PipelineType};
 
+#[derive(Debug)]
+pub enum PipelineError {
+    State(PipelineStateError),
+    InputSignature(InputSignatureError),
+    Layout(Vec<LayoutError>),
+    Reflection(ShaderError),
+    Creation(String),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::State(err) => write!(f, "invalid graphics pipeline desc: {}", err),
+            PipelineError::InputSignature(err) => {
+                write!(f, "input layout does not match vertex shader: {}", err)
+            }
+            PipelineError::Layout(errors) => {
+                write!(f, "pipeline layout does not match shaders:")?;
+
+                for err in errors {
+                    write!(f, "\n  {}", err)?;
+                }
+
+                Ok(())
+            }
+            PipelineError::Reflection(err) => write!(f, "{}", err),
+            PipelineError::Creation(err) => write!(f, "failed to create pipeline state: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
 #[derive(Clone, Debug)]
 pub struct Pipeline<T: PipelineType>(Arc<PipelineInner<T>>);
 
 #[derive(Debug)]
 pub struct PipelineInner<T: PipelineType> {
-    pub(crate) raw: dx::PipelineState,
+    raw: RwLock<dx::PipelineState>,
     _marker: PhantomData<T>,
 }
 
-impl Pipeline<Graphics> {
-    pub(crate) fn inner_new_graphics(device: &Device, desc: &GraphicsPipelineDesc) -> Self {
-        let desc = &desc.as_raw();
-        let raw = device.raw.create_graphics_pipeline(desc).unwrap();
+impl<T: PipelineType> Deref for Pipeline<T> {
+    type Target = PipelineInner<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
 
+impl<T: PipelineType> Pipeline<T> {
+    fn from_raw(raw: dx::PipelineState) -> Self {
         Self(Arc::new(PipelineInner {
-            raw,
+            raw: RwLock::new(raw),
             _marker: PhantomData,
         }))
     }
+
+    pub(crate) fn is_unique(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
+
+    fn create_cached<E: fmt::Debug>(
+        device: &Device,
+        key: impl FnOnce() -> CacheKey,
+        create: impl Fn(Option<&[u8]>) -> Result<dx::PipelineState, E>,
+    ) -> Result<dx::PipelineState, PipelineError> {
+        let Some(cache) = device.disk_cache() else {
+            return create(None).map_err(|err| PipelineError::Creation(format!("{:?}", err)));
+        };
+
+        let key = key();
+
+        if let Some(blob) = cache.load(CacheKind::Pipeline, key) {
+            match create(Some(&blob)) {
+                Ok(raw) => return Ok(raw),
+                Err(err) => {
+                    tracing::warn!("Cached pipeline {} was rejected: {:?}", key, err);
+                    cache.invalidate(CacheKind::Pipeline, key);
+                }
+            }
+        }
+
+        let raw = create(None).map_err(|err| PipelineError::Creation(format!("{:?}", err)))?;
+
+        let stored = raw
+            .get_cached_blob()
+            .map_err(|err| format!("{:?}", err))
+            .and_then(|blob| {
+                cache
+                    .store(CacheKind::Pipeline, key, blob.as_slice())
+                    .map_err(|err| err.to_string())
+            });
+
+        if let Err(err) = stored {
+            tracing::warn!("Failed to cache pipeline {}: {}", key, err);
+        }
+
+        Ok(raw)
+    }
+}
+
+impl<T: PipelineType> PipelineInner<T> {
+    pub(crate) fn raw(&self) -> dx::PipelineState {
+        self.raw.read().clone()
+    }
+
+    pub(crate) fn swap(&self, raw: dx::PipelineState) -> dx::PipelineState {
+        std::mem::replace(&mut *self.raw.write(), raw)
+    }
+}
+
+impl Pipeline<Graphics> {
+    pub(crate) fn inner_new_graphics(
+        device: &Device,
+        desc: &GraphicsPipelineDesc,
+    ) -> Result<Self, PipelineError> {
+        Self::build_graphics(device, desc).map(Self::from_raw)
+    }
+
+    pub(crate) fn build_graphics(
+        device: &Device,
+        desc: &GraphicsPipelineDesc,
+    ) -> Result<dx::PipelineState, PipelineError> {
+        desc.validate().map_err(PipelineError::State)?;
+
+        let signature = desc
+            .vs
+            .input_signature()
+            .map_err(PipelineError::Reflection)?;
+        check_input_signature(&desc.state.input_layout, &signature)
+            .map_err(PipelineError::InputSignature)?;
+
+        let bindings = desc.stage_bindings().map_err(PipelineError::Reflection)?;
+        desc.layout
+            .check(&bindings)
+            .map_err(PipelineError::Layout)?;
+
+        let semantics = desc.state.semantics();
+        let input_layout = desc.state.input_layout_raw(&semantics);
+        let blobs = desc.blobs();
+
+        Self::create_cached(
+            device,
+            || desc.cache_key(device.name(), &blobs),
+            |cached| {
+                let desc = desc.as_raw(&blobs, &input_layout);
+                let desc = match cached {
+                    Some(blob) => desc.with_cached_pso(blob),
+                    None => desc,
+                };
+
+                device.raw.create_graphics_pipeline(&desc)
+            },
+        )
+    }
+}
+
+impl Pipeline<Compute> {
+    pub(crate) fn inner_new_compute(
+        device: &Device,
+        desc: &ComputePipelineDesc,
+    ) -> Result<Self, PipelineError> {
+        Self::build_compute(device, desc).map(Self::from_raw)
+    }
+
+    pub(crate) fn build_compute(
+        device: &Device,
+        desc: &ComputePipelineDesc,
+    ) -> Result<dx::PipelineState, PipelineError> {
+        let bindings = desc.stage_bindings().map_err(PipelineError::Reflection)?;
+        desc.layout
+            .check(&bindings)
+            .map_err(PipelineError::Layout)?;
+
+        let cs = desc.cs.raw();
+
+        Self::create_cached(
+            device,
+            || desc.cache_key(device.name(), &cs),
+            |cached| {
+                let desc = desc.as_raw(&cs);
+                let desc = match cached {
+                    Some(blob) => desc.with_cached_pso(blob),
+                    None => desc,
+                };
+
+                device.raw.create_compute_pipeline(&desc)
+            },
+        )
+    }
 }
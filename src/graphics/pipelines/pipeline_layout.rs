@@ -1,11 +1,41 @@
+use std::{fmt, sync::Arc};
+
 use oxidx::dx::{self, IDevice};
 use smallvec::SmallVec;
 
-use crate::graphics::{BindingType, Device, StaticSampler};
+use crate::graphics::{
+    check_flags, check_layout, reflect_layout, validate_static_samplers, BindingType, CacheKey,
+    CacheKeyHasher, DescriptorFlags, Device, LayoutBinding, LayoutError, ShaderBindings,
+    StaticSampler, StaticSamplerError,
+};
+
+#[derive(Debug)]
+pub enum PipelineLayoutError {
+    Flags(LayoutError),
+    StaticSampler(StaticSamplerError),
+    Creation(String),
+}
+
+impl fmt::Display for PipelineLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineLayoutError::Flags(err) => write!(f, "{}", err),
+            PipelineLayoutError::StaticSampler(err) => write!(f, "invalid static sampler: {}", err),
+            PipelineLayoutError::Creation(err) => {
+                write!(f, "failed to create root signature: {}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PipelineLayoutError {}
 
 #[derive(Clone, Debug)]
 pub struct PipelineLayout {
     pub(crate) raw: dx::RootSignature,
+    pub(crate) key: CacheKey,
+    bindings: Arc<[LayoutBinding]>,
+    static_samplers: Arc<[StaticSampler]>,
 }
 
 impl PipelineLayout {
@@ -13,7 +43,54 @@ impl PipelineLayout {
         device: &Device,
         layout: &[BindingType],
         static_samplers: &[StaticSampler],
-    ) -> Self {
+    ) -> Result<Self, PipelineLayoutError> {
+        let bindings = layout
+            .iter()
+            .map(LayoutBinding::from_binding_type)
+            .collect::<Arc<[_]>>();
+
+        check_flags(&bindings).map_err(PipelineLayoutError::Flags)?;
+        validate_static_samplers(static_samplers).map_err(PipelineLayoutError::StaticSampler)?;
+
+        let samplers = static_samplers
+            .iter()
+            .map(|i| i.as_raw())
+            .collect::<SmallVec<[_; 4]>>();
+
+        let raw = if device.capabilities().root_signature_1_1 {
+            Self::create_v1_1(device, layout, &samplers)?
+        } else {
+            for (parameter, binding) in bindings.iter().enumerate() {
+                // Descriptors are always volatile under 1.0, only static data is lost.
+                if binding.flags().contains(DescriptorFlags::DataStatic) {
+                    tracing::warn!(
+                        "Root signature 1.1 is not supported, ignoring DataStatic on parameter {}",
+                        parameter
+                    );
+                }
+            }
+
+            Self::create_v1_0(device, layout, &samplers)?
+        };
+
+        let key = CacheKeyHasher::new("pipeline_layout")
+            .with_str(&format!("{:?}", layout))
+            .with_str(&format!("{:?}", static_samplers))
+            .finish();
+
+        Ok(Self {
+            raw,
+            key,
+            bindings,
+            static_samplers: static_samplers.into(),
+        })
+    }
+
+    fn create_v1_0(
+        device: &Device,
+        layout: &[BindingType],
+        samplers: &[dx::StaticSamplerDesc],
+    ) -> Result<dx::RootSignature, PipelineLayoutError> {
         let ranges = layout
             .iter()
             .map(|i| i.get_ranges())
@@ -24,21 +101,68 @@ impl PipelineLayout {
             .zip(ranges.iter())
             .map(|(i, ranges)| i.as_raw(ranges))
             .collect::<SmallVec<[_; 4]>>();
-        let samplers = static_samplers
-            .iter()
-            .map(|i| i.as_raw())
-            .collect::<SmallVec<[_; 4]>>();
 
         let desc = dx::RootSignatureDesc::default()
             .with_parameters(&params)
-            .with_samplers(&samplers)
+            .with_samplers(samplers)
             .with_flags(dx::RootSignatureFlags::AllowInputAssemblerInputLayout);
 
-        let raw = device
+        device
             .raw
             .serialize_and_create_root_signature(&desc, dx::RootSignatureVersion::V1_0, 0)
-            .unwrap();
+            .map_err(|err| PipelineLayoutError::Creation(format!("{:?}", err)))
+    }
+
+    fn create_v1_1(
+        device: &Device,
+        layout: &[BindingType],
+        samplers: &[dx::StaticSamplerDesc],
+    ) -> Result<dx::RootSignature, PipelineLayoutError> {
+        let ranges = layout
+            .iter()
+            .map(|i| i.get_ranges1())
+            .collect::<SmallVec<[_; 4]>>();
+
+        let params = layout
+            .iter()
+            .zip(ranges.iter())
+            .map(|(i, ranges)| i.as_raw1(ranges))
+            .collect::<SmallVec<[_; 4]>>();
+
+        let desc = dx::RootSignatureDesc1::default()
+            .with_parameters(&params)
+            .with_samplers(samplers)
+            .with_flags(dx::RootSignatureFlags::AllowInputAssemblerInputLayout);
+
+        device
+            .raw
+            .serialize_and_create_root_signature(&desc, dx::RootSignatureVersion::V1_1, 0)
+            .map_err(|err| PipelineLayoutError::Creation(format!("{:?}", err)))
+    }
+
+    pub(crate) fn inner_new_reflected(
+        device: &Device,
+        stages: &[ShaderBindings],
+        static_samplers: &[StaticSampler],
+    ) -> Result<Self, PipelineLayoutError> {
+        let bindings = reflect_layout(stages, static_samplers);
+        let layout = bindings
+            .iter()
+            .map(|b| b.as_binding_type())
+            .collect::<SmallVec<[_; 8]>>();
+
+        Self::inner_new(device, &layout, static_samplers)
+    }
+
+    pub fn bindings(&self) -> &[LayoutBinding] {
+        &self.bindings
+    }
+
+    pub fn static_samplers(&self) -> &[StaticSampler] {
+        &self.static_samplers
+    }
 
-        Self { raw }
+    pub fn check(&self, stages: &[ShaderBindings]) -> Result<(), Vec<LayoutError>> {
+        check_layout(&self.bindings, &self.static_samplers, stages)
     }
 }
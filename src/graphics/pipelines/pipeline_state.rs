@@ -0,0 +1,1174 @@
+use std::{ffi::CString, fmt};
+
+use oxidx::dx;
+use smallvec::SmallVec;
+
+use crate::graphics::CacheKeyHasher;
+
+use super::VertexFormat;
+
+pub const MAX_RENDER_TARGETS: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FillMode {
+    #[default]
+    Solid,
+    Wireframe,
+}
+
+impl FillMode {
+    pub(crate) fn as_raw(&self) -> dx::FillMode {
+        match self {
+            FillMode::Solid => dx::FillMode::Solid,
+            FillMode::Wireframe => dx::FillMode::Wireframe,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CullMode {
+    None,
+    Front,
+    #[default]
+    Back,
+}
+
+impl CullMode {
+    pub(crate) fn as_raw(&self) -> dx::CullMode {
+        match self {
+            CullMode::None => dx::CullMode::None,
+            CullMode::Front => dx::CullMode::Front,
+            CullMode::Back => dx::CullMode::Back,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DepthBias {
+    pub constant: i32,
+    pub slope_scaled: f32,
+    pub clamp: f32,
+}
+
+impl DepthBias {
+    pub fn new(constant: i32, slope_scaled: f32) -> Self {
+        Self {
+            constant,
+            slope_scaled,
+            clamp: 0.0,
+        }
+    }
+
+    pub fn slope_scaled(slope_scaled: f32) -> Self {
+        Self::new(0, slope_scaled)
+    }
+
+    pub fn with_clamp(mut self, clamp: f32) -> Self {
+        self.clamp = clamp;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.constant != 0 || self.slope_scaled != 0.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RasterizerState {
+    pub fill_mode: FillMode,
+    pub cull_mode: CullMode,
+    pub front_counter_clockwise: bool,
+    pub depth_bias: DepthBias,
+    pub depth_clip: bool,
+}
+
+impl Default for RasterizerState {
+    fn default() -> Self {
+        Self {
+            fill_mode: FillMode::Solid,
+            cull_mode: CullMode::Back,
+            front_counter_clockwise: false,
+            depth_bias: DepthBias::default(),
+            depth_clip: true,
+        }
+    }
+}
+
+impl RasterizerState {
+    pub fn with_fill_mode(mut self, fill_mode: FillMode) -> Self {
+        self.fill_mode = fill_mode;
+        self
+    }
+
+    pub fn with_cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn with_front_counter_clockwise(mut self, front_counter_clockwise: bool) -> Self {
+        self.front_counter_clockwise = front_counter_clockwise;
+        self
+    }
+
+    pub fn with_depth_bias(mut self, depth_bias: DepthBias) -> Self {
+        self.depth_bias = depth_bias;
+        self
+    }
+
+    pub fn with_depth_clip(mut self, depth_clip: bool) -> Self {
+        self.depth_clip = depth_clip;
+        self
+    }
+
+    pub(crate) fn as_raw(&self) -> dx::RasterizerDesc {
+        dx::RasterizerDesc::default()
+            .with_fill_mode(self.fill_mode.as_raw())
+            .with_cull_mode(self.cull_mode.as_raw())
+            .with_front_counter_clockwise(self.front_counter_clockwise)
+            .with_depth_bias(self.depth_bias.constant)
+            .with_slope_scaled_depth_bias(self.depth_bias.slope_scaled)
+            .with_depth_bias_clamp(self.depth_bias.clamp)
+            .with_depth_clip_enable(self.depth_clip)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompareOp {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareOp {
+    pub(crate) fn as_raw(&self) -> dx::ComparisonFunc {
+        match self {
+            CompareOp::Never => dx::ComparisonFunc::Never,
+            CompareOp::Less => dx::ComparisonFunc::Less,
+            CompareOp::Equal => dx::ComparisonFunc::Equal,
+            CompareOp::LessEqual => dx::ComparisonFunc::LessEqual,
+            CompareOp::Greater => dx::ComparisonFunc::Greater,
+            CompareOp::NotEqual => dx::ComparisonFunc::NotEqual,
+            CompareOp::GreaterEqual => dx::ComparisonFunc::GreaterEqual,
+            CompareOp::Always => dx::ComparisonFunc::Always,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    IncrementClamp,
+    DecrementClamp,
+    Invert,
+    IncrementWrap,
+    DecrementWrap,
+}
+
+impl StencilOp {
+    pub(crate) fn as_raw(&self) -> dx::StencilOp {
+        match self {
+            StencilOp::Keep => dx::StencilOp::Keep,
+            StencilOp::Zero => dx::StencilOp::Zero,
+            StencilOp::Replace => dx::StencilOp::Replace,
+            StencilOp::IncrementClamp => dx::StencilOp::IncrSat,
+            StencilOp::DecrementClamp => dx::StencilOp::DecrSat,
+            StencilOp::Invert => dx::StencilOp::Invert,
+            StencilOp::IncrementWrap => dx::StencilOp::Incr,
+            StencilOp::DecrementWrap => dx::StencilOp::Decr,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StencilFace {
+    pub fail: StencilOp,
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+    pub compare: CompareOp,
+}
+
+impl Default for StencilFace {
+    fn default() -> Self {
+        Self {
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+            compare: CompareOp::Always,
+        }
+    }
+}
+
+impl StencilFace {
+    pub(crate) fn as_raw(&self) -> dx::DepthStencilOpDesc {
+        dx::DepthStencilOpDesc::default()
+            .with_stencil_fail_op(self.fail.as_raw())
+            .with_stencil_depth_fail_op(self.depth_fail.as_raw())
+            .with_stencil_pass_op(self.pass.as_raw())
+            .with_stencil_func(self.compare.as_raw())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StencilState {
+    pub read_mask: u8,
+    pub write_mask: u8,
+    pub front: StencilFace,
+    pub back: StencilFace,
+}
+
+impl Default for StencilState {
+    fn default() -> Self {
+        Self {
+            read_mask: 0xff,
+            write_mask: 0xff,
+            front: StencilFace::default(),
+            back: StencilFace::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DepthStencilState {
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub depth_compare: CompareOp,
+    pub stencil: Option<StencilState>,
+}
+
+impl Default for DepthStencilState {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl DepthStencilState {
+    pub fn disabled() -> Self {
+        Self {
+            depth_test: false,
+            depth_write: false,
+            depth_compare: CompareOp::Always,
+            stencil: None,
+        }
+    }
+
+    pub fn read_write(compare: CompareOp) -> Self {
+        Self {
+            depth_test: true,
+            depth_write: true,
+            depth_compare: compare,
+            stencil: None,
+        }
+    }
+
+    pub fn read_only(compare: CompareOp) -> Self {
+        Self {
+            depth_test: true,
+            depth_write: false,
+            depth_compare: compare,
+            stencil: None,
+        }
+    }
+
+    pub fn with_stencil(mut self, stencil: StencilState) -> Self {
+        self.stencil = Some(stencil);
+        self
+    }
+
+    pub fn uses_depth(&self) -> bool {
+        self.depth_test || self.depth_write || self.stencil.is_some()
+    }
+
+    pub(crate) fn as_raw(&self) -> dx::DepthStencilDesc {
+        let write_mask = if self.depth_write {
+            dx::DepthWriteMask::All
+        } else {
+            dx::DepthWriteMask::Zero
+        };
+
+        let desc = dx::DepthStencilDesc::default()
+            .with_depth_enable(self.depth_test)
+            .with_depth_write_mask(write_mask)
+            .with_depth_func(self.depth_compare.as_raw());
+
+        if let Some(stencil) = &self.stencil {
+            desc.with_stencil_enable(true)
+                .with_stencil_read_mask(stencil.read_mask)
+                .with_stencil_write_mask(stencil.write_mask)
+                .with_front_face(stencil.front.as_raw())
+                .with_back_face(stencil.back.as_raw())
+        } else {
+            desc.with_stencil_enable(false)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    InvSrcColor,
+    SrcAlpha,
+    InvSrcAlpha,
+    DstColor,
+    InvDstColor,
+    DstAlpha,
+    InvDstAlpha,
+}
+
+impl BlendFactor {
+    pub(crate) fn as_raw(&self) -> dx::Blend {
+        match self {
+            BlendFactor::Zero => dx::Blend::Zero,
+            BlendFactor::One => dx::Blend::One,
+            BlendFactor::SrcColor => dx::Blend::SrcColor,
+            BlendFactor::InvSrcColor => dx::Blend::InvSrcColor,
+            BlendFactor::SrcAlpha => dx::Blend::SrcAlpha,
+            BlendFactor::InvSrcAlpha => dx::Blend::InvSrcAlpha,
+            BlendFactor::DstColor => dx::Blend::DestColor,
+            BlendFactor::InvDstColor => dx::Blend::InvDestColor,
+            BlendFactor::DstAlpha => dx::Blend::DestAlpha,
+            BlendFactor::InvDstAlpha => dx::Blend::InvDestAlpha,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendOp {
+    Add,
+    Subtract,
+    RevSubtract,
+    Min,
+    Max,
+}
+
+impl BlendOp {
+    pub(crate) fn as_raw(&self) -> dx::BlendOp {
+        match self {
+            BlendOp::Add => dx::BlendOp::Add,
+            BlendOp::Subtract => dx::BlendOp::Subtract,
+            BlendOp::RevSubtract => dx::BlendOp::RevSubtract,
+            BlendOp::Min => dx::BlendOp::Min,
+            BlendOp::Max => dx::BlendOp::Max,
+        }
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    pub struct ColorWriteMask: u8 {
+        const Red = 1;
+        const Green = 2;
+        const Blue = 4;
+        const Alpha = 8;
+        const All = Self::Red.bits() | Self::Green.bits() | Self::Blue.bits() | Self::Alpha.bits();
+    }
+}
+
+impl ColorWriteMask {
+    pub(crate) fn as_raw(&self) -> dx::ColorWriteEnable {
+        dx::ColorWriteEnable::from_bits(self.bits()).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlendState {
+    pub enabled: bool,
+    pub src: BlendFactor,
+    pub dst: BlendFactor,
+    pub op: BlendOp,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+    pub op_alpha: BlendOp,
+    pub write_mask: ColorWriteMask,
+}
+
+impl Default for BlendState {
+    fn default() -> Self {
+        Self::opaque()
+    }
+}
+
+impl BlendState {
+    pub fn opaque() -> Self {
+        Self {
+            enabled: false,
+            src: BlendFactor::One,
+            dst: BlendFactor::Zero,
+            op: BlendOp::Add,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::Zero,
+            op_alpha: BlendOp::Add,
+            write_mask: ColorWriteMask::All,
+        }
+    }
+
+    pub fn alpha() -> Self {
+        Self {
+            enabled: true,
+            src: BlendFactor::SrcAlpha,
+            dst: BlendFactor::InvSrcAlpha,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::InvSrcAlpha,
+            ..Self::opaque()
+        }
+    }
+
+    pub fn premultiplied() -> Self {
+        Self {
+            enabled: true,
+            src: BlendFactor::One,
+            dst: BlendFactor::InvSrcAlpha,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::InvSrcAlpha,
+            ..Self::opaque()
+        }
+    }
+
+    pub fn additive() -> Self {
+        Self {
+            enabled: true,
+            src: BlendFactor::One,
+            dst: BlendFactor::One,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::One,
+            ..Self::opaque()
+        }
+    }
+
+    pub fn with_write_mask(mut self, write_mask: ColorWriteMask) -> Self {
+        self.write_mask = write_mask;
+        self
+    }
+
+    pub(crate) fn as_raw(&self) -> dx::RenderTargetBlendDesc {
+        dx::RenderTargetBlendDesc::default()
+            .with_blend_enable(self.enabled)
+            .with_src_blend(self.src.as_raw())
+            .with_dest_blend(self.dst.as_raw())
+            .with_blend_op(self.op.as_raw())
+            .with_src_blend_alpha(self.src_alpha.as_raw())
+            .with_dest_blend_alpha(self.dst_alpha.as_raw())
+            .with_blend_op_alpha(self.op_alpha.as_raw())
+            .with_render_target_write_mask(self.write_mask.as_raw())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderTarget {
+    pub format: dx::Format,
+    pub blend: BlendState,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Topology {
+    Point,
+    Line,
+    #[default]
+    Triangle,
+    Patch,
+}
+
+impl Topology {
+    pub(crate) fn as_raw(&self) -> dx::PipelinePrimitiveTopology {
+        match self {
+            Topology::Point => dx::PipelinePrimitiveTopology::Point,
+            Topology::Line => dx::PipelinePrimitiveTopology::Line,
+            Topology::Triangle => dx::PipelinePrimitiveTopology::Triangle,
+            Topology::Patch => dx::PipelinePrimitiveTopology::Patch,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputRate {
+    PerVertex,
+    PerInstance(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputElement {
+    pub semantic: &'static str,
+    pub index: u32,
+    pub format: dx::Format,
+    pub slot: u32,
+    pub offset: u32,
+    pub rate: InputRate,
+}
+
+impl InputElement {
+    pub fn new(semantic: &'static str, index: u32, format: dx::Format, offset: u32) -> Self {
+        Self {
+            semantic,
+            index,
+            format,
+            slot: 0,
+            offset,
+            rate: InputRate::PerVertex,
+        }
+    }
+
+    pub fn with_slot(mut self, slot: u32) -> Self {
+        self.slot = slot;
+        self
+    }
+
+    pub fn per_instance(mut self, step_rate: u32) -> Self {
+        self.rate = InputRate::PerInstance(step_rate);
+        self
+    }
+
+    pub(crate) fn as_raw<'a>(&self, semantic: &'a CString) -> dx::InputElementDesc<'a> {
+        match self.rate {
+            InputRate::PerVertex => {
+                dx::InputElementDesc::per_vertex(semantic, self.index, self.format, self.slot)
+            }
+            InputRate::PerInstance(step_rate) => dx::InputElementDesc::per_instance(
+                semantic,
+                self.index,
+                self.format,
+                self.slot,
+                step_rate,
+            ),
+        }
+        .with_offset(self.offset)
+    }
+}
+
+pub(crate) fn is_depth_format(format: dx::Format) -> bool {
+    matches!(
+        format,
+        dx::Format::D16Unorm
+            | dx::Format::D24UnormS8Uint
+            | dx::Format::D32Float
+            | dx::Format::D32FloatS8X24Uint
+    )
+}
+
+pub(crate) fn has_stencil(format: dx::Format) -> bool {
+    matches!(
+        format,
+        dx::Format::D24UnormS8Uint | dx::Format::D32FloatS8X24Uint
+    )
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PipelineStateError {
+    TooManyRenderTargets(usize),
+    InvalidRenderTargetFormat { index: usize, format: dx::Format },
+    InvalidDepthFormat(dx::Format),
+    MissingDepthFormat,
+    MissingStencilFormat(dx::Format),
+    DepthWriteWithoutTest,
+    InvalidDepthBias(DepthBias),
+    MissingPixelShader,
+    NoOutputs,
+    InvalidSampleCount(u32),
+    MissingTessellationStage,
+    PatchTopologyMismatch,
+    InvalidInputElement { semantic: &'static str, index: u32 },
+    DuplicateInputElement { semantic: &'static str, index: u32 },
+}
+
+impl fmt::Display for PipelineStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineStateError::TooManyRenderTargets(count) => write!(
+                f,
+                "{} render targets bound, at most {} are supported",
+                count, MAX_RENDER_TARGETS
+            ),
+            PipelineStateError::InvalidRenderTargetFormat { index, format } => write!(
+                f,
+                "render target {} has non-color format {:?}",
+                index, format
+            ),
+            PipelineStateError::InvalidDepthFormat(format) => {
+                write!(f, "{:?} is not a depth format", format)
+            }
+            PipelineStateError::MissingDepthFormat => {
+                write!(
+                    f,
+                    "depth-stencil state or depth bias set without a depth format"
+                )
+            }
+            PipelineStateError::MissingStencilFormat(format) => {
+                write!(
+                    f,
+                    "stencil enabled but depth format {:?} has no stencil",
+                    format
+                )
+            }
+            PipelineStateError::DepthWriteWithoutTest => {
+                write!(f, "depth write requires depth test to be enabled")
+            }
+            PipelineStateError::InvalidDepthBias(bias) => write!(
+                f,
+                "depth bias slope {} / clamp {} is not finite",
+                bias.slope_scaled, bias.clamp
+            ),
+            PipelineStateError::MissingPixelShader => {
+                write!(f, "render targets are bound but there is no pixel shader")
+            }
+            PipelineStateError::NoOutputs => {
+                write!(f, "pipeline has neither render targets nor a depth target")
+            }
+            PipelineStateError::InvalidSampleCount(count) => {
+                write!(f, "sample count {} is not a power of two in 1..=32", count)
+            }
+            PipelineStateError::MissingTessellationStage => {
+                write!(f, "hull and domain shaders must be used together")
+            }
+            PipelineStateError::PatchTopologyMismatch => {
+                write!(f, "tessellation requires patch topology and vice versa")
+            }
+            PipelineStateError::InvalidInputElement { semantic, index } => {
+                write!(f, "input element {}{} is invalid", semantic, index)
+            }
+            PipelineStateError::DuplicateInputElement { semantic, index } => {
+                write!(f, "input element {}{} is declared twice", semantic, index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PipelineStateError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PipelineState {
+    pub input_layout: Vec<InputElement>,
+    pub render_targets: SmallVec<[RenderTarget; MAX_RENDER_TARGETS]>,
+    pub depth_format: Option<dx::Format>,
+    pub rasterizer: RasterizerState,
+    pub depth_stencil: DepthStencilState,
+    pub topology: Topology,
+    pub sample_count: u32,
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        Self {
+            input_layout: vec![],
+            render_targets: Default::default(),
+            depth_format: None,
+            rasterizer: RasterizerState::default(),
+            depth_stencil: DepthStencilState::disabled(),
+            topology: Topology::Triangle,
+            sample_count: 1,
+        }
+    }
+}
+
+impl PipelineState {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn opaque(color: dx::Format, depth: dx::Format) -> Self {
+        Self::new()
+            .with_render_target(color)
+            .with_depth(depth, DepthStencilState::read_write(CompareOp::Less))
+    }
+
+    pub fn depth_only(depth: dx::Format) -> Self {
+        Self::new().with_depth(depth, DepthStencilState::read_write(CompareOp::Less))
+    }
+
+    pub fn shadow(depth: dx::Format) -> Self {
+        Self::depth_only(depth).with_rasterizer(
+            RasterizerState::default()
+                .with_cull_mode(CullMode::Front)
+                .with_depth_bias(DepthBias::new(1, 1.5))
+                .with_depth_clip(false),
+        )
+    }
+
+    pub fn with_input_layout(mut self, input_layout: &[InputElement]) -> Self {
+        self.input_layout = input_layout.to_vec();
+        self
+    }
+
+    pub fn with_vertex_buffer<V: VertexFormat>(mut self, slot: u32) -> Self {
+        self.input_layout
+            .extend(V::input_layout(slot, InputRate::PerVertex));
+        self
+    }
+
+    pub fn with_instance_buffer<V: VertexFormat>(mut self, slot: u32, step_rate: u32) -> Self {
+        self.input_layout
+            .extend(V::input_layout(slot, InputRate::PerInstance(step_rate)));
+        self
+    }
+
+    pub fn with_render_target(self, format: dx::Format) -> Self {
+        self.with_blended_render_target(format, BlendState::opaque())
+    }
+
+    pub fn with_blended_render_target(mut self, format: dx::Format, blend: BlendState) -> Self {
+        self.render_targets.push(RenderTarget { format, blend });
+        self
+    }
+
+    pub fn with_depth(mut self, format: dx::Format, depth_stencil: DepthStencilState) -> Self {
+        self.depth_format = Some(format);
+        self.depth_stencil = depth_stencil;
+        self
+    }
+
+    pub fn with_depth_stencil(mut self, depth_stencil: DepthStencilState) -> Self {
+        self.depth_stencil = depth_stencil;
+        self
+    }
+
+    pub fn with_rasterizer(mut self, rasterizer: RasterizerState) -> Self {
+        self.rasterizer = rasterizer;
+        self
+    }
+
+    pub fn with_cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.rasterizer.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn with_fill_mode(mut self, fill_mode: FillMode) -> Self {
+        self.rasterizer.fill_mode = fill_mode;
+        self
+    }
+
+    pub fn with_depth_bias(mut self, depth_bias: DepthBias) -> Self {
+        self.rasterizer.depth_bias = depth_bias;
+        self
+    }
+
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn validate(&self, has_pixel_shader: bool) -> Result<(), PipelineStateError> {
+        if self.render_targets.len() > MAX_RENDER_TARGETS {
+            return Err(PipelineStateError::TooManyRenderTargets(
+                self.render_targets.len(),
+            ));
+        }
+
+        for (index, target) in self.render_targets.iter().enumerate() {
+            if target.format == dx::Format::Unknown || is_depth_format(target.format) {
+                return Err(PipelineStateError::InvalidRenderTargetFormat {
+                    index,
+                    format: target.format,
+                });
+            }
+        }
+
+        if !self.render_targets.is_empty() && !has_pixel_shader {
+            return Err(PipelineStateError::MissingPixelShader);
+        }
+
+        match self.depth_format {
+            Some(format) => {
+                if !is_depth_format(format) {
+                    return Err(PipelineStateError::InvalidDepthFormat(format));
+                }
+
+                if self.depth_stencil.stencil.is_some() && !has_stencil(format) {
+                    return Err(PipelineStateError::MissingStencilFormat(format));
+                }
+            }
+            None => {
+                if self.depth_stencil.uses_depth() || self.rasterizer.depth_bias.is_enabled() {
+                    return Err(PipelineStateError::MissingDepthFormat);
+                }
+
+                if self.render_targets.is_empty() {
+                    return Err(PipelineStateError::NoOutputs);
+                }
+            }
+        }
+
+        if self.depth_stencil.depth_write && !self.depth_stencil.depth_test {
+            return Err(PipelineStateError::DepthWriteWithoutTest);
+        }
+
+        let bias = self.rasterizer.depth_bias;
+        if !bias.slope_scaled.is_finite() || !bias.clamp.is_finite() {
+            return Err(PipelineStateError::InvalidDepthBias(bias));
+        }
+
+        if !self.sample_count.is_power_of_two() || self.sample_count > 32 {
+            return Err(PipelineStateError::InvalidSampleCount(self.sample_count));
+        }
+
+        for (i, element) in self.input_layout.iter().enumerate() {
+            if element.semantic.is_empty()
+                || element.semantic.contains('\0')
+                || element.format == dx::Format::Unknown
+                || element.rate == InputRate::PerInstance(0)
+            {
+                return Err(PipelineStateError::InvalidInputElement {
+                    semantic: element.semantic,
+                    index: element.index,
+                });
+            }
+
+            if self.input_layout[..i]
+                .iter()
+                .any(|e| e.semantic == element.semantic && e.index == element.index)
+            {
+                return Err(PipelineStateError::DuplicateInputElement {
+                    semantic: element.semantic,
+                    index: element.index,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_tessellation(
+        &self,
+        has_hull: bool,
+        has_domain: bool,
+    ) -> Result<(), PipelineStateError> {
+        if has_hull != has_domain {
+            return Err(PipelineStateError::MissingTessellationStage);
+        }
+
+        if has_hull != (self.topology == Topology::Patch) {
+            return Err(PipelineStateError::PatchTopologyMismatch);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn semantics(&self) -> SmallVec<[CString; 8]> {
+        self.input_layout
+            .iter()
+            .map(|e| CString::new(e.semantic).unwrap())
+            .collect()
+    }
+
+    pub(crate) fn input_layout_raw<'a>(
+        &self,
+        semantics: &'a [CString],
+    ) -> SmallVec<[dx::InputElementDesc<'a>; 8]> {
+        self.input_layout
+            .iter()
+            .zip(semantics.iter())
+            .map(|(e, semantic)| e.as_raw(semantic))
+            .collect()
+    }
+
+    pub(crate) fn render_target_formats(&self) -> SmallVec<[dx::Format; MAX_RENDER_TARGETS]> {
+        self.render_targets.iter().map(|t| t.format).collect()
+    }
+
+    pub(crate) fn hash_into(&self, hasher: CacheKeyHasher) -> CacheKeyHasher {
+        let mut hasher = hasher.with_u64(self.input_layout.len() as u64);
+
+        for element in &self.input_layout {
+            let step_rate = match element.rate {
+                InputRate::PerVertex => 0,
+                InputRate::PerInstance(step_rate) => step_rate as u64 + 1,
+            };
+
+            hasher = hasher
+                .with_str(element.semantic)
+                .with_u64(element.index as u64)
+                .with_u64(element.format as u64)
+                .with_u64(element.slot as u64)
+                .with_u64(element.offset as u64)
+                .with_u64(step_rate);
+        }
+
+        hasher = hasher.with_u64(self.render_targets.len() as u64);
+
+        for target in &self.render_targets {
+            let blend = &target.blend;
+
+            hasher = hasher
+                .with_u64(target.format as u64)
+                .with_u64(blend.enabled as u64)
+                .with_u64(blend.src as u64)
+                .with_u64(blend.dst as u64)
+                .with_u64(blend.op as u64)
+                .with_u64(blend.src_alpha as u64)
+                .with_u64(blend.dst_alpha as u64)
+                .with_u64(blend.op_alpha as u64)
+                .with_u64(blend.write_mask.bits() as u64);
+        }
+
+        let rasterizer = &self.rasterizer;
+        let depth_stencil = &self.depth_stencil;
+
+        hasher = hasher
+            .with_u64(self.depth_format.map_or(0, |format| format as u64 + 1))
+            .with_u64(rasterizer.fill_mode as u64)
+            .with_u64(rasterizer.cull_mode as u64)
+            .with_u64(rasterizer.front_counter_clockwise as u64)
+            .with_u64(rasterizer.depth_bias.constant as u64)
+            .with_u64(rasterizer.depth_bias.slope_scaled.to_bits() as u64)
+            .with_u64(rasterizer.depth_bias.clamp.to_bits() as u64)
+            .with_u64(rasterizer.depth_clip as u64)
+            .with_u64(depth_stencil.depth_test as u64)
+            .with_u64(depth_stencil.depth_write as u64)
+            .with_u64(depth_stencil.depth_compare as u64)
+            .with_u64(depth_stencil.stencil.is_some() as u64);
+
+        if let Some(stencil) = &depth_stencil.stencil {
+            hasher = hasher
+                .with_u64(stencil.read_mask as u64)
+                .with_u64(stencil.write_mask as u64);
+
+            for face in [&stencil.front, &stencil.back] {
+                hasher = hasher
+                    .with_u64(face.fail as u64)
+                    .with_u64(face.depth_fail as u64)
+                    .with_u64(face.pass as u64)
+                    .with_u64(face.compare as u64);
+            }
+        }
+
+        hasher
+            .with_u64(self.topology as u64)
+            .with_u64(self.sample_count as u64)
+    }
+
+    pub(crate) fn blend_raw(&self) -> dx::BlendDesc {
+        let independent = self
+            .render_targets
+            .windows(2)
+            .any(|w| w[0].blend != w[1].blend);
+
+        dx::BlendDesc::default()
+            .with_independent_blend_enable(independent)
+            .with_render_targets(self.render_targets.iter().map(|t| t.blend.as_raw()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oxidx::dx;
+
+    use crate::graphics::CacheKeyHasher;
+
+    use super::{
+        BlendState, CompareOp, CullMode, DepthBias, DepthStencilState, InputElement, PipelineState,
+        PipelineStateError, StencilState, Topology,
+    };
+
+    const COLOR: dx::Format = dx::Format::Rgba8Unorm;
+    const DEPTH: dx::Format = dx::Format::D32Float;
+
+    #[test]
+    fn test_hash_tracks_state_fields() {
+        let hash = |state: &PipelineState| state.hash_into(CacheKeyHasher::new("test")).finish();
+        let state = PipelineState::opaque(COLOR, DEPTH);
+
+        assert_eq!(hash(&state), hash(&state.clone()));
+        assert_ne!(
+            hash(&state),
+            hash(&state.clone().with_cull_mode(CullMode::Front))
+        );
+        assert_ne!(
+            hash(&state),
+            hash(&state.clone().with_depth_bias(DepthBias::new(0, 0.5)))
+        );
+        assert_ne!(hash(&state), hash(&state.clone().with_render_target(COLOR)));
+        assert_ne!(
+            hash(&state),
+            hash(&state.clone().with_input_layout(&[
+                InputElement::new("POSITION", 0, dx::Format::Rgb32Float, 0).per_instance(1)
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_shadow_preset_is_depth_only() {
+        let state = PipelineState::shadow(DEPTH);
+
+        assert!(state.render_targets.is_empty());
+        assert_eq!(state.depth_format, Some(DEPTH));
+        assert_eq!(state.rasterizer.cull_mode, CullMode::Front);
+        assert!(state.rasterizer.depth_bias.slope_scaled > 0.0);
+        assert!(state.depth_stencil.depth_write);
+        assert_eq!(state.validate(false), Ok(()));
+    }
+
+    #[test]
+    fn test_render_targets_require_pixel_shader() {
+        let state = PipelineState::opaque(COLOR, DEPTH);
+
+        assert_eq!(state.validate(true), Ok(()));
+        assert_eq!(
+            state.validate(false),
+            Err(PipelineStateError::MissingPixelShader)
+        );
+    }
+
+    #[test]
+    fn test_too_many_render_targets() {
+        let state = (0..9).fold(PipelineState::new(), |s, _| s.with_render_target(COLOR));
+
+        assert_eq!(
+            state.validate(true),
+            Err(PipelineStateError::TooManyRenderTargets(9))
+        );
+    }
+
+    #[test]
+    fn test_depth_format_checks() {
+        assert_eq!(
+            PipelineState::depth_only(COLOR).validate(false),
+            Err(PipelineStateError::InvalidDepthFormat(COLOR))
+        );
+        assert_eq!(
+            PipelineState::new()
+                .with_render_target(DEPTH)
+                .validate(true),
+            Err(PipelineStateError::InvalidRenderTargetFormat {
+                index: 0,
+                format: DEPTH
+            })
+        );
+        assert_eq!(
+            PipelineState::new()
+                .with_render_target(COLOR)
+                .with_depth_stencil(DepthStencilState::read_only(CompareOp::Equal))
+                .validate(true),
+            Err(PipelineStateError::MissingDepthFormat)
+        );
+        assert_eq!(
+            PipelineState::new()
+                .with_render_target(COLOR)
+                .with_depth_bias(DepthBias::slope_scaled(2.0))
+                .validate(true),
+            Err(PipelineStateError::MissingDepthFormat)
+        );
+    }
+
+    #[test]
+    fn test_depth_stencil_checks() {
+        let stencil =
+            DepthStencilState::read_write(CompareOp::Less).with_stencil(StencilState::default());
+
+        assert_eq!(
+            PipelineState::new()
+                .with_depth(DEPTH, stencil)
+                .validate(false),
+            Err(PipelineStateError::MissingStencilFormat(DEPTH))
+        );
+        assert_eq!(
+            PipelineState::new()
+                .with_depth(dx::Format::D24UnormS8Uint, stencil)
+                .validate(false),
+            Ok(())
+        );
+
+        let write_only = DepthStencilState {
+            depth_test: false,
+            ..DepthStencilState::read_write(CompareOp::Less)
+        };
+
+        assert_eq!(
+            PipelineState::new()
+                .with_depth(DEPTH, write_only)
+                .validate(false),
+            Err(PipelineStateError::DepthWriteWithoutTest)
+        );
+    }
+
+    #[test]
+    fn test_tessellation_requires_patches() {
+        let state = PipelineState::depth_only(DEPTH);
+
+        assert_eq!(state.validate_tessellation(false, false), Ok(()));
+        assert_eq!(
+            state.validate_tessellation(true, false),
+            Err(PipelineStateError::MissingTessellationStage)
+        );
+        assert_eq!(
+            state.validate_tessellation(true, true),
+            Err(PipelineStateError::PatchTopologyMismatch)
+        );
+
+        let state = state.with_topology(Topology::Patch);
+
+        assert_eq!(state.validate_tessellation(true, true), Ok(()));
+        assert_eq!(
+            state.validate_tessellation(false, false),
+            Err(PipelineStateError::PatchTopologyMismatch)
+        );
+    }
+
+    #[test]
+    fn test_empty_pipeline_has_no_outputs() {
+        assert_eq!(
+            PipelineState::new().validate(true),
+            Err(PipelineStateError::NoOutputs)
+        );
+    }
+
+    #[test]
+    fn test_input_layout_checks() {
+        let state = PipelineState::opaque(COLOR, DEPTH).with_input_layout(&[
+            InputElement::new("POSITION", 0, dx::Format::Rgb32Float, 0),
+            InputElement::new("TEXCOORD", 0, dx::Format::Rg32Float, 12),
+            InputElement::new("TEXCOORD", 1, dx::Format::Rg32Float, 20),
+        ]);
+
+        assert_eq!(state.validate(true), Ok(()));
+
+        let state = state.with_input_layout(&[
+            InputElement::new("POSITION", 0, dx::Format::Rgb32Float, 0),
+            InputElement::new("POSITION", 0, dx::Format::Rgb32Float, 0).with_slot(1),
+        ]);
+
+        assert_eq!(
+            state.validate(true),
+            Err(PipelineStateError::DuplicateInputElement {
+                semantic: "POSITION",
+                index: 0
+            })
+        );
+
+        let state =
+            state.with_input_layout(&[
+                InputElement::new("WORLD", 0, dx::Format::Rgba32Float, 0).per_instance(0)
+            ]);
+
+        assert_eq!(
+            state.validate(true),
+            Err(PipelineStateError::InvalidInputElement {
+                semantic: "WORLD",
+                index: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_sample_count_and_blend() {
+        let state = PipelineState::new()
+            .with_blended_render_target(COLOR, BlendState::alpha())
+            .with_render_target(COLOR);
+
+        assert_eq!(state.validate(true), Ok(()));
+        assert_eq!(
+            state.clone().with_sample_count(3).validate(true),
+            Err(PipelineStateError::InvalidSampleCount(3))
+        );
+        assert_eq!(state.with_sample_count(4).validate(true), Ok(()));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::graphics::{GraphicsPipelineDesc, Sealed};
+use crate::graphics::{Compute, ComputePipelineDesc, GraphicsPipelineDesc, Sealed};
 
 pub trait PipelineType: Sealed {
     type Desc;
@@ -11,3 +11,7 @@ impl Sealed for Graphics {}
 impl PipelineType for Graphics {
     type Desc = GraphicsPipelineDesc;
 }
+
+impl PipelineType for Compute {
+    type Desc = ComputePipelineDesc;
+}
@@ -0,0 +1,366 @@
+use std::fmt;
+
+use oxidx::dx;
+
+use super::CompareOp;
+
+pub const MAX_ANISOTROPY: u32 = 16;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Filter {
+    Point,
+    Bilinear,
+    #[default]
+    Linear,
+    Anisotropic,
+}
+
+impl Filter {
+    pub(crate) fn as_raw(&self, comparison: bool) -> dx::Filter {
+        match (self, comparison) {
+            (Filter::Point, false) => dx::Filter::Point,
+            (Filter::Bilinear, false) => dx::Filter::MinMagLinearMipPoint,
+            (Filter::Linear, false) => dx::Filter::Linear,
+            (Filter::Anisotropic, false) => dx::Filter::Anisotropic,
+            (Filter::Point, true) => dx::Filter::ComparisonPoint,
+            (Filter::Bilinear, true) => dx::Filter::ComparisonMinMagLinearMipPoint,
+            (Filter::Linear, true) => dx::Filter::ComparisonLinear,
+            (Filter::Anisotropic, true) => dx::Filter::ComparisonAnisotropic,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AddressMode {
+    #[default]
+    Wrap,
+    Mirror,
+    Clamp,
+    Border,
+    MirrorOnce,
+}
+
+impl AddressMode {
+    pub(crate) fn as_raw(&self) -> dx::AddressMode {
+        match self {
+            AddressMode::Wrap => dx::AddressMode::Wrap,
+            AddressMode::Mirror => dx::AddressMode::Mirror,
+            AddressMode::Clamp => dx::AddressMode::Clamp,
+            AddressMode::Border => dx::AddressMode::Border,
+            AddressMode::MirrorOnce => dx::AddressMode::MirrorOnce,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BorderColor {
+    TransparentBlack,
+    #[default]
+    OpaqueBlack,
+    OpaqueWhite,
+}
+
+impl BorderColor {
+    pub(crate) fn as_raw(&self) -> dx::StaticBorderColor {
+        match self {
+            BorderColor::TransparentBlack => dx::StaticBorderColor::TransparentBlack,
+            BorderColor::OpaqueBlack => dx::StaticBorderColor::OpaqueBlack,
+            BorderColor::OpaqueWhite => dx::StaticBorderColor::OpaqueWhite,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StaticSampler {
+    pub slot: u32,
+    pub space: u32,
+    pub visibility: dx::ShaderVisibility,
+    pub filter: Filter,
+    pub address: [AddressMode; 3],
+    pub comparison: Option<CompareOp>,
+    pub border_color: BorderColor,
+    pub max_anisotropy: u32,
+    pub mip_lod_bias: f32,
+    pub min_lod: f32,
+    pub max_lod: f32,
+}
+
+impl StaticSampler {
+    pub fn new(slot: u32, filter: Filter, address: AddressMode) -> Self {
+        Self {
+            slot,
+            space: 0,
+            visibility: dx::ShaderVisibility::All,
+            filter,
+            address: [address; 3],
+            comparison: None,
+            border_color: BorderColor::default(),
+            max_anisotropy: if filter == Filter::Anisotropic {
+                MAX_ANISOTROPY
+            } else {
+                1
+            },
+            mip_lod_bias: 0.0,
+            min_lod: 0.0,
+            max_lod: f32::MAX,
+        }
+    }
+
+    pub fn point_clamp(slot: u32) -> Self {
+        Self::new(slot, Filter::Point, AddressMode::Clamp)
+    }
+
+    pub fn point_wrap(slot: u32) -> Self {
+        Self::new(slot, Filter::Point, AddressMode::Wrap)
+    }
+
+    pub fn linear_clamp(slot: u32) -> Self {
+        Self::new(slot, Filter::Linear, AddressMode::Clamp)
+    }
+
+    pub fn linear_wrap(slot: u32) -> Self {
+        Self::new(slot, Filter::Linear, AddressMode::Wrap)
+    }
+
+    pub fn anisotropic_wrap(slot: u32) -> Self {
+        Self::new(slot, Filter::Anisotropic, AddressMode::Wrap)
+    }
+
+    pub fn shadow_pcf(slot: u32) -> Self {
+        Self::new(slot, Filter::Bilinear, AddressMode::Border)
+            .with_comparison(CompareOp::LessEqual)
+            .with_border_color(BorderColor::OpaqueWhite)
+            .with_visibility(dx::ShaderVisibility::Pixel)
+    }
+
+    pub fn shadow_pcf_reversed_z(slot: u32) -> Self {
+        Self::shadow_pcf(slot)
+            .with_comparison(CompareOp::GreaterEqual)
+            .with_border_color(BorderColor::OpaqueBlack)
+    }
+
+    pub fn with_space(mut self, space: u32) -> Self {
+        self.space = space;
+        self
+    }
+
+    pub fn with_visibility(mut self, visibility: dx::ShaderVisibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_address(mut self, u: AddressMode, v: AddressMode, w: AddressMode) -> Self {
+        self.address = [u, v, w];
+        self
+    }
+
+    pub fn with_comparison(mut self, comparison: CompareOp) -> Self {
+        self.comparison = Some(comparison);
+        self
+    }
+
+    pub fn with_border_color(mut self, border_color: BorderColor) -> Self {
+        self.border_color = border_color;
+        self
+    }
+
+    pub fn with_max_anisotropy(mut self, max_anisotropy: u32) -> Self {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+
+    pub fn with_mip_lod_bias(mut self, mip_lod_bias: f32) -> Self {
+        self.mip_lod_bias = mip_lod_bias;
+        self
+    }
+
+    pub fn with_lod_range(mut self, min_lod: f32, max_lod: f32) -> Self {
+        self.min_lod = min_lod;
+        self.max_lod = max_lod;
+        self
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        self.comparison.is_some()
+    }
+
+    pub fn is_visible_to(&self, visibility: dx::ShaderVisibility) -> bool {
+        self.visibility == dx::ShaderVisibility::All || self.visibility == visibility
+    }
+
+    pub fn validate(&self) -> Result<(), StaticSamplerError> {
+        if self.filter == Filter::Anisotropic
+            && !(1..=MAX_ANISOTROPY).contains(&self.max_anisotropy)
+        {
+            return Err(StaticSamplerError::InvalidAnisotropy(self.max_anisotropy));
+        }
+
+        if self.min_lod.is_nan() || self.max_lod.is_nan() || self.min_lod > self.max_lod {
+            return Err(StaticSamplerError::InvalidLodRange {
+                min: self.min_lod,
+                max: self.max_lod,
+            });
+        }
+
+        if !(-16.0..=15.99).contains(&self.mip_lod_bias) {
+            return Err(StaticSamplerError::InvalidMipLodBias(self.mip_lod_bias));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn as_raw(&self) -> dx::StaticSamplerDesc {
+        let [u, v, w] = self.address;
+
+        dx::StaticSamplerDesc::default()
+            .with_filter(self.filter.as_raw(self.is_comparison()))
+            .with_address_u(u.as_raw())
+            .with_address_v(v.as_raw())
+            .with_address_w(w.as_raw())
+            .with_mip_lod_bias(self.mip_lod_bias)
+            .with_max_anisotropy(self.max_anisotropy)
+            .with_comparison_func(
+                self.comparison
+                    .map_or(dx::ComparisonFunc::Never, |op| op.as_raw()),
+            )
+            .with_border_color(self.border_color.as_raw())
+            .with_min_lod(self.min_lod)
+            .with_max_lod(self.max_lod)
+            .with_shader_register(self.slot)
+            .with_register_space(self.space)
+            .with_visibility(self.visibility)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StaticSamplerError {
+    InvalidAnisotropy(u32),
+    InvalidLodRange { min: f32, max: f32 },
+    InvalidMipLodBias(f32),
+    DuplicateRegister { slot: u32, space: u32 },
+}
+
+impl fmt::Display for StaticSamplerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StaticSamplerError::InvalidAnisotropy(value) => write!(
+                f,
+                "max anisotropy {} is outside of 1..={}",
+                value, MAX_ANISOTROPY
+            ),
+            StaticSamplerError::InvalidLodRange { min, max } => {
+                write!(f, "invalid LOD range {}..{}", min, max)
+            }
+            StaticSamplerError::InvalidMipLodBias(bias) => {
+                write!(f, "mip LOD bias {} is outside of -16..16", bias)
+            }
+            StaticSamplerError::DuplicateRegister { slot, space } => write!(
+                f,
+                "more than one static sampler is bound to s{}, space{}",
+                slot, space
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StaticSamplerError {}
+
+pub fn validate_static_samplers(samplers: &[StaticSampler]) -> Result<(), StaticSamplerError> {
+    for (i, sampler) in samplers.iter().enumerate() {
+        sampler.validate()?;
+
+        let duplicate = samplers[..i].iter().any(|other| {
+            other.slot == sampler.slot
+                && other.space == sampler.space
+                && (other.is_visible_to(sampler.visibility)
+                    || sampler.is_visible_to(other.visibility))
+        });
+
+        if duplicate {
+            return Err(StaticSamplerError::DuplicateRegister {
+                slot: sampler.slot,
+                space: sampler.space,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use oxidx::dx::ShaderVisibility;
+
+    use crate::graphics::CompareOp;
+
+    use super::{
+        validate_static_samplers, AddressMode, BorderColor, Filter, StaticSampler,
+        StaticSamplerError,
+    };
+
+    #[test]
+    fn test_shadow_presets() {
+        let pcf = StaticSampler::shadow_pcf(1).with_space(2);
+
+        assert_eq!(pcf.filter, Filter::Bilinear);
+        assert_eq!(pcf.address, [AddressMode::Border; 3]);
+        assert_eq!(pcf.comparison, Some(CompareOp::LessEqual));
+        assert_eq!(pcf.border_color, BorderColor::OpaqueWhite);
+        assert_eq!(pcf.visibility, ShaderVisibility::Pixel);
+        assert_eq!((pcf.slot, pcf.space), (1, 2));
+        assert!(pcf.is_comparison());
+
+        let reversed = StaticSampler::shadow_pcf_reversed_z(1);
+        assert_eq!(reversed.comparison, Some(CompareOp::GreaterEqual));
+        assert_eq!(reversed.border_color, BorderColor::OpaqueBlack);
+
+        assert!(!StaticSampler::linear_clamp(0).is_comparison());
+        assert_eq!(pcf.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_sampler() {
+        assert_eq!(StaticSampler::anisotropic_wrap(0).max_anisotropy, 16);
+        assert_eq!(StaticSampler::point_wrap(0).max_anisotropy, 1);
+
+        assert_eq!(
+            StaticSampler::anisotropic_wrap(0)
+                .with_max_anisotropy(32)
+                .validate(),
+            Err(StaticSamplerError::InvalidAnisotropy(32))
+        );
+        assert_eq!(
+            StaticSampler::linear_wrap(0)
+                .with_lod_range(4.0, 2.0)
+                .validate(),
+            Err(StaticSamplerError::InvalidLodRange { min: 4.0, max: 2.0 })
+        );
+        assert_eq!(
+            StaticSampler::linear_wrap(0)
+                .with_mip_lod_bias(-20.0)
+                .validate(),
+            Err(StaticSamplerError::InvalidMipLodBias(-20.0))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_registers() {
+        let pixel = StaticSampler::linear_wrap(0).with_visibility(ShaderVisibility::Pixel);
+        let vertex = StaticSampler::point_clamp(0).with_visibility(ShaderVisibility::Vertex);
+
+        assert_eq!(validate_static_samplers(&[pixel, vertex]), Ok(()));
+        assert_eq!(
+            validate_static_samplers(&[pixel, vertex.with_space(1), StaticSampler::shadow_pcf(1)]),
+            Ok(())
+        );
+        assert_eq!(
+            validate_static_samplers(&[pixel, StaticSampler::point_clamp(0)]),
+            Err(StaticSamplerError::DuplicateRegister { slot: 0, space: 0 })
+        );
+    }
+}
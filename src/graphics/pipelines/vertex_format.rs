@@ -0,0 +1,337 @@
+use std::fmt;
+
+use oxidx::dx;
+
+use super::{InputElement, InputRate};
+
+pub trait VertexAttribute {
+    const FORMAT: dx::Format;
+}
+
+macro_rules! impl_vertex_attribute {
+    ($($ty:ty => $format:ident),* $(,)?) => {
+        $(
+            impl VertexAttribute for $ty {
+                const FORMAT: dx::Format = dx::Format::$format;
+            }
+        )*
+    };
+}
+
+impl_vertex_attribute! {
+    f32 => R32Float,
+    [f32; 2] => Rg32Float,
+    [f32; 3] => Rgb32Float,
+    [f32; 4] => Rgba32Float,
+    u32 => R32Uint,
+    [u32; 2] => Rg32Uint,
+    [u32; 3] => Rgb32Uint,
+    [u32; 4] => Rgba32Uint,
+    i32 => R32Sint,
+    [i32; 2] => Rg32Sint,
+    [i32; 3] => Rgb32Sint,
+    [i32; 4] => Rgba32Sint,
+    [u8; 4] => Rgba8Unorm,
+    glam::Vec2 => Rg32Float,
+    glam::Vec3 => Rgb32Float,
+    glam::Vec4 => Rgba32Float,
+    glam::UVec2 => Rg32Uint,
+    glam::UVec3 => Rgb32Uint,
+    glam::UVec4 => Rgba32Uint,
+    glam::IVec2 => Rg32Sint,
+    glam::IVec3 => Rgb32Sint,
+    glam::IVec4 => Rgba32Sint,
+}
+
+pub trait VertexFormat: Copy + 'static {
+    const ELEMENTS: &'static [InputElement];
+
+    fn input_layout(slot: u32, rate: InputRate) -> impl Iterator<Item = InputElement> {
+        Self::ELEMENTS
+            .iter()
+            .map(move |e| InputElement { slot, rate, ..*e })
+    }
+}
+
+#[macro_export]
+macro_rules! vertex_format {
+    (@format $ty:ty) => {
+        <$ty as $crate::graphics::VertexAttribute>::FORMAT
+    };
+    (@format $ty:ty, $format:ident) => {
+        ::oxidx::dx::Format::$format
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $field_vis:vis $field:ident : $ty:ty => $semantic:literal $([$index:literal])? $(as $format:ident)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(C)]
+        $vis struct $name {
+            $($field_vis $field: $ty),*
+        }
+
+        impl $crate::graphics::VertexFormat for $name {
+            const ELEMENTS: &'static [$crate::graphics::InputElement] = &[
+                $(
+                    $crate::graphics::InputElement {
+                        semantic: $semantic,
+                        index: 0 $(+ $index)?,
+                        format: $crate::vertex_format!(@format $ty $(, $format)?),
+                        slot: 0,
+                        offset: ::std::mem::offset_of!($name, $field) as u32,
+                        rate: $crate::graphics::InputRate::PerVertex,
+                    }
+                ),*
+            ];
+        }
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ComponentType {
+    Float,
+    Uint,
+    Sint,
+}
+
+impl ComponentType {
+    pub fn of_format(format: dx::Format) -> Option<Self> {
+        match format {
+            dx::Format::R32Float
+            | dx::Format::Rg32Float
+            | dx::Format::Rgb32Float
+            | dx::Format::Rgba32Float
+            | dx::Format::R16Float
+            | dx::Format::Rg16Float
+            | dx::Format::Rgba16Float
+            | dx::Format::R8Unorm
+            | dx::Format::Rg8Unorm
+            | dx::Format::Rgba8Unorm
+            | dx::Format::Rgba8Snorm
+            | dx::Format::R16Unorm
+            | dx::Format::Rg16Unorm
+            | dx::Format::Rgba16Unorm
+            | dx::Format::Rgba16Snorm
+            | dx::Format::Rgb10A2Unorm => Some(ComponentType::Float),
+            dx::Format::R32Uint
+            | dx::Format::Rg32Uint
+            | dx::Format::Rgb32Uint
+            | dx::Format::Rgba32Uint
+            | dx::Format::R16Uint
+            | dx::Format::Rg16Uint
+            | dx::Format::Rgba16Uint
+            | dx::Format::R8Uint
+            | dx::Format::Rg8Uint
+            | dx::Format::Rgba8Uint => Some(ComponentType::Uint),
+            dx::Format::R32Sint
+            | dx::Format::Rg32Sint
+            | dx::Format::Rgb32Sint
+            | dx::Format::Rgba32Sint
+            | dx::Format::R16Sint
+            | dx::Format::Rg16Sint
+            | dx::Format::Rgba16Sint
+            | dx::Format::R8Sint
+            | dx::Format::Rg8Sint
+            | dx::Format::Rgba8Sint => Some(ComponentType::Sint),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderInput {
+    pub semantic: String,
+    pub index: u32,
+    pub component_type: ComponentType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputSignatureError {
+    MissingElement {
+        semantic: String,
+        index: u32,
+    },
+    TypeMismatch {
+        semantic: String,
+        index: u32,
+        format: dx::Format,
+        expected: ComponentType,
+    },
+}
+
+impl fmt::Display for InputSignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputSignatureError::MissingElement { semantic, index } => write!(
+                f,
+                "shader input {}{} is not provided by the input layout",
+                semantic, index
+            ),
+            InputSignatureError::TypeMismatch {
+                semantic,
+                index,
+                format,
+                expected,
+            } => write!(
+                f,
+                "shader input {}{} expects {:?} components but the input layout provides {:?}",
+                semantic, index, expected, format
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputSignatureError {}
+
+pub fn check_input_signature(
+    layout: &[InputElement],
+    signature: &[ShaderInput],
+) -> Result<(), InputSignatureError> {
+    for input in signature {
+        let element = layout
+            .iter()
+            .find(|e| e.index == input.index && e.semantic.eq_ignore_ascii_case(&input.semantic))
+            .ok_or_else(|| InputSignatureError::MissingElement {
+                semantic: input.semantic.clone(),
+                index: input.index,
+            })?;
+
+        if ComponentType::of_format(element.format).is_some_and(|t| t != input.component_type) {
+            return Err(InputSignatureError::TypeMismatch {
+                semantic: input.semantic.clone(),
+                index: input.index,
+                format: element.format,
+                expected: input.component_type,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use oxidx::dx;
+
+    use crate::graphics::{InputElement, InputRate, PipelineState};
+
+    use super::{
+        check_input_signature, ComponentType, InputSignatureError, ShaderInput, VertexFormat,
+    };
+
+    crate::vertex_format! {
+        #[derive(Clone, Copy, Debug)]
+        pub struct MeshVertex {
+            pub position: glam::Vec3 => "POSITION",
+            pub normal: [f32; 3] => "NORMAL",
+            pub uv: glam::Vec2 => "TEXCOORD",
+            pub uv2: glam::Vec2 => "TEXCOORD"[1],
+            pub color: [u8; 4] => "COLOR",
+            pub bones: [u8; 4] => "BLENDINDICES" as Rgba8Uint,
+        }
+    }
+
+    crate::vertex_format! {
+        #[derive(Clone, Copy, Debug)]
+        struct InstanceData {
+            world: glam::Vec4 => "WORLD",
+        }
+    }
+
+    fn input(semantic: &str, index: u32, component_type: ComponentType) -> ShaderInput {
+        ShaderInput {
+            semantic: semantic.to_string(),
+            index,
+            component_type,
+        }
+    }
+
+    #[test]
+    fn test_elements_follow_struct_layout() {
+        let elements = MeshVertex::ELEMENTS;
+
+        assert_eq!(elements.len(), 6);
+        assert_eq!(
+            elements
+                .iter()
+                .map(|e| (e.semantic, e.index, e.offset))
+                .collect::<Vec<_>>(),
+            vec![
+                ("POSITION", 0, 0),
+                ("NORMAL", 0, 12),
+                ("TEXCOORD", 0, 24),
+                ("TEXCOORD", 1, 32),
+                ("COLOR", 0, 40),
+                ("BLENDINDICES", 0, 44),
+            ]
+        );
+        assert_eq!(elements[0].format, dx::Format::Rgb32Float);
+        assert_eq!(elements[4].format, dx::Format::Rgba8Unorm);
+        assert_eq!(elements[5].format, dx::Format::Rgba8Uint);
+        assert_eq!(size_of::<MeshVertex>(), 48);
+    }
+
+    #[test]
+    fn test_pipeline_state_takes_vertex_and_instance_buffers() {
+        let state = PipelineState::depth_only(dx::Format::D32Float)
+            .with_vertex_buffer::<MeshVertex>(0)
+            .with_instance_buffer::<InstanceData>(1, 1);
+
+        assert_eq!(state.input_layout.len(), 7);
+        assert_eq!(
+            state.input_layout[6],
+            InputElement::new("WORLD", 0, dx::Format::Rgba32Float, 0)
+                .with_slot(1)
+                .per_instance(1)
+        );
+        assert!(state.input_layout[..6]
+            .iter()
+            .all(|e| e.slot == 0 && e.rate == InputRate::PerVertex));
+        assert_eq!(state.validate(false), Ok(()));
+    }
+
+    #[test]
+    fn test_signature_matches_layout() {
+        let signature = [
+            input("POSITION", 0, ComponentType::Float),
+            input("texcoord", 1, ComponentType::Float),
+            input("BLENDINDICES", 0, ComponentType::Uint),
+        ];
+
+        assert_eq!(
+            check_input_signature(MeshVertex::ELEMENTS, &signature),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_signature_reports_missing_and_mismatched_inputs() {
+        assert_eq!(
+            check_input_signature(
+                MeshVertex::ELEMENTS,
+                &[input("TANGENT", 0, ComponentType::Float)]
+            ),
+            Err(InputSignatureError::MissingElement {
+                semantic: "TANGENT".to_string(),
+                index: 0
+            })
+        );
+        assert_eq!(
+            check_input_signature(
+                MeshVertex::ELEMENTS,
+                &[input("BLENDINDICES", 0, ComponentType::Float)]
+            ),
+            Err(InputSignatureError::TypeMismatch {
+                semantic: "BLENDINDICES".to_string(),
+                index: 0,
+                format: dx::Format::Rgba8Uint,
+                expected: ComponentType::Float
+            })
+        );
+    }
+}
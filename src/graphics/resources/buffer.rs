@@ -1,15 +1,27 @@
 use std::fmt::Debug;
 
-use atomig::Atomic;
 use oxidx::dx;
 
-use crate::graphics::{heaps::Allocation, ResourceStates};
+use crate::graphics::heaps::ResourceMemory;
+
+use super::ResourceState;
 
 #[derive(Debug)]
 pub struct BaseBuffer {
     pub(super) raw: dx::Resource,
     pub(super) size: usize,
-    pub(super) state: Atomic<ResourceStates>,
+    pub(super) state: ResourceState,
     pub(super) flags: dx::ResourceFlags,
-    pub(super) allocation: Option<Allocation>,
+    pub(super) memory: ResourceMemory,
+}
+
+impl Drop for BaseBuffer {
+    fn drop(&mut self) {
+        if let ResourceMemory::Placed(allocation) = &self.memory {
+            allocation
+                .heap
+                .device
+                .release((self.raw.clone(), allocation.clone()));
+        }
+    }
 }
@@ -1,12 +1,12 @@
 use std::{fmt::Debug, marker::PhantomData, ops::Deref, sync::Arc};
 
-use atomig::Atomic;
 use oxidx::dx::{self, IDevice, IResource};
 use parking_lot::Mutex;
 
 use crate::graphics::{
+    deferred::DeferredView,
     device::Device,
-    heaps::{Allocation, MemoryHeap},
+    heaps::{Allocation, MemoryHeap, MemoryKind, ResourceMemory},
     utils::NonNullSend,
     views::{CbvView, GpuView},
     MemoryHeapType, ResourceStates, ViewAllocator,
@@ -14,6 +14,7 @@ use crate::graphics::{
 
 use super::{
     buffer::BaseBuffer, BufferResource, BufferResourceDesc, GpuAccess, Resource, ResourceDesc,
+    ResourceState,
 };
 
 #[derive(Clone, Debug)]
@@ -29,6 +30,7 @@ impl<T: Clone> Deref for ConstantBuffer<T> {
 
 #[derive(Debug)]
 pub struct ConstantBufferInner<T: Clone> {
+    device: Device,
     buffer: BaseBuffer,
     mapped_data: Mutex<NonNullSend<T>>,
     count: usize,
@@ -38,11 +40,12 @@ pub struct ConstantBufferInner<T: Clone> {
 
 impl<T: Clone> ConstantBuffer<T> {
     pub(in super::super) fn inner_new(
+        device: &Device,
         resource: dx::Resource,
         desc: ConstantBufferDesc<T>,
         access: GpuAccess,
         state: ResourceStates,
-        allocation: Option<Allocation>,
+        memory: ResourceMemory,
     ) -> Self {
         let mapped_data = resource.map::<T>(0, None).unwrap();
 
@@ -59,12 +62,13 @@ impl<T: Clone> ConstantBuffer<T> {
         };
 
         Self(Arc::new(ConstantBufferInner {
+            device: device.clone(),
             buffer: BaseBuffer {
                 raw: resource,
                 size: desc.count * size_of::<T>(),
-                state: Atomic::new(state),
+                state: ResourceState::new(state, 1),
                 flags: dx::ResourceFlags::empty(),
-                allocation,
+                memory,
             },
             mapped_data: Mutex::new(mapped_data.into()),
             count: desc.count,
@@ -133,13 +137,15 @@ impl<T: Clone> Drop for ConstantBufferInner<T> {
     fn drop(&mut self) {
         self.buffer.raw.unmap(0, None);
 
-        if let CbGpuAccess::View(ref allocator, ref mut vec) = self.access {
-            let handles = std::mem::take(vec);
+        let views = match self.access {
+            CbGpuAccess::View(ref allocator, ref mut vec) => std::mem::take(vec)
+                .into_iter()
+                .map(|view| DeferredView::new(allocator.clone(), view))
+                .collect(),
+            CbGpuAccess::Addresses(_) => vec![],
+        };
 
-            for handle in handles {
-                allocator.remove_cbv(handle);
-            }
-        }
+        self.device.release((self.buffer.raw.clone(), views));
     }
 }
 
@@ -151,6 +157,10 @@ impl<T: Clone> Resource for ConstantBuffer<T> {
         &self.buffer.raw
     }
 
+    fn get_state(&self) -> &ResourceState {
+        &self.buffer.state
+    }
+
     fn get_desc(&self) -> Self::Desc {
         ConstantBufferDesc {
             count: self.count,
@@ -169,18 +179,29 @@ impl<T: Clone> Resource for ConstantBuffer<T> {
         };
         let element_byte_size = size_of::<T>();
 
+        let raw_desc = dx::ResourceDesc::buffer(desc.count * element_byte_size);
+
         let resource: dx::Resource = device
             .raw
             .create_committed_resource(
                 &dx::HeapProperties::upload(),
                 dx::HeapFlags::empty(),
-                &dx::ResourceDesc::buffer(desc.count * element_byte_size),
+                &raw_desc,
                 dx::ResourceStates::GenericRead,
                 None,
             )
             .unwrap();
 
-        Self::inner_new(resource, desc, access, ResourceStates::GenericRead, None)
+        let memory = device.track_resource(&raw_desc, MemoryHeapType::Cpu, MemoryKind::Committed);
+
+        Self::inner_new(
+            device,
+            resource,
+            desc,
+            access,
+            ResourceStates::GenericRead,
+            ResourceMemory::Committed(memory),
+        )
     }
 
     fn from_raw_placed(
@@ -210,11 +231,12 @@ impl<T: Clone> Resource for ConstantBuffer<T> {
             .unwrap();
 
         Self::inner_new(
+            &heap.device,
             raw,
             desc,
             access,
             ResourceStates::GenericRead,
-            Some(allocation),
+            ResourceMemory::Placed(allocation),
         )
     }
 }
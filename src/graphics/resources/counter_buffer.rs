@@ -4,19 +4,19 @@ use std::{
     sync::{Arc, OnceLock},
 };
 
-use atomig::Atomic;
 use oxidx::dx::{self, IDevice};
 
 use crate::graphics::{
     device::Device,
-    heaps::{Allocation, MemoryHeap},
+    heaps::{Allocation, MemoryHeap, MemoryKind, ResourceMemory},
     types::MemoryHeapType,
     views::{GpuView, SrvView, UavView},
     ResourceStates,
 };
 
 use super::{
-    buffer::BaseBuffer, BufferResource, BufferResourceDesc, Resource, ResourceDesc, ViewAccess,
+    buffer::BaseBuffer, BufferResource, BufferResourceDesc, Resource, ResourceDesc, ResourceState,
+    ViewAccess,
 };
 
 #[derive(Clone, Debug)]
@@ -45,16 +45,16 @@ impl CounterBuffer {
         resource: dx::Resource,
         desc: CounterBufferDesc,
         state: ResourceStates,
-        allocation: Option<Allocation>,
+        memory: ResourceMemory,
         access: ViewAccess,
     ) -> Self {
         Self(Arc::new(CounterBufferInner {
             buffer: BaseBuffer {
                 raw: resource,
                 size: desc.count * 4,
-                state: Atomic::new(state),
+                state: ResourceState::new(state, 1),
                 flags: dx::ResourceFlags::empty(),
-                allocation,
+                memory,
             },
             count: desc.count,
             access,
@@ -116,6 +116,10 @@ impl Resource for CounterBuffer {
         &self.buffer.raw
     }
 
+    fn get_state(&self) -> &ResourceState {
+        &self.buffer.state
+    }
+
     fn get_desc(&self) -> Self::Desc {
         CounterBufferDesc { count: self.count }
     }
@@ -126,18 +130,28 @@ impl Resource for CounterBuffer {
         access: Self::Access,
         init_state: ResourceStates,
     ) -> Self {
+        let raw_desc = dx::ResourceDesc::buffer(desc.count * 4);
+
         let resource: dx::Resource = device
             .raw
             .create_committed_resource(
                 &dx::HeapProperties::upload(),
                 dx::HeapFlags::empty(),
-                &dx::ResourceDesc::buffer(desc.count * 4),
+                &raw_desc,
                 init_state.as_raw(),
                 None,
             )
             .unwrap();
 
-        Self::inner_new(resource, desc, init_state, None, access)
+        let memory = device.track_resource(&raw_desc, MemoryHeapType::Cpu, MemoryKind::Committed);
+
+        Self::inner_new(
+            resource,
+            desc,
+            init_state,
+            ResourceMemory::Committed(memory),
+            access,
+        )
     }
 
     fn from_raw_placed(
@@ -167,7 +181,7 @@ impl Resource for CounterBuffer {
             raw,
             desc,
             ResourceStates::GenericRead,
-            Some(allocation),
+            ResourceMemory::Placed(allocation),
             access,
         )
     }
@@ -175,10 +189,7 @@ impl Resource for CounterBuffer {
 
 impl BufferResource for CounterBuffer {
     fn get_barrier(&self, state: ResourceStates) -> Option<dx::ResourceBarrier<'_>> {
-        let old = self
-            .buffer
-            .state
-            .swap(state, std::sync::atomic::Ordering::Relaxed);
+        let old = self.buffer.state.swap(0, state);
 
         if old != state {
             Some(dx::ResourceBarrier::transition(
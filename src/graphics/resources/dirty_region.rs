@@ -0,0 +1,341 @@
+use crate::graphics::types::SubresourceIndex;
+
+const DEFAULT_MAX_REGIONS: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl DirtyRect {
+    pub fn new(left: u32, top: u32, right: u32, bottom: u32) -> Self {
+        assert!(
+            left <= right && top <= bottom,
+            "DirtyRect: inverted rect {}x{} - {}x{}",
+            left,
+            top,
+            right,
+            bottom
+        );
+
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub fn from_size(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self::new(x, y, x + width, y + height)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> u32 {
+        self.bottom - self.top
+    }
+
+    pub fn area(&self) -> u64 {
+        self.width() as u64 * self.height() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.area() == 0
+    }
+
+    pub fn intersection(&self, other: &DirtyRect) -> Option<DirtyRect> {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right.min(other.right);
+        let bottom = self.bottom.min(other.bottom);
+
+        (left < right && top < bottom).then(|| DirtyRect::new(left, top, right, bottom))
+    }
+
+    pub fn union(&self, other: &DirtyRect) -> DirtyRect {
+        DirtyRect::new(
+            self.left.min(other.left),
+            self.top.min(other.top),
+            self.right.max(other.right),
+            self.bottom.max(other.bottom),
+        )
+    }
+
+    fn can_merge(&self, other: &DirtyRect) -> bool {
+        let overlap = self.intersection(other).map_or(0, |r| r.area());
+
+        self.union(other).area() + overlap <= self.area() + other.area()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirtyRegion {
+    pub subresource: SubresourceIndex,
+    pub rect: DirtyRect,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DirtyUpdate {
+    Clean,
+    Full,
+    Regions(Vec<DirtyRegion>),
+}
+
+#[derive(Clone, Debug)]
+pub struct DirtyRegions {
+    full: bool,
+    regions: Vec<DirtyRegion>,
+    max_regions: usize,
+}
+
+impl Default for DirtyRegions {
+    fn default() -> Self {
+        Self {
+            full: false,
+            regions: vec![],
+            max_regions: DEFAULT_MAX_REGIONS,
+        }
+    }
+}
+
+impl DirtyRegions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_max_regions(mut self, max_regions: usize) -> Self {
+        self.max_regions = max_regions;
+        self
+    }
+
+    pub fn is_clean(&self) -> bool {
+        !self.full && self.regions.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    pub fn regions(&self) -> &[DirtyRegion] {
+        &self.regions
+    }
+
+    pub fn mark(&mut self, subresource: SubresourceIndex, rect: DirtyRect) {
+        if self.full || rect.is_empty() {
+            return;
+        }
+
+        let mut rect = rect;
+
+        while let Some(index) = self
+            .regions
+            .iter()
+            .position(|r| r.subresource == subresource && r.rect.can_merge(&rect))
+        {
+            let region = self.regions.swap_remove(index);
+            rect = region.rect.union(&rect);
+        }
+
+        self.regions.push(DirtyRegion { subresource, rect });
+
+        if self.regions.len() > self.max_regions {
+            self.mark_all();
+        }
+    }
+
+    pub fn mark_all(&mut self) {
+        self.full = true;
+        self.regions.clear();
+    }
+
+    pub fn apply(&mut self, update: &DirtyUpdate) {
+        match update {
+            DirtyUpdate::Clean => {}
+            DirtyUpdate::Full => self.mark_all(),
+            DirtyUpdate::Regions(regions) => {
+                for region in regions {
+                    self.mark(region.subresource, region.rect);
+                }
+            }
+        }
+    }
+
+    pub fn take(&mut self) -> DirtyUpdate {
+        if std::mem::take(&mut self.full) {
+            DirtyUpdate::Full
+        } else if self.regions.is_empty() {
+            DirtyUpdate::Clean
+        } else {
+            DirtyUpdate::Regions(std::mem::take(&mut self.regions))
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SharedDirtyRegions {
+    pub(crate) dirty: DirtyRegions,
+    pushed: Vec<DirtyRegions>,
+}
+
+impl SharedDirtyRegions {
+    pub(crate) fn connect(&mut self) -> usize {
+        self.pushed.push(DirtyRegions::new());
+        self.pushed.len() - 1
+    }
+
+    pub(crate) fn take_dirty(&mut self, consumer: usize) -> DirtyUpdate {
+        let update = self.dirty.take();
+
+        for (index, pushed) in self.pushed.iter_mut().enumerate() {
+            if index != consumer {
+                pushed.apply(&update);
+            }
+        }
+
+        update
+    }
+
+    pub(crate) fn take_pushed(&mut self, consumer: usize) -> DirtyUpdate {
+        self.pushed[consumer].take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::types::SubresourceIndex;
+
+    use super::{DirtyRect, DirtyRegions, DirtyUpdate, SharedDirtyRegions};
+
+    const MIP0: SubresourceIndex = SubresourceIndex {
+        array_index: 0,
+        mip_index: 0,
+    };
+
+    const SLICE1: SubresourceIndex = SubresourceIndex {
+        array_index: 1,
+        mip_index: 0,
+    };
+
+    fn rects(update: DirtyUpdate) -> Vec<DirtyRect> {
+        match update {
+            DirtyUpdate::Regions(regions) => {
+                let mut rects = regions.into_iter().map(|r| r.rect).collect::<Vec<_>>();
+                rects.sort_by_key(|r| (r.top, r.left));
+                rects
+            }
+            other => panic!("expected regions, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_adjacent_tiles_merge() {
+        let mut dirty = DirtyRegions::new();
+
+        dirty.mark(MIP0, DirtyRect::from_size(0, 0, 256, 256));
+        dirty.mark(MIP0, DirtyRect::from_size(256, 0, 256, 256));
+        dirty.mark(MIP0, DirtyRect::from_size(0, 256, 512, 256));
+
+        assert_eq!(
+            rects(dirty.take()),
+            vec![DirtyRect::from_size(0, 0, 512, 512)]
+        );
+        assert!(dirty.is_clean());
+    }
+
+    #[test]
+    fn test_diagonal_tiles_stay_separate() {
+        let mut dirty = DirtyRegions::new();
+
+        dirty.mark(MIP0, DirtyRect::from_size(0, 0, 256, 256));
+        dirty.mark(MIP0, DirtyRect::from_size(256, 256, 256, 256));
+
+        assert_eq!(
+            rects(dirty.take()),
+            vec![
+                DirtyRect::from_size(0, 0, 256, 256),
+                DirtyRect::from_size(256, 256, 256, 256)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contained_rect_is_absorbed() {
+        let mut dirty = DirtyRegions::new();
+
+        dirty.mark(MIP0, DirtyRect::from_size(10, 10, 20, 20));
+        dirty.mark(MIP0, DirtyRect::from_size(0, 0, 100, 100));
+
+        assert_eq!(
+            rects(dirty.take()),
+            vec![DirtyRect::from_size(0, 0, 100, 100)]
+        );
+    }
+
+    #[test]
+    fn test_subresources_do_not_merge() {
+        let mut dirty = DirtyRegions::new();
+
+        dirty.mark(MIP0, DirtyRect::from_size(0, 0, 64, 64));
+        dirty.mark(SLICE1, DirtyRect::from_size(0, 0, 64, 64));
+
+        assert_eq!(dirty.regions().len(), 2);
+    }
+
+    #[test]
+    fn test_too_many_regions_collapse_to_full() {
+        let mut dirty = DirtyRegions::new().with_max_regions(4);
+
+        for i in 0..5 {
+            dirty.mark(MIP0, DirtyRect::from_size(i * 20, i * 20, 10, 10));
+        }
+
+        assert!(dirty.is_full());
+        assert_eq!(dirty.take(), DirtyUpdate::Full);
+        assert_eq!(dirty.take(), DirtyUpdate::Clean);
+    }
+
+    #[test]
+    fn test_apply_accumulates_pushed_regions() {
+        let mut pushed = DirtyRegions::new();
+        let mut dirty = DirtyRegions::new();
+
+        dirty.mark(MIP0, DirtyRect::from_size(0, 0, 32, 32));
+        pushed.apply(&dirty.take());
+
+        dirty.mark(MIP0, DirtyRect::from_size(32, 0, 32, 32));
+        pushed.apply(&dirty.take());
+
+        assert_eq!(
+            rects(pushed.take()),
+            vec![DirtyRect::from_size(0, 0, 64, 32)]
+        );
+    }
+
+    #[test]
+    fn test_pushed_regions_are_tracked_per_consumer() {
+        let mut shared = SharedDirtyRegions::default();
+        let owner = shared.connect();
+        let first = shared.connect();
+        let second = shared.connect();
+
+        shared.dirty.mark(MIP0, DirtyRect::from_size(0, 0, 32, 32));
+        shared.take_dirty(owner);
+
+        assert_eq!(shared.take_pushed(owner), DirtyUpdate::Clean);
+        assert_eq!(
+            rects(shared.take_pushed(first)),
+            vec![DirtyRect::from_size(0, 0, 32, 32)]
+        );
+        assert_eq!(shared.take_pushed(first), DirtyUpdate::Clean);
+        assert_eq!(
+            rects(shared.take_pushed(second)),
+            vec![DirtyRect::from_size(0, 0, 32, 32)]
+        );
+    }
+}
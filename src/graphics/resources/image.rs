@@ -6,23 +6,23 @@ use std::{
     sync::{Arc, OnceLock},
 };
 
-use atomig::Atomic;
 use oxidx::dx::{self, IDevice, IGraphicsCommandListExt};
 use parking_lot::Mutex;
 
 use crate::graphics::{
     commands::{WorkerThread, WorkerType},
+    deferred::DeferredView,
     device::Device,
-    heaps::{Allocation, MemoryHeap},
+    heaps::{Allocation, MemoryHeap, MemoryKind, ResourceMemory},
     types::{MemoryHeapType, SubresourceIndex, TextureCopyableFootprints, TextureUsage},
-    views::{DsvView, GpuView, RtvView, SrvView, UavView, ViewType},
+    views::{DsvView, GpuView, RtvView, SrvView, UavView, ViewAllocator, ViewType},
     ResourceStates,
 };
 
 use super::{
     staging_buffer::{StagingBuffer, StagingBufferDesc},
-    ImageResource, ImageResourceDesc, NoGpuAccess, Resource, ResourceDesc, ShareableImage,
-    ShareableImageDesc, ViewAccess,
+    ImageResource, ImageResourceDesc, NoGpuAccess, Resource, ResourceDesc, ResourceState,
+    ShareableImage, ShareableImageDesc, ViewAccess,
 };
 
 #[derive(Clone, Debug)]
@@ -38,10 +38,11 @@ impl Deref for Image {
 
 #[derive(Debug)]
 pub struct ImageInner {
+    device: Device,
     raw: dx::Resource,
     desc: ImageDesc,
-    state: Vec<Atomic<ResourceStates>>,
-    allocation: Option<Allocation>,
+    state: ResourceState,
+    memory: ResourceMemory,
 
     rtv: OnceLock<GpuView<RtvView>>,
     dsv: OnceLock<GpuView<DsvView>>,
@@ -65,13 +66,11 @@ impl Image {
         desc: ImageDesc,
         access: ViewAccess,
         state: ResourceStates,
-        allocation: Option<Allocation>,
+        memory: ResourceMemory,
     ) -> Self {
         let footprint = device.get_texture_copyable_footprints(desc.clone());
 
-        let state = (0..(desc.mip_levels * desc.count))
-            .map(|_| Atomic::new(state))
-            .collect();
+        let state = ResourceState::new(state, (desc.mip_levels * desc.count) as usize);
 
         let staging_buffer = StagingBuffer::from_desc(
             device,
@@ -81,10 +80,11 @@ impl Image {
         );
 
         Self(Arc::new(ImageInner {
+            device: device.clone(),
             raw: resource,
             desc,
             state,
-            allocation,
+            memory,
             rtv: Default::default(),
             dsv: Default::default(),
             srv: Default::default(),
@@ -290,41 +290,33 @@ impl Image {
 
 impl Drop for ImageInner {
     fn drop(&mut self) {
-        if let Some(rtv) = self.rtv.get() {
-            self.access.0.remove_rtv(*rtv);
-        }
-
-        if let Some(dsv) = self.dsv.get() {
-            self.access.0.remove_dsv(*dsv);
-        }
+        let allocator = &self.access.0;
 
-        if let Some(srv) = self.srv.get() {
-            self.access.0.remove_srv(*srv);
-        }
+        let rtv = deferred_views(allocator, self.rtv.take(), self.cached_rtv.get_mut());
+        let dsv = deferred_views(allocator, self.dsv.take(), self.cached_dsv.get_mut());
+        let srv = deferred_views(allocator, self.srv.take(), self.cached_srv.get_mut());
+        let uav = deferred_views(allocator, self.uav.take(), self.cached_uav.get_mut());
 
-        if let Some(uav) = self.uav.get() {
-            self.access.0.remove_uav(*uav);
-        }
-
-        self.cached_rtv
-            .lock()
-            .iter()
-            .for_each(|(_, handle)| self.access.0.remove_rtv(*handle));
-        self.cached_dsv
-            .lock()
-            .iter()
-            .for_each(|(_, handle)| self.access.0.remove_dsv(*handle));
-        self.cached_srv
-            .lock()
-            .iter()
-            .for_each(|(_, handle)| self.access.0.remove_srv(*handle));
-        self.cached_uav
-            .lock()
-            .iter()
-            .for_each(|(_, handle)| self.access.0.remove_uav(*handle));
+        self.device.release((
+            self.raw.clone(),
+            self.memory.allocation().cloned(),
+            self.staging_buffer.get_raw().clone(),
+            (rtv, dsv, srv, uav),
+        ));
     }
 }
 
+fn deferred_views<V: ViewType, K>(
+    allocator: &ViewAllocator,
+    view: Option<GpuView<V>>,
+    cached: &mut HashMap<K, GpuView<V>>,
+) -> Vec<DeferredView<V>> {
+    view.into_iter()
+        .chain(cached.drain().map(|(_, view)| view))
+        .map(|view| DeferredView::new(allocator.clone(), view))
+        .collect()
+}
+
 impl Resource for Image {
     type Desc = ImageDesc;
     type Access = ViewAccess;
@@ -333,6 +325,10 @@ impl Resource for Image {
         &self.raw
     }
 
+    fn get_state(&self) -> &ResourceState {
+        &self.state
+    }
+
     fn get_desc(&self) -> Self::Desc {
         self.desc.clone()
     }
@@ -343,18 +339,29 @@ impl Resource for Image {
         access: Self::Access,
         init_state: ResourceStates,
     ) -> Self {
+        let raw_desc = desc.clone().into();
+
         let resource: dx::Resource = device
             .raw
             .create_committed_resource(
                 &dx::HeapProperties::default(),
                 dx::HeapFlags::empty(),
-                &desc.clone().into(),
+                &raw_desc,
                 init_state.as_raw(),
                 desc.clear_color().as_ref(),
             )
             .unwrap();
 
-        Self::inner_new(device, resource, desc, access, init_state, None)
+        let memory = device.track_resource(&raw_desc, MemoryHeapType::Gpu, MemoryKind::Committed);
+
+        Self::inner_new(
+            device,
+            resource,
+            desc,
+            access,
+            init_state,
+            ResourceMemory::Committed(memory),
+        )
     }
 
     fn from_raw_placed(
@@ -383,7 +390,14 @@ impl Resource for Image {
             )
             .unwrap();
 
-        Self::inner_new(&heap.device, raw, desc, access, state, Some(allocation))
+        Self::inner_new(
+            &heap.device,
+            raw,
+            desc,
+            access,
+            state,
+            ResourceMemory::Placed(allocation),
+        )
     }
 }
 
@@ -393,16 +407,9 @@ impl ImageResource for Image {
         state: ResourceStates,
         subresource: Option<SubresourceIndex>,
     ) -> Option<dx::ResourceBarrier<'_>> {
-        let index = if let Some(subindex) = subresource {
-            assert!(!self.is_array() || subindex.array_index == 0);
-
-            Some(subindex.mip_index + subindex.array_index * (self.desc.mip_levels as usize))
-        } else {
-            None
-        };
-
-        if let Some(index) = index {
-            let old = self.state[index].swap(state, std::sync::atomic::Ordering::Relaxed);
+        if let Some(subresource) = subresource {
+            let index = self.subresource_index(subresource);
+            let old = self.state.swap(index, state);
 
             if old != state {
                 Some(dx::ResourceBarrier::transition(
@@ -416,11 +423,8 @@ impl ImageResource for Image {
             }
         } else {
             //TODO: Return smallvec?
-            let old = self.state[0].swap(state, std::sync::atomic::Ordering::Relaxed);
-            self.state
-                .iter()
-                .skip(1)
-                .for_each(|s| s.store(state, std::sync::atomic::Ordering::Relaxed));
+            let old = self.state.swap(0, state);
+            self.state.set_all(state);
 
             if old != state {
                 Some(dx::ResourceBarrier::transition(
@@ -434,6 +438,12 @@ impl ImageResource for Image {
             }
         }
     }
+
+    fn subresource_index(&self, subresource: SubresourceIndex) -> usize {
+        assert!(!self.is_array() || subresource.array_index == 0);
+
+        subresource.mip_index + subresource.array_index * (self.desc.mip_levels as usize)
+    }
 }
 
 impl ShareableImage for Image {}
@@ -1,12 +1,11 @@
 use std::{fmt::Debug, marker::PhantomData, ops::Deref, sync::Arc};
 
-use atomig::Atomic;
 use oxidx::dx::{self, IDevice, IGraphicsCommandListExt, IResource};
 
 use crate::graphics::{
     commands::{WorkerThread, WorkerType},
     device::Device,
-    heaps::{Allocation, MemoryHeap},
+    heaps::{Allocation, MemoryHeap, MemoryKind, ResourceMemory},
     types::MemoryHeapType,
     ResourceStates, Sealed,
 };
@@ -14,7 +13,7 @@ use crate::graphics::{
 use super::{
     buffer::BaseBuffer,
     staging_buffer::{StagingBuffer, StagingBufferDesc},
-    BufferResource, BufferResourceDesc, NoGpuAccess, Resource, ResourceDesc,
+    BufferResource, BufferResourceDesc, NoGpuAccess, Resource, ResourceDesc, ResourceState,
 };
 
 pub trait IndexBufferType: Sealed + Clone {
@@ -71,7 +70,7 @@ impl<T: IndexBufferType> IndexBuffer<T> {
         resource: dx::Resource,
         desc: IndexBufferDesc<T>,
         state: ResourceStates,
-        allocation: Option<Allocation>,
+        memory: ResourceMemory,
     ) -> Self {
         let view = dx::IndexBufferView::new(
             resource.get_gpu_virtual_address(),
@@ -94,9 +93,9 @@ impl<T: IndexBufferType> IndexBuffer<T> {
             buffer: BaseBuffer {
                 raw: resource,
                 size: desc.count * size_of::<T>(),
-                state: Atomic::new(state),
+                state: ResourceState::new(state, 1),
                 flags: dx::ResourceFlags::empty(),
-                allocation,
+                memory,
             },
             count: desc.count,
             staging_buffer,
@@ -152,6 +151,10 @@ impl<T: IndexBufferType> Resource for IndexBuffer<T> {
         &self.buffer.raw
     }
 
+    fn get_state(&self) -> &ResourceState {
+        &self.buffer.state
+    }
+
     fn get_desc(&self) -> Self::Desc {
         IndexBufferDesc {
             count: self.count,
@@ -172,18 +175,28 @@ impl<T: IndexBufferType> Resource for IndexBuffer<T> {
             init_state = ResourceStates::GenericRead;
         }
 
+        let raw_desc = dx::ResourceDesc::buffer(desc.count * element_byte_size);
+
         let resource: dx::Resource = device
             .raw
             .create_committed_resource(
                 &dx::HeapProperties::default(),
                 dx::HeapFlags::empty(),
-                &dx::ResourceDesc::buffer(desc.count * element_byte_size),
+                &raw_desc,
                 init_state.as_raw(),
                 None,
             )
             .unwrap();
 
-        Self::inner_new(device, resource, desc, init_state, None)
+        let memory = device.track_resource(&raw_desc, MemoryHeapType::Gpu, MemoryKind::Committed);
+
+        Self::inner_new(
+            device,
+            resource,
+            desc,
+            init_state,
+            ResourceMemory::Committed(memory),
+        )
     }
 
     fn from_raw_placed(
@@ -218,16 +231,19 @@ impl<T: IndexBufferType> Resource for IndexBuffer<T> {
             )
             .unwrap();
 
-        Self::inner_new(&heap.device, raw, desc, state, Some(allocation))
+        Self::inner_new(
+            &heap.device,
+            raw,
+            desc,
+            state,
+            ResourceMemory::Placed(allocation),
+        )
     }
 }
 
 impl<T: IndexBufferType> BufferResource for IndexBuffer<T> {
     fn get_barrier(&self, state: ResourceStates) -> Option<dx::ResourceBarrier<'_>> {
-        let old = self
-            .buffer
-            .state
-            .swap(state, std::sync::atomic::Ordering::Relaxed);
+        let old = self.buffer.state.swap(0, state);
 
         if old != state {
             Some(dx::ResourceBarrier::transition(
@@ -3,19 +3,25 @@ mod resource;
 
 mod buffer;
 mod counter_buffer;
+mod dirty_region;
 mod image;
 mod index_buffer;
 mod shared_resource;
+mod shared_ring;
+mod slot_ring;
 mod staging_buffer;
 mod storage_buffer;
 mod vertex_buffer;
 
 pub use constant_buffer::*;
 pub use counter_buffer::*;
+pub use dirty_region::*;
 pub use image::*;
 pub use index_buffer::*;
 pub use resource::*;
 pub use shared_resource::*;
+pub use shared_ring::*;
+pub use slot_ring::*;
 pub use staging_buffer::*;
 pub use storage_buffer::*;
 pub use vertex_buffer::*;
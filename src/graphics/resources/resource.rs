@@ -1,6 +1,10 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use atomig::Atomic;
 use oxidx::dx;
 
 use crate::graphics::{
+    commands::TrackedState,
     heaps::{Allocation, MemoryHeap},
     views::ViewAllocator,
     ResourceStates, SubresourceIndex,
@@ -14,6 +18,7 @@ pub trait Resource {
 
     fn get_raw(&self) -> &dx::Resource;
     fn get_desc(&self) -> Self::Desc;
+    fn get_state(&self) -> &ResourceState;
 
     fn from_desc(
         device: &Device,
@@ -58,11 +63,77 @@ pub trait ImageResource: Resource<Desc: ImageResourceDesc> {
         state: ResourceStates,
         subresource: Option<SubresourceIndex>,
     ) -> Option<dx::ResourceBarrier<'_>>;
+
+    fn subresource_index(&self, subresource: SubresourceIndex) -> usize;
 }
 
 pub trait ShareableBuffer: BufferResource<Desc: ShareableBufferDesc> {}
 pub trait ShareableImage: ImageResource<Desc: ShareableImageDesc> {}
 
+#[derive(Clone, Debug)]
+pub struct ResourceState(Arc<[Atomic<ResourceStates>]>);
+
+impl ResourceState {
+    pub(crate) fn new(state: ResourceStates, count: usize) -> Self {
+        Self((0..count).map(|_| Atomic::new(state)).collect())
+    }
+
+    pub(crate) fn get(&self, subresource: usize) -> ResourceStates {
+        self.0[subresource].load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set(&self, subresource: usize, state: ResourceStates) {
+        self.0[subresource].store(state, Ordering::Relaxed);
+    }
+
+    pub(crate) fn swap(&self, subresource: usize, state: ResourceStates) -> ResourceStates {
+        self.0[subresource].swap(state, Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_all(&self, state: ResourceStates) {
+        self.0
+            .iter()
+            .for_each(|s| s.store(state, Ordering::Relaxed));
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct TrackedResource {
+    pub(crate) raw: dx::Resource,
+    pub(crate) state: ResourceState,
+}
+
+impl TrackedResource {
+    pub(crate) fn new<R: Resource>(resource: &R) -> Self {
+        Self {
+            raw: resource.get_raw().clone(),
+            state: resource.get_state().clone(),
+        }
+    }
+}
+
+impl TrackedState for TrackedResource {
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.state.0) as *const () as usize
+    }
+
+    fn subresource_count(&self) -> usize {
+        self.state.len()
+    }
+
+    fn global_state(&self, subresource: usize) -> ResourceStates {
+        self.state.get(subresource)
+    }
+
+    fn set_global_state(&self, subresource: usize, state: ResourceStates) {
+        self.state.set(subresource, state);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum GpuAccess {
     Address,
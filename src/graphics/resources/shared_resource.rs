@@ -1,21 +1,38 @@
+use std::sync::Arc;
+
 use oxidx::dx;
+use parking_lot::Mutex;
 
 use crate::graphics::{
-    heaps::MemoryHeap,
+    heaps::{Allocation, MemoryHeap},
     resources::{ImageResourceDesc, ShareableBufferDesc, ShareableImageDesc},
+    types::SubresourceIndex,
     MemoryHeapType, ResourceStates,
 };
 
-use super::{super::device::Device, Resource, ShareableBuffer, ShareableImage};
+use super::{
+    super::device::Device, DirtyRect, DirtyUpdate, Resource, ShareableBuffer, ShareableImage,
+    SharedDirtyRegions,
+};
 
 #[derive(Clone, Debug)]
 pub struct SharedResource<R: Resource> {
     owner: Device,
     state: SharedResourceState<R>,
+    allocation: Allocation,
+    dirty: Arc<Mutex<SharedDirtyRegions>>,
+    consumer: usize,
 
     desc: R::Desc,
 }
 
+fn new_dirty() -> (Arc<Mutex<SharedDirtyRegions>>, usize) {
+    let mut dirty = SharedDirtyRegions::default();
+    let consumer = dirty.connect();
+
+    (Arc::new(Mutex::new(dirty)), consumer)
+}
+
 #[derive(Clone, Debug)]
 enum SharedResourceState<R: Resource> {
     CrossAdapter { cross: R },
@@ -44,17 +61,40 @@ impl<R: Resource> SharedResource<R> {
     pub fn owner(&self) -> &Device {
         &self.owner
     }
+
+    pub fn allocation(&self) -> &Allocation {
+        &self.allocation
+    }
 }
 
 impl<R: ShareableImage> SharedResource<R> {
+    pub fn mark_dirty(&self, subresource: SubresourceIndex, rect: DirtyRect) {
+        self.dirty.lock().dirty.mark(subresource, rect);
+    }
+
+    pub fn mark_all_dirty(&self) {
+        self.dirty.lock().dirty.mark_all();
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.lock().dirty.is_clean()
+    }
+
+    pub(crate) fn take_dirty(&self) -> DirtyUpdate {
+        self.dirty.lock().take_dirty(self.consumer)
+    }
+
+    pub(crate) fn take_pushed(&self) -> DirtyUpdate {
+        self.dirty.lock().take_pushed(self.consumer)
+    }
+
     pub(in super::super) fn inner_new_image(
         owner: &MemoryHeap,
-        offset: usize,
         desc: R::Desc,
         access: R::Access,
         local_state: ResourceStates,
         share_state: ResourceStates,
-    ) -> Self {
+    ) -> Option<Self> {
         assert!(owner.mtype == MemoryHeapType::Shared);
 
         let (flags, state) = if owner.device.is_cross_adapter_texture_supported() {
@@ -71,29 +111,37 @@ impl<R: ShareableImage> SharedResource<R> {
             .with_flags(flags)
             .with_layout(dx::TextureLayout::RowMajor);
 
-        let cross = owner.create_placed_texture(cross_desc, offset, access.clone(), state);
+        let allocation = owner.allocate_for(&cross_desc.clone().into())?;
+        let cross = owner.place_texture(cross_desc, allocation.clone(), access.clone(), state);
+
+        let (dirty, consumer) = new_dirty();
 
         if owner.device.is_cross_adapter_texture_supported() {
-            Self {
+            Some(Self {
                 owner: owner.device.clone(),
                 state: SharedResourceState::CrossAdapter { cross },
+                allocation,
+                dirty,
+                consumer,
                 desc,
-            }
+            })
         } else {
             let local = R::from_desc(&owner.device, desc.clone(), access, local_state);
 
-            Self {
+            Some(Self {
                 owner: owner.device.clone(),
                 state: SharedResourceState::Binded { cross, local },
+                allocation,
+                dirty,
+                consumer,
                 desc,
-            }
+            })
         }
     }
 
     pub fn connect_texture(
         &self,
         other: &MemoryHeap,
-        offset: usize,
         access: R::Access,
         local_state: ResourceStates,
         share_state: ResourceStates,
@@ -109,9 +157,10 @@ impl<R: ShareableImage> SharedResource<R> {
             (dx::ResourceFlags::AllowCrossAdapter, share_state)
         };
 
-        let cross = other.create_placed_texture(
+        let allocation = other.share_allocation(&self.allocation);
+        let cross = other.place_texture(
             self.cross_resource().get_desc().with_flags(flags),
-            offset,
+            allocation.clone(),
             access.clone(),
             state,
         );
@@ -120,6 +169,9 @@ impl<R: ShareableImage> SharedResource<R> {
             Self {
                 owner: other.device.clone(),
                 state: SharedResourceState::CrossAdapter { cross },
+                allocation,
+                dirty: Arc::clone(&self.dirty),
+                consumer: self.dirty.lock().connect(),
                 desc: self.desc.clone(),
             }
         } else {
@@ -128,6 +180,9 @@ impl<R: ShareableImage> SharedResource<R> {
             Self {
                 owner: other.device.clone(),
                 state: SharedResourceState::Binded { cross, local },
+                allocation,
+                dirty: Arc::clone(&self.dirty),
+                consumer: self.dirty.lock().connect(),
                 desc: self.desc.clone(),
             }
         }
@@ -137,12 +192,11 @@ impl<R: ShareableImage> SharedResource<R> {
 impl<R: ShareableBuffer> SharedResource<R> {
     pub(in super::super) fn inner_new_buffer(
         owner: &MemoryHeap,
-        offset: usize,
         desc: R::Desc,
         access: R::Access,
         local_state: ResourceStates,
         share_state: ResourceStates,
-    ) -> Self {
+    ) -> Option<Self> {
         assert!(owner.mtype == MemoryHeapType::Shared);
 
         let (flags, state) = if owner.device.is_cross_adapter_texture_supported() {
@@ -156,29 +210,37 @@ impl<R: ShareableBuffer> SharedResource<R> {
 
         let cross_desc = desc.clone().with_flags(flags);
 
-        let cross = owner.create_placed_buffer(cross_desc, offset, access.clone(), state);
+        let allocation = owner.allocate_for(&cross_desc.clone().into())?;
+        let cross = owner.place_buffer(cross_desc, allocation.clone(), access.clone(), state);
+
+        let (dirty, consumer) = new_dirty();
 
         if owner.device.is_cross_adapter_texture_supported() {
-            Self {
+            Some(Self {
                 owner: owner.device.clone(),
                 state: SharedResourceState::CrossAdapter { cross },
+                allocation,
+                dirty,
+                consumer,
                 desc,
-            }
+            })
         } else {
             let local = R::from_desc(&owner.device, desc.clone(), access, local_state);
 
-            Self {
+            Some(Self {
                 owner: owner.device.clone(),
                 state: SharedResourceState::Binded { cross, local },
+                allocation,
+                dirty,
+                consumer,
                 desc,
-            }
+            })
         }
     }
 
     pub fn connect_buffer(
         &self,
         other: &MemoryHeap,
-        offset: usize,
         access: R::Access,
         local_state: ResourceStates,
         share_state: ResourceStates,
@@ -194,9 +256,10 @@ impl<R: ShareableBuffer> SharedResource<R> {
             (dx::ResourceFlags::AllowCrossAdapter, share_state)
         };
 
-        let cross = other.create_placed_buffer(
+        let allocation = other.share_allocation(&self.allocation);
+        let cross = other.place_buffer(
             self.cross_resource().get_desc().with_flags(flags),
-            offset,
+            allocation.clone(),
             access.clone(),
             state,
         );
@@ -205,6 +268,9 @@ impl<R: ShareableBuffer> SharedResource<R> {
             Self {
                 owner: other.device.clone(),
                 state: SharedResourceState::CrossAdapter { cross },
+                allocation,
+                dirty: Arc::clone(&self.dirty),
+                consumer: self.dirty.lock().connect(),
                 desc: self.desc.clone(),
             }
         } else {
@@ -213,6 +279,9 @@ impl<R: ShareableBuffer> SharedResource<R> {
             Self {
                 owner: other.device.clone(),
                 state: SharedResourceState::Binded { cross, local },
+                allocation,
+                dirty: Arc::clone(&self.dirty),
+                consumer: self.dirty.lock().connect(),
                 desc: self.desc.clone(),
             }
         }
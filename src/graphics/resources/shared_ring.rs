@@ -0,0 +1,202 @@
+use crate::graphics::{
+    commands::{CommandQueue, WorkerType},
+    fence::{SharedFence, SharedSignal},
+    heaps::MemoryHeap,
+    ResourceStates,
+};
+
+use super::{Resource, ShareableBuffer, ShareableImage, SharedResource, SlotRing};
+
+#[derive(Debug)]
+pub struct SharedResourceRing<R: Resource> {
+    producer: Vec<SharedResource<R>>,
+    consumer: Vec<SharedResource<R>>,
+
+    written: [SharedFence; 2],
+    read: [SharedFence; 2],
+
+    ring: SlotRing<SharedSignal>,
+}
+
+impl<R: Resource> SharedResourceRing<R> {
+    fn inner_new(
+        producer_heap: &MemoryHeap,
+        consumer_heap: &MemoryHeap,
+        producer: Vec<SharedResource<R>>,
+        consumer: Vec<SharedResource<R>>,
+    ) -> Self {
+        let written = producer_heap.device.create_shared_fence();
+        let written_remote = written.connect(consumer_heap.device.clone());
+
+        let read = consumer_heap.device.create_shared_fence();
+        let read_remote = read.connect(producer_heap.device.clone());
+
+        Self {
+            ring: SlotRing::new(producer.len()),
+            producer,
+            consumer,
+            written: [written, written_remote],
+            read: [read_remote, read],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.producer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.producer.is_empty()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.ring.pending()
+    }
+
+    pub fn producer_resource(&self, slot: usize) -> &SharedResource<R> {
+        &self.producer[slot]
+    }
+
+    pub fn consumer_resource(&self, slot: usize) -> &SharedResource<R> {
+        &self.consumer[slot]
+    }
+
+    pub fn begin_write<T: WorkerType>(
+        &mut self,
+        queue: &CommandQueue<T>,
+    ) -> Option<&SharedResource<R>> {
+        let access = self.ring.begin_write()?;
+
+        if let Some(signal) = &access.wait {
+            queue.wait_shared(&self.read[0], signal);
+        }
+
+        Some(&self.producer[access.slot])
+    }
+
+    pub fn end_write<T: WorkerType>(&mut self, queue: &CommandQueue<T>) -> SharedSignal {
+        let signal = queue.signal_shared(&self.written[0]);
+        self.ring.end_write(signal.clone());
+
+        signal
+    }
+
+    pub fn begin_read<T: WorkerType>(
+        &mut self,
+        queue: &CommandQueue<T>,
+    ) -> Option<&SharedResource<R>> {
+        let access = self.ring.begin_read()?;
+
+        if let Some(signal) = &access.wait {
+            queue.wait_shared(&self.written[1], signal);
+        }
+
+        Some(&self.consumer[access.slot])
+    }
+
+    pub fn end_read<T: WorkerType>(&mut self, queue: &CommandQueue<T>) -> SharedSignal {
+        let signal = queue.signal_shared(&self.read[1]);
+        self.ring.end_read(signal.clone());
+
+        signal
+    }
+}
+
+impl<R: ShareableImage> SharedResourceRing<R> {
+    pub(in super::super) fn inner_new_images(
+        producer: (&MemoryHeap, R::Access),
+        consumer: (&MemoryHeap, R::Access),
+        count: usize,
+        desc: R::Desc,
+        local_state: ResourceStates,
+        share_state: ResourceStates,
+    ) -> Option<Self> {
+        assert!(
+            count > 0,
+            "SharedResourceRing: at least one slot is required"
+        );
+
+        let (producer_heap, producer_access) = producer;
+        let (consumer_heap, consumer_access) = consumer;
+
+        let produced = (0..count)
+            .map(|_| {
+                SharedResource::inner_new_image(
+                    producer_heap,
+                    desc.clone(),
+                    producer_access.clone(),
+                    local_state,
+                    share_state,
+                )
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let consumed = produced
+            .iter()
+            .map(|resource| {
+                resource.connect_texture(
+                    consumer_heap,
+                    consumer_access.clone(),
+                    local_state,
+                    share_state,
+                )
+            })
+            .collect();
+
+        Some(Self::inner_new(
+            producer_heap,
+            consumer_heap,
+            produced,
+            consumed,
+        ))
+    }
+}
+
+impl<R: ShareableBuffer> SharedResourceRing<R> {
+    pub(in super::super) fn inner_new_buffers(
+        producer: (&MemoryHeap, R::Access),
+        consumer: (&MemoryHeap, R::Access),
+        count: usize,
+        desc: R::Desc,
+        local_state: ResourceStates,
+        share_state: ResourceStates,
+    ) -> Option<Self> {
+        assert!(
+            count > 0,
+            "SharedResourceRing: at least one slot is required"
+        );
+
+        let (producer_heap, producer_access) = producer;
+        let (consumer_heap, consumer_access) = consumer;
+
+        let produced = (0..count)
+            .map(|_| {
+                SharedResource::inner_new_buffer(
+                    producer_heap,
+                    desc.clone(),
+                    producer_access.clone(),
+                    local_state,
+                    share_state,
+                )
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let consumed = produced
+            .iter()
+            .map(|resource| {
+                resource.connect_buffer(
+                    consumer_heap,
+                    consumer_access.clone(),
+                    local_state,
+                    share_state,
+                )
+            })
+            .collect();
+
+        Some(Self::inner_new(
+            producer_heap,
+            consumer_heap,
+            produced,
+            consumed,
+        ))
+    }
+}
@@ -0,0 +1,216 @@
+#[derive(Clone, Debug)]
+struct Slot<S> {
+    written: Option<S>,
+    read: Option<S>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotAccess<S> {
+    pub slot: usize,
+    pub wait: Option<S>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SlotRing<S = u64> {
+    slots: Vec<Slot<S>>,
+    writes: u64,
+    reads: u64,
+    writing: bool,
+    reading: bool,
+}
+
+impl<S: Clone> SlotRing<S> {
+    pub fn new(count: usize) -> Self {
+        assert!(count > 0, "SlotRing: at least one slot is required");
+
+        Self {
+            slots: (0..count)
+                .map(|_| Slot {
+                    written: None,
+                    read: None,
+                })
+                .collect(),
+            writes: 0,
+            reads: 0,
+            writing: false,
+            reading: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn pending(&self) -> usize {
+        (self.writes - self.reads) as usize
+    }
+
+    pub fn write_slot(&self) -> usize {
+        (self.writes % self.slots.len() as u64) as usize
+    }
+
+    pub fn read_slot(&self) -> usize {
+        (self.reads % self.slots.len() as u64) as usize
+    }
+
+    pub fn can_write(&self) -> bool {
+        !self.writing && self.pending() < self.slots.len()
+    }
+
+    pub fn can_read(&self) -> bool {
+        !self.reading && self.pending() > 0
+    }
+
+    pub fn begin_write(&mut self) -> Option<SlotAccess<S>> {
+        if !self.can_write() {
+            return None;
+        }
+
+        self.writing = true;
+        let slot = self.write_slot();
+
+        Some(SlotAccess {
+            slot,
+            wait: self.slots[slot].read.clone(),
+        })
+    }
+
+    pub fn end_write(&mut self, signal: S) -> usize {
+        assert!(self.writing, "SlotRing: end_write without begin_write");
+
+        let slot = self.write_slot();
+        self.slots[slot].written = Some(signal);
+        self.writes += 1;
+        self.writing = false;
+
+        slot
+    }
+
+    pub fn begin_read(&mut self) -> Option<SlotAccess<S>> {
+        if !self.can_read() {
+            return None;
+        }
+
+        self.reading = true;
+        let slot = self.read_slot();
+
+        Some(SlotAccess {
+            slot,
+            wait: self.slots[slot].written.clone(),
+        })
+    }
+
+    pub fn end_read(&mut self, signal: S) -> usize {
+        assert!(self.reading, "SlotRing: end_read without begin_read");
+
+        let slot = self.read_slot();
+        self.slots[slot].read = Some(signal);
+        self.reads += 1;
+        self.reading = false;
+
+        slot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SlotAccess, SlotRing};
+
+    #[test]
+    fn test_rotates_through_slots() {
+        let mut ring = SlotRing::new(3);
+
+        for frame in 0..6u64 {
+            let write = ring.begin_write().unwrap();
+            assert_eq!(write.slot, frame as usize % 3);
+            ring.end_write(frame + 1);
+
+            let read = ring.begin_read().unwrap();
+            assert_eq!(read.slot, frame as usize % 3);
+            assert_eq!(read.wait, Some(frame + 1));
+            ring.end_read(frame + 1);
+        }
+    }
+
+    #[test]
+    fn test_producer_writes_next_slot_while_consumer_reads() {
+        let mut ring = SlotRing::new(2);
+
+        ring.begin_write().unwrap();
+        ring.end_write(1);
+
+        let read = ring.begin_read().unwrap();
+        let write = ring.begin_write().unwrap();
+
+        assert_eq!(read.slot, 0);
+        assert_eq!(write.slot, 1);
+    }
+
+    #[test]
+    fn test_full_ring_blocks_producer() {
+        let mut ring = SlotRing::new(2);
+
+        for value in 1..=2 {
+            ring.begin_write().unwrap();
+            ring.end_write(value);
+        }
+
+        assert_eq!(ring.begin_write(), None);
+
+        ring.begin_read().unwrap();
+        assert_eq!(ring.begin_write(), None);
+        ring.end_read(10);
+
+        assert_eq!(
+            ring.begin_write(),
+            Some(SlotAccess {
+                slot: 0,
+                wait: Some(10)
+            })
+        );
+    }
+
+    #[test]
+    fn test_empty_ring_blocks_consumer() {
+        let mut ring: SlotRing = SlotRing::new(2);
+
+        assert_eq!(ring.begin_read(), None);
+
+        ring.begin_write().unwrap();
+        assert_eq!(ring.begin_read(), None);
+    }
+
+    #[test]
+    fn test_never_writes_slot_being_read() {
+        let mut ring = SlotRing::new(3);
+        let mut reading = None;
+        let mut signal = 0;
+
+        for step in 0..300u64 {
+            if step % 3 != 2 {
+                if let Some(write) = ring.begin_write() {
+                    assert_ne!(Some(write.slot), reading);
+                    signal += 1;
+                    ring.end_write(signal);
+                }
+            }
+
+            if step % 2 == 0 {
+                if reading.is_some() {
+                    signal += 1;
+                    ring.end_read(signal);
+                    reading = None;
+                } else if let Some(read) = ring.begin_read() {
+                    assert!(read.wait.is_some());
+                    reading = Some(read.slot);
+                }
+            }
+
+            assert!(ring.pending() <= ring.len());
+        }
+    }
+}
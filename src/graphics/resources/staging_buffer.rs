@@ -5,19 +5,19 @@ use std::{
     sync::Arc,
 };
 
-use atomig::Atomic;
 use oxidx::dx::{self, IDevice, IResource};
 use parking_lot::Mutex;
 
 use crate::graphics::{
     device::Device,
-    heaps::{Allocation, MemoryHeap},
+    heaps::{Allocation, MemoryHeap, MemoryKind, ResourceMemory},
     utils::NonNullSend,
     MemoryHeapType, ResourceStates,
 };
 
 use super::{
     buffer::BaseBuffer, BufferResource, BufferResourceDesc, NoGpuAccess, Resource, ResourceDesc,
+    ResourceState,
 };
 
 #[derive(Clone, Debug)]
@@ -45,7 +45,7 @@ impl<T: Clone> StagingBuffer<T> {
         resource: dx::Resource,
         desc: StagingBufferDesc<T>,
         state: ResourceStates,
-        allocation: Option<Allocation>,
+        memory: ResourceMemory,
     ) -> Self {
         let mapped_data = resource.map::<T>(0, None).unwrap();
 
@@ -53,9 +53,9 @@ impl<T: Clone> StagingBuffer<T> {
             buffer: BaseBuffer {
                 raw: resource,
                 size: desc.count * size_of::<T>(),
-                state: Atomic::new(state),
+                state: ResourceState::new(state, 1),
                 flags: dx::ResourceFlags::empty(),
-                allocation,
+                memory,
             },
             count: desc.count,
             readback: desc.readback,
@@ -67,10 +67,7 @@ impl<T: Clone> StagingBuffer<T> {
 
 impl<T: Clone> StagingBuffer<T> {
     pub fn write_data(&self, src: &[T], write_range: Option<Range<usize>>) {
-        assert_eq!(
-            self.buffer.state.load(std::sync::atomic::Ordering::Relaxed),
-            ResourceStates::GenericRead
-        );
+        assert_eq!(self.buffer.state.get(0), ResourceStates::GenericRead);
 
         let mut guard = self.mapped_data.lock();
         let slice = unsafe { std::slice::from_raw_parts_mut(guard.as_mut(), self.count) };
@@ -118,6 +115,10 @@ impl<T: Clone> Resource for StagingBuffer<T> {
         &self.buffer.raw
     }
 
+    fn get_state(&self) -> &ResourceState {
+        &self.buffer.state
+    }
+
     fn get_desc(&self) -> Self::Desc {
         StagingBufferDesc {
             count: self.count,
@@ -134,26 +135,35 @@ impl<T: Clone> Resource for StagingBuffer<T> {
     ) -> Self {
         let element_byte_size = size_of::<T>();
 
-        let heap_props = if desc.readback {
+        let (heap_props, heap_type) = if desc.readback {
             assert_eq!(init_state, ResourceStates::CopyDst);
-            dx::HeapProperties::readback()
+            (dx::HeapProperties::readback(), MemoryHeapType::Readback)
         } else {
             assert_eq!(init_state, ResourceStates::GenericRead);
-            dx::HeapProperties::upload()
+            (dx::HeapProperties::upload(), MemoryHeapType::Cpu)
         };
 
+        let raw_desc = dx::ResourceDesc::buffer(desc.count * element_byte_size);
+
         let resource: dx::Resource = device
             .raw
             .create_committed_resource(
                 &heap_props,
                 dx::HeapFlags::empty(),
-                &dx::ResourceDesc::buffer(desc.count * element_byte_size),
+                &raw_desc,
                 init_state.as_raw(),
                 None,
             )
             .unwrap();
 
-        Self::inner_new(resource, desc, init_state, None)
+        let memory = device.track_resource(&raw_desc, heap_type, MemoryKind::Staging);
+
+        Self::inner_new(
+            resource,
+            desc,
+            init_state,
+            ResourceMemory::Committed(memory),
+        )
     }
 
     fn from_raw_placed(
@@ -185,16 +195,13 @@ impl<T: Clone> Resource for StagingBuffer<T> {
             )
             .unwrap();
 
-        Self::inner_new(raw, desc, state, Some(allocation))
+        Self::inner_new(raw, desc, state, ResourceMemory::Placed(allocation))
     }
 }
 
 impl<T: Clone> BufferResource for StagingBuffer<T> {
     fn get_barrier(&self, state: ResourceStates) -> Option<dx::ResourceBarrier<'_>> {
-        let old = self
-            .buffer
-            .state
-            .swap(state, std::sync::atomic::Ordering::Relaxed);
+        let old = self.buffer.state.swap(0, state);
 
         if old != state {
             Some(dx::ResourceBarrier::transition(
@@ -5,19 +5,18 @@ use std::{
     sync::{Arc, OnceLock},
 };
 
-use atomig::Atomic;
 use oxidx::dx::{self, IDevice};
 
 use crate::graphics::{
     device::Device,
-    heaps::{Allocation, MemoryHeap},
+    heaps::{Allocation, MemoryHeap, MemoryKind, ResourceMemory},
     views::{GpuView, SrvView, UavView},
     MemoryHeapType, ResourceStates,
 };
 
 use super::{
     buffer::BaseBuffer, BufferResource, BufferResourceDesc, CounterBuffer, CounterBufferDesc,
-    Resource, ResourceDesc, ViewAccess,
+    Resource, ResourceDesc, ResourceState, ViewAccess,
 };
 
 #[derive(Clone, Debug)]
@@ -50,7 +49,7 @@ impl<T> StorageBuffer<T> {
         resource: dx::Resource,
         desc: StorageBufferDesc<T>,
         state: ResourceStates,
-        allocation: Option<Allocation>,
+        memory: ResourceMemory,
         access: ViewAccess,
     ) -> Self {
         let counter_buffer = CounterBuffer::from_desc(
@@ -64,9 +63,9 @@ impl<T> StorageBuffer<T> {
             buffer: BaseBuffer {
                 raw: resource,
                 size: desc.count * size_of::<T>(),
-                state: Atomic::new(state),
+                state: ResourceState::new(state, 1),
                 flags: dx::ResourceFlags::empty(),
-                allocation,
+                memory,
             },
             count: desc.count,
             counter_buffer,
@@ -134,6 +133,10 @@ impl<T> Resource for StorageBuffer<T> {
         &self.buffer.raw
     }
 
+    fn get_state(&self) -> &ResourceState {
+        &self.buffer.state
+    }
+
     fn get_desc(&self) -> Self::Desc {
         StorageBufferDesc {
             count: self.count,
@@ -149,18 +152,29 @@ impl<T> Resource for StorageBuffer<T> {
     ) -> Self {
         let element_byte_size = size_of::<T>();
 
+        let raw_desc = dx::ResourceDesc::buffer(desc.count * element_byte_size);
+
         let resource: dx::Resource = device
             .raw
             .create_committed_resource(
                 &dx::HeapProperties::default(),
                 dx::HeapFlags::empty(),
-                &dx::ResourceDesc::buffer(desc.count * element_byte_size),
+                &raw_desc,
                 init_state.as_raw(),
                 None,
             )
             .unwrap();
 
-        Self::inner_new(device, resource, desc, init_state, None, access)
+        let memory = device.track_resource(&raw_desc, MemoryHeapType::Gpu, MemoryKind::Committed);
+
+        Self::inner_new(
+            device,
+            resource,
+            desc,
+            init_state,
+            ResourceMemory::Committed(memory),
+            access,
+        )
     }
 
     fn from_raw_placed(
@@ -186,16 +200,20 @@ impl<T> Resource for StorageBuffer<T> {
             )
             .unwrap();
 
-        Self::inner_new(&heap.device, raw, desc, state, Some(allocation), access)
+        Self::inner_new(
+            &heap.device,
+            raw,
+            desc,
+            state,
+            ResourceMemory::Placed(allocation),
+            access,
+        )
     }
 }
 
 impl<T> BufferResource for StorageBuffer<T> {
     fn get_barrier(&self, state: ResourceStates) -> Option<dx::ResourceBarrier<'_>> {
-        let old = self
-            .buffer
-            .state
-            .swap(state, std::sync::atomic::Ordering::Relaxed);
+        let old = self.buffer.state.swap(0, state);
 
         if old != state {
             Some(dx::ResourceBarrier::transition(
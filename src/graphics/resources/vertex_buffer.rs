@@ -1,19 +1,18 @@
 use std::{fmt::Debug, marker::PhantomData, ops::Deref, sync::Arc};
 
-use atomig::Atomic;
 use oxidx::dx::{self, IDevice, IGraphicsCommandListExt, IResource};
 
 use crate::graphics::{
     commands::{WorkerThread, WorkerType},
     device::Device,
-    heaps::{Allocation, MemoryHeap},
+    heaps::{Allocation, MemoryHeap, MemoryKind, ResourceMemory},
     MemoryHeapType, ResourceStates,
 };
 
 use super::{
     buffer::BaseBuffer,
     staging_buffer::{StagingBuffer, StagingBufferDesc},
-    BufferResource, BufferResourceDesc, NoGpuAccess, Resource, ResourceDesc,
+    BufferResource, BufferResourceDesc, NoGpuAccess, Resource, ResourceDesc, ResourceState,
 };
 
 #[derive(Clone, Debug)]
@@ -42,7 +41,7 @@ impl<T: Clone> VertexBuffer<T> {
         resource: dx::Resource,
         desc: VertexBufferDesc<T>,
         state: ResourceStates,
-        allocation: Option<Allocation>,
+        memory: ResourceMemory,
     ) -> Self {
         let view = dx::VertexBufferView::new(
             resource.get_gpu_virtual_address(),
@@ -65,9 +64,9 @@ impl<T: Clone> VertexBuffer<T> {
             buffer: BaseBuffer {
                 raw: resource,
                 size: desc.count * size_of::<T>(),
-                state: Atomic::new(state),
+                state: ResourceState::new(state, 1),
                 flags: dx::ResourceFlags::empty(),
-                allocation,
+                memory,
             },
             count: desc.count,
             staging_buffer,
@@ -123,6 +122,10 @@ impl<T: Clone> Resource for VertexBuffer<T> {
         &self.buffer.raw
     }
 
+    fn get_state(&self) -> &ResourceState {
+        &self.buffer.state
+    }
+
     fn get_desc(&self) -> Self::Desc {
         VertexBufferDesc {
             count: self.count,
@@ -143,18 +146,28 @@ impl<T: Clone> Resource for VertexBuffer<T> {
             init_state = ResourceStates::GenericRead;
         }
 
+        let raw_desc = dx::ResourceDesc::buffer(desc.count * element_byte_size);
+
         let resource: dx::Resource = device
             .raw
             .create_committed_resource(
                 &dx::HeapProperties::default(),
                 dx::HeapFlags::empty(),
-                &dx::ResourceDesc::buffer(desc.count * element_byte_size),
+                &raw_desc,
                 init_state.as_raw(),
                 None,
             )
             .unwrap();
 
-        Self::inner_new(device, resource, desc, init_state, None)
+        let memory = device.track_resource(&raw_desc, MemoryHeapType::Gpu, MemoryKind::Committed);
+
+        Self::inner_new(
+            device,
+            resource,
+            desc,
+            init_state,
+            ResourceMemory::Committed(memory),
+        )
     }
 
     fn from_raw_placed(
@@ -189,16 +202,19 @@ impl<T: Clone> Resource for VertexBuffer<T> {
             )
             .unwrap();
 
-        Self::inner_new(&heap.device, raw, desc, state, Some(allocation))
+        Self::inner_new(
+            &heap.device,
+            raw,
+            desc,
+            state,
+            ResourceMemory::Placed(allocation),
+        )
     }
 }
 
 impl<T: Clone> BufferResource for VertexBuffer<T> {
     fn get_barrier(&self, state: ResourceStates) -> Option<dx::ResourceBarrier<'_>> {
-        let old = self
-            .buffer
-            .state
-            .swap(state, std::sync::atomic::Ordering::Relaxed);
+        let old = self.buffer.state.swap(0, state);
 
         if old != state {
             Some(dx::ResourceBarrier::transition(
@@ -1,21 +1,27 @@
 use std::sync::Arc;
 
-use super::{GpuView, SamplerDesc, SamplerView, ViewAllocator};
+use super::{device::Device, GpuView, SamplerDesc, SamplerView, ViewAllocator};
 
 #[derive(Clone, Debug)]
 pub struct Sampler(Arc<SamplerInner>);
 
 #[derive(Debug)]
 pub struct SamplerInner {
+    device: Device,
     allocator: ViewAllocator,
     pub(crate) view: GpuView<SamplerView>,
 }
 
 impl Sampler {
-    pub(crate) fn inner_new(view_allocator: ViewAllocator, desc: &SamplerDesc) -> Self {
+    pub(crate) fn inner_new(
+        device: &Device,
+        view_allocator: ViewAllocator,
+        desc: &SamplerDesc,
+    ) -> Self {
         let view = view_allocator.push_sampler(&desc.as_raw());
 
         Self(Arc::new(SamplerInner {
+            device: device.clone(),
             allocator: view_allocator,
             view,
         }))
@@ -24,6 +30,6 @@ impl Sampler {
 
 impl Drop for SamplerInner {
     fn drop(&mut self) {
-        self.allocator.remove_sampler(self.view);
+        self.device.release_view(&self.allocator, self.view);
     }
 }
@@ -1,5 +1,11 @@
 mod shader;
+mod shader_compiler;
+mod shader_registry;
 mod shader_type;
+mod shader_watch;
 
 pub use shader::*;
+pub use shader_compiler::*;
+pub use shader_registry::*;
 pub use shader_type::*;
+pub use shader_watch::*;
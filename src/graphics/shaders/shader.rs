@@ -1,16 +1,25 @@
 use std::{ffi::CString, marker::PhantomData, ops::Deref, path::Path, sync::Arc};
 
 use oxidx::dx::{self, IBlobExt};
+use parking_lot::RwLock;
 use smallvec::SmallVec;
 
-use super::ShaderType;
+use crate::graphics::{
+    BoundResource, CacheKey, CacheKind, CbufferLayout, CbufferVariable, ComponentType, DiskCache,
+    ResourceKind, ShaderBindings, ShaderInput, UNBOUNDED,
+};
+
+use super::{
+    compile_dxc, is_dxil_container, CompilerKind, ShaderConfig, ShaderError, ShaderOptions,
+    ShaderType, Vertex,
+};
 
 #[derive(Clone, Debug)]
 pub struct Shader<T: ShaderType>(Arc<ShaderInner<T>>);
 
 #[derive(Debug)]
 pub struct ShaderInner<T: ShaderType> {
-    pub(crate) raw: dx::Blob,
+    raw: RwLock<dx::Blob>,
     _marker: PhantomData<T>,
 }
 
@@ -23,16 +32,91 @@ impl<T: ShaderType> Deref for Shader<T> {
 }
 
 impl<T: ShaderType> Shader<T> {
-    pub(crate) fn inner_new(
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub(crate) fn is_unique(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
+
+    pub(crate) fn compile(
         path: impl AsRef<Path>,
         entry_point: impl AsRef<str>,
-        defines: &[(&'static str, &'static str)],
-    ) -> Self {
-        let entry_point = CString::new(entry_point.as_ref()).unwrap();
+        options: &ShaderOptions,
+        cache: Option<&DiskCache>,
+    ) -> Result<Self, ShaderError> {
+        let path = path.as_ref();
+        let entry_point = entry_point.as_ref();
+        let target = options.target::<T>();
+
+        let key = cache.and_then(|_| CacheKey::shader(path, entry_point, &target, options).ok());
+
+        if let (Some(cache), Some(key)) = (cache, key) {
+            if let Some(bytecode) = cache.load(CacheKind::Shader, key) {
+                return Ok(Self::from_raw(dx::Blob::from_bytes(&bytecode).unwrap()));
+            }
+        }
+
+        let raw = if options.uses_dxc() {
+            let bytecode = compile_dxc(path, entry_point, &target, options)?;
+            dx::Blob::from_bytes(&bytecode).unwrap()
+        } else {
+            Self::compile_fxc(path, entry_point, &target, options)?
+        };
+        let shader = Self::from_raw(raw);
+
+        if let (Some(cache), Some(key)) = (cache, key) {
+            if let Err(err) = cache.store(CacheKind::Shader, key, shader.raw().as_slice()) {
+                tracing::warn!("Failed to cache shader {}: {}", path.display(), err);
+            }
+        }
+
+        Ok(shader)
+    }
+
+    // D3DReflect only understands DXBC, DXIL containers have to go through DXC.
+    fn reflect(&self) -> Result<dx::ShaderReflection, ShaderError> {
+        let raw = self.raw();
 
-        let defines = defines
+        let reflection = if is_dxil_container(raw.as_slice()) {
+            dx::DxcUtils::new().and_then(|utils| utils.create_reflection(raw.as_slice()))
+        } else {
+            dx::ShaderReflection::new(&raw)
+        };
+
+        reflection.map_err(ShaderError::reflection)
+    }
+
+    fn from_raw(raw: dx::Blob) -> Self {
+        Self(Arc::new(ShaderInner {
+            raw: RwLock::new(raw),
+            _marker: PhantomData,
+        }))
+    }
+
+    fn compile_fxc(
+        path: &Path,
+        entry_point: &str,
+        target: &str,
+        options: &ShaderOptions,
+    ) -> Result<dx::Blob, ShaderError> {
+        if !options.include_dirs.is_empty() {
+            return Err(ShaderError::IncludeDirsUnsupported(path.to_path_buf()));
+        }
+
+        let entry_point = CString::new(entry_point).unwrap();
+        let target = CString::new(target).unwrap();
+
+        let defines = options
+            .defines
             .iter()
-            .map(|(name, key)| (CString::new(*name).unwrap(), CString::new(*key).unwrap()))
+            .map(|(name, key)| {
+                (
+                    CString::new(name.as_str()).unwrap(),
+                    CString::new(key.as_str()).unwrap(),
+                )
+            })
             .collect::<SmallVec<[_; 4]>>();
 
         let defines = if !defines.is_empty() {
@@ -45,19 +129,142 @@ impl<T: ShaderType> Shader<T> {
             Default::default()
         };
 
-        let raw = dx::Blob::compile_from_file(
-            path,
-            &defines,
-            &entry_point,
-            T::TARGET,
-            dx::COMPILE_DEBUG | dx::COMPILE_SKIP_OPT,
-            0,
-        )
-        .unwrap();
+        let flags = match options.config {
+            ShaderConfig::Debug => dx::COMPILE_DEBUG | dx::COMPILE_SKIP_OPT,
+            ShaderConfig::Release => dx::COMPILE_OPTIMIZATION_LEVEL3,
+        };
 
-        Self(Arc::new(ShaderInner {
-            raw,
-            _marker: PhantomData,
-        }))
+        if let Err(error) = std::fs::metadata(path) {
+            return Err(ShaderError::Io {
+                path: path.to_path_buf(),
+                error,
+            });
+        }
+
+        dx::Blob::compile_from_file(path, &defines, &entry_point, &target, flags, 0)
+            .map_err(|err| ShaderError::compilation(path, CompilerKind::Fxc, err.to_string()))
+    }
+}
+
+impl<T: ShaderType> ShaderInner<T> {
+    pub(crate) fn raw(&self) -> dx::Blob {
+        self.raw.read().clone()
+    }
+
+    pub(crate) fn swap(&self, raw: dx::Blob) {
+        *self.raw.write() = raw;
+    }
+}
+
+impl<T: ShaderType> Shader<T> {
+    pub fn bindings(&self) -> Result<ShaderBindings, ShaderError> {
+        let reflection = self.reflect()?;
+        let desc = reflection.get_desc().map_err(ShaderError::reflection)?;
+
+        let bound = (0..desc.bound_resources())
+            .map(|i| reflection.get_resource_binding_desc(i))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ShaderError::reflection)?;
+
+        let resources = bound
+            .into_iter()
+            .map(|res| {
+                let kind = match res.input_type() {
+                    dx::ShaderInputType::CBuffer => ResourceKind::ConstantBuffer,
+                    dx::ShaderInputType::Sampler => ResourceKind::Sampler,
+                    dx::ShaderInputType::TBuffer
+                    | dx::ShaderInputType::Texture
+                    | dx::ShaderInputType::Structured
+                    | dx::ShaderInputType::ByteAddress => ResourceKind::ShaderResource,
+                    _ => ResourceKind::UnorderedAccess,
+                };
+
+                let count = match res.bind_count() {
+                    0 => UNBOUNDED,
+                    count => count,
+                };
+
+                let resource = BoundResource::new(
+                    res.name().to_string_lossy(),
+                    kind,
+                    res.bind_point(),
+                    res.space(),
+                )
+                .with_count(count);
+
+                if res
+                    .flags()
+                    .contains(dx::ShaderInputFlags::ComparisonSampler)
+                {
+                    resource.with_comparison()
+                } else {
+                    resource
+                }
+            })
+            .collect();
+
+        let cbuffers = (0..desc.constant_buffers())
+            .map(|i| {
+                let cbuffer = reflection.get_constant_buffer_by_index(i);
+                let cbuffer_desc = cbuffer.get_desc().map_err(ShaderError::reflection)?;
+
+                let variables = (0..cbuffer_desc.variables())
+                    .map(|j| cbuffer.get_variable_by_index(j).get_desc())
+                    .map(|var| {
+                        var.map(|var| CbufferVariable {
+                            name: var.name().to_string_lossy().into_owned(),
+                            offset: var.start_offset(),
+                            size: var.size(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(ShaderError::reflection)?;
+
+                Ok(CbufferLayout {
+                    name: cbuffer_desc.name().to_string_lossy().into_owned(),
+                    size: cbuffer_desc.size(),
+                    variables,
+                })
+            })
+            .collect::<Result<Vec<_>, ShaderError>>()?;
+
+        Ok(ShaderBindings {
+            visibility: T::VISIBILITY,
+            resources,
+            cbuffers,
+        })
+    }
+}
+
+impl Shader<Vertex> {
+    pub fn input_signature(&self) -> Result<Vec<ShaderInput>, ShaderError> {
+        let reflection = self.reflect()?;
+        let desc = reflection.get_desc().map_err(ShaderError::reflection)?;
+
+        let params = (0..desc.input_parameters())
+            .map(|i| reflection.get_input_parameter_desc(i))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ShaderError::reflection)?;
+
+        let inputs = params
+            .into_iter()
+            .filter(|param| param.system_value_type() == dx::Name::Undefined)
+            .filter_map(|param| {
+                let component_type = match param.component_type() {
+                    dx::RegisterComponentType::Float32 => ComponentType::Float,
+                    dx::RegisterComponentType::Uint32 => ComponentType::Uint,
+                    dx::RegisterComponentType::Sint32 => ComponentType::Sint,
+                    _ => return None,
+                };
+
+                Some(ShaderInput {
+                    semantic: param.semantic_name().to_string_lossy().into_owned(),
+                    index: param.semantic_index(),
+                    component_type,
+                })
+            })
+            .collect();
+
+        Ok(inputs)
     }
 }
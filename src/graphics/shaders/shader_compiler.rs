@@ -0,0 +1,690 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use crate::graphics::ShaderModel;
+
+use super::ShaderType;
+
+const DXC_PATH_VAR: &str = "DXC_PATH";
+
+static OUTPUT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+static DXC_VERSIONS: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderConfig {
+    Debug,
+    Release,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompilerKind {
+    Fxc,
+    Dxc,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderOptions {
+    pub model: ShaderModel,
+    pub config: ShaderConfig,
+    pub defines: Vec<(String, String)>,
+    pub include_dirs: Vec<PathBuf>,
+    pub compiler: Option<PathBuf>,
+}
+
+impl Default for ShaderOptions {
+    fn default() -> Self {
+        Self {
+            model: ShaderModel::new(5, 1),
+            config: if cfg!(debug_assertions) {
+                ShaderConfig::Debug
+            } else {
+                ShaderConfig::Release
+            },
+            defines: vec![],
+            include_dirs: vec![],
+            compiler: None,
+        }
+    }
+}
+
+impl ShaderOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn legacy() -> Self {
+        Self::new()
+            .with_model(ShaderModel::new(5, 1))
+            .with_config(ShaderConfig::Debug)
+    }
+
+    pub fn with_model(mut self, model: ShaderModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    pub fn with_config(mut self, config: ShaderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defines.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_defines(mut self, defines: &[(&str, &str)]) -> Self {
+        self.defines.extend(
+            defines
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+        self
+    }
+
+    pub fn with_include_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    pub fn with_compiler(mut self, compiler: impl Into<PathBuf>) -> Self {
+        self.compiler = Some(compiler.into());
+        self
+    }
+
+    pub fn uses_dxc(&self) -> bool {
+        self.compiler_kind() == CompilerKind::Dxc
+    }
+
+    pub fn compiler_kind(&self) -> CompilerKind {
+        if self.model.major >= 6 {
+            CompilerKind::Dxc
+        } else {
+            CompilerKind::Fxc
+        }
+    }
+
+    pub fn target<T: ShaderType>(&self) -> String {
+        format!("{}_{}_{}", T::STAGE, self.model.major, self.model.minor)
+    }
+
+    pub(crate) fn compiler_path(&self) -> PathBuf {
+        self.compiler
+            .clone()
+            .or_else(|| std::env::var_os(DXC_PATH_VAR).map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("dxc"))
+    }
+
+    pub(crate) fn compiler_identity(&self) -> String {
+        match self.compiler_kind() {
+            CompilerKind::Fxc => "d3dcompiler_47".to_string(),
+            CompilerKind::Dxc => {
+                let compiler = self.compiler_path();
+                let version = DXC_VERSIONS
+                    .get_or_init(Default::default)
+                    .lock()
+                    .unwrap()
+                    .entry(compiler.clone())
+                    .or_insert_with(|| dxc_version(&compiler))
+                    .clone();
+
+                format!("{}:{}", compiler.display(), version)
+            }
+        }
+    }
+
+    pub(crate) fn dxc_args(
+        &self,
+        path: &Path,
+        entry_point: &str,
+        target: &str,
+        output: &Path,
+    ) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            "-T".into(),
+            target.into(),
+            "-E".into(),
+            entry_point.into(),
+            "-Fo".into(),
+            output.into(),
+        ];
+
+        match self.config {
+            ShaderConfig::Debug => {
+                args.extend(["-Zi".into(), "-Qembed_debug".into(), "-Od".into()]);
+            }
+            ShaderConfig::Release => args.push("-O3".into()),
+        }
+
+        for (name, value) in &self.defines {
+            args.push("-D".into());
+            args.push(format!("{}={}", name, value).into());
+        }
+
+        for dir in &self.include_dirs {
+            args.push("-I".into());
+            args.push(dir.into());
+        }
+
+        args.push(path.into());
+        args
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderDiagnostic {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.file, self.line, self.column, severity, self.message
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    CompilerNotFound(PathBuf),
+    Compilation {
+        path: PathBuf,
+        diagnostics: Vec<ShaderDiagnostic>,
+        output: String,
+    },
+    Reflection(String),
+    IncludeDirsUnsupported(PathBuf),
+}
+
+impl ShaderError {
+    pub(crate) fn compilation(path: &Path, compiler: CompilerKind, output: String) -> Self {
+        ShaderError::Compilation {
+            path: path.to_path_buf(),
+            diagnostics: parse_diagnostics(&output, compiler),
+            output,
+        }
+    }
+
+    pub(crate) fn reflection(err: impl fmt::Display) -> Self {
+        ShaderError::Reflection(err.to_string())
+    }
+
+    pub fn diagnostics(&self) -> &[ShaderDiagnostic] {
+        match self {
+            ShaderError::Compilation { diagnostics, .. } => diagnostics,
+            _ => &[],
+        }
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ShaderDiagnostic> {
+        self.diagnostics()
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io { path, error } => {
+                write!(f, "failed to access {}: {}", path.display(), error)
+            }
+            ShaderError::CompilerNotFound(path) => write!(
+                f,
+                "shader compiler {} not found, set {} or add it to PATH",
+                path.display(),
+                DXC_PATH_VAR
+            ),
+            ShaderError::Compilation {
+                path,
+                diagnostics,
+                output,
+            } => {
+                writeln!(f, "failed to compile {}", path.display())?;
+
+                if diagnostics.is_empty() {
+                    write!(f, "{}", output.trim_end())
+                } else {
+                    for diagnostic in diagnostics {
+                        writeln!(f, "{}", diagnostic)?;
+                    }
+
+                    Ok(())
+                }
+            }
+            ShaderError::Reflection(err) => write!(f, "failed to reflect shader: {}", err),
+            ShaderError::IncludeDirsUnsupported(path) => write!(
+                f,
+                "{} sets include directories, which FXC does not support, use a 6.x shader model",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShaderError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+fn parse_severity(severity: &str) -> Option<Severity> {
+    let severity = severity.trim();
+
+    if severity.starts_with("error") || severity.starts_with("fatal error") {
+        Some(Severity::Error)
+    } else if severity.starts_with("warning") {
+        Some(Severity::Warning)
+    } else if severity.starts_with("note") {
+        Some(Severity::Note)
+    } else {
+        None
+    }
+}
+
+fn parse_dxc_line(line: &str) -> Option<ShaderDiagnostic> {
+    let (location, severity, message) = [
+        (": fatal error: ", Severity::Error),
+        (": error: ", Severity::Error),
+        (": warning: ", Severity::Warning),
+        (": note: ", Severity::Note),
+    ]
+    .into_iter()
+    .find_map(|(marker, severity)| {
+        let (location, message) = line.split_once(marker)?;
+        Some((location, severity, message))
+    })?;
+
+    let mut location = location.rsplitn(3, ':');
+    let column = location.next()?.parse().ok()?;
+    let line_number = location.next()?.parse().ok()?;
+    let file = location.next()?;
+
+    Some(ShaderDiagnostic {
+        file: file.to_string(),
+        line: line_number,
+        column,
+        severity,
+        message: message.to_string(),
+    })
+}
+
+fn parse_fxc_line(line: &str) -> Option<ShaderDiagnostic> {
+    let (file, rest) = line.split_once('(')?;
+    let (position, rest) = rest.split_once("): ")?;
+    let (severity, message) = rest.split_once(": ")?;
+    let severity = parse_severity(severity)?;
+
+    let mut position = position.split(',');
+    let line_number = position.next()?.parse().ok()?;
+    let column = position
+        .next()
+        .and_then(|c| c.split('-').next())
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0);
+
+    Some(ShaderDiagnostic {
+        file: file.to_string(),
+        line: line_number,
+        column,
+        severity,
+        message: message.to_string(),
+    })
+}
+
+pub fn parse_diagnostics(output: &str, compiler: CompilerKind) -> Vec<ShaderDiagnostic> {
+    let parse = match compiler {
+        CompilerKind::Fxc => parse_fxc_line,
+        CompilerKind::Dxc => parse_dxc_line,
+    };
+
+    output.lines().filter_map(parse).collect()
+}
+
+pub(crate) fn is_dxil_container(bytecode: &[u8]) -> bool {
+    let read_u32 = |offset: usize| {
+        bytecode
+            .get(offset..offset + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u32::from_le_bytes)
+    };
+
+    if bytecode.get(0..4) != Some(b"DXBC".as_slice()) {
+        return false;
+    }
+
+    let Some(parts) = read_u32(28) else {
+        return false;
+    };
+
+    (0..parts as usize)
+        .filter_map(|i| read_u32(32 + i * 4))
+        .any(|offset| {
+            let offset = offset as usize;
+            bytecode.get(offset..offset + 4) == Some(b"DXIL".as_slice())
+        })
+}
+
+fn dxc_version(compiler: &Path) -> String {
+    match Command::new(compiler).arg("--version").output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+pub(crate) fn compile_dxc(
+    path: &Path,
+    entry_point: &str,
+    target: &str,
+    options: &ShaderOptions,
+) -> Result<Vec<u8>, ShaderError> {
+    if let Err(error) = std::fs::metadata(path) {
+        return Err(ShaderError::Io {
+            path: path.to_path_buf(),
+            error,
+        });
+    }
+
+    let output_path = std::env::temp_dir().join(format!(
+        "shader-{}-{}.dxil",
+        std::process::id(),
+        OUTPUT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let compiler = options.compiler_path();
+    let result = Command::new(&compiler)
+        .args(options.dxc_args(path, entry_point, target, &output_path))
+        .output();
+
+    let result = match result {
+        Ok(result) => result,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Err(ShaderError::CompilerNotFound(compiler))
+        }
+        Err(error) => {
+            return Err(ShaderError::Io {
+                path: compiler,
+                error,
+            })
+        }
+    };
+
+    if !result.status.success() {
+        let _ = std::fs::remove_file(&output_path);
+
+        return Err(ShaderError::compilation(
+            path,
+            CompilerKind::Dxc,
+            String::from_utf8_lossy(&result.stderr).into_owned(),
+        ));
+    }
+
+    let bytecode = std::fs::read(&output_path).map_err(|error| ShaderError::Io {
+        path: output_path.clone(),
+        error,
+    });
+    let _ = std::fs::remove_file(&output_path);
+
+    bytecode
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::graphics::{Compute, ShaderModel, Vertex};
+
+    use super::{
+        compile_dxc, is_dxil_container, parse_diagnostics, CompilerKind, Severity, ShaderConfig,
+        ShaderDiagnostic, ShaderError, ShaderOptions,
+    };
+
+    fn temp_source(name: &str, source: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "shader-compiler-{}-{}-{:?}.hlsl",
+            name,
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    fn container(parts: &[&[u8; 4]]) -> Vec<u8> {
+        let header = 32 + parts.len() * 4;
+
+        let mut bytes = b"DXBC".to_vec();
+        bytes.extend([0; 16]);
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(((header + parts.len() * 8) as u32).to_le_bytes());
+        bytes.extend((parts.len() as u32).to_le_bytes());
+
+        for i in 0..parts.len() {
+            bytes.extend(((header + i * 8) as u32).to_le_bytes());
+        }
+
+        for part in parts {
+            bytes.extend(part.as_slice());
+            bytes.extend(0u32.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn test_targets_follow_shader_model() {
+        let options = ShaderOptions::new().with_model(ShaderModel::new(6, 6));
+
+        assert_eq!(options.target::<Vertex>(), "vs_6_6");
+        assert_eq!(options.target::<Compute>(), "cs_6_6");
+        assert!(options.uses_dxc());
+
+        assert_eq!(ShaderOptions::legacy().target::<Vertex>(), "vs_5_1");
+        assert!(!ShaderOptions::legacy().uses_dxc());
+        assert!(!ShaderOptions::new().uses_dxc());
+    }
+
+    #[test]
+    fn test_dxc_args() {
+        let args = ShaderOptions::new()
+            .with_config(ShaderConfig::Release)
+            .with_define("SHADOW", "1")
+            .with_include_dir("shaders/include")
+            .dxc_args(
+                Path::new("shaders/mesh.hlsl"),
+                "main",
+                "ps_6_0",
+                Path::new("out.dxil"),
+            );
+
+        let args = args
+            .iter()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            args,
+            vec![
+                "-T",
+                "ps_6_0",
+                "-E",
+                "main",
+                "-Fo",
+                "out.dxil",
+                "-O3",
+                "-D",
+                "SHADOW=1",
+                "-I",
+                "shaders/include",
+                "shaders/mesh.hlsl"
+            ]
+        );
+
+        let debug = ShaderOptions::new()
+            .with_config(ShaderConfig::Debug)
+            .dxc_args(Path::new("a.hlsl"), "main", "vs_6_0", Path::new("a.dxil"));
+
+        assert!(debug.iter().any(|a| a == "-Zi"));
+        assert!(debug.iter().any(|a| a == "-Od"));
+    }
+
+    #[test]
+    fn test_parses_dxc_diagnostics() {
+        let output = "\
+shaders/mesh.hlsl:12:5: error: use of undeclared identifier 'albedo'
+    return albedo;
+           ^
+shaders/mesh.hlsl:3:10: warning: implicit truncation: vector type
+C:\\src\\shaders\\common.hlsli:7:1: note: expanded from macro 'SAMPLE'
+";
+
+        assert_eq!(
+            parse_diagnostics(output, CompilerKind::Dxc),
+            vec![
+                ShaderDiagnostic {
+                    file: "shaders/mesh.hlsl".to_string(),
+                    line: 12,
+                    column: 5,
+                    severity: Severity::Error,
+                    message: "use of undeclared identifier 'albedo'".to_string(),
+                },
+                ShaderDiagnostic {
+                    file: "shaders/mesh.hlsl".to_string(),
+                    line: 3,
+                    column: 10,
+                    severity: Severity::Warning,
+                    message: "implicit truncation: vector type".to_string(),
+                },
+                ShaderDiagnostic {
+                    file: "C:\\src\\shaders\\common.hlsli".to_string(),
+                    line: 7,
+                    column: 1,
+                    severity: Severity::Note,
+                    message: "expanded from macro 'SAMPLE'".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parses_fxc_diagnostics() {
+        let output =
+            "C:\\src\\shaders\\mesh.hlsl(12,5-10): error X3004: undeclared identifier 'albedo'\n";
+
+        assert_eq!(
+            parse_diagnostics(output, CompilerKind::Fxc),
+            vec![ShaderDiagnostic {
+                file: "C:\\src\\shaders\\mesh.hlsl".to_string(),
+                line: 12,
+                column: 5,
+                severity: Severity::Error,
+                message: "undeclared identifier 'albedo'".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_dxc_lines_with_parentheses_are_not_parsed_as_fxc() {
+        let output = "shaders/mesh.hlsl:4:12: warning: SAMPLE(2): note: macro was redefined\n";
+
+        assert_eq!(
+            parse_diagnostics(output, CompilerKind::Dxc),
+            vec![ShaderDiagnostic {
+                file: "shaders/mesh.hlsl".to_string(),
+                line: 4,
+                column: 12,
+                severity: Severity::Warning,
+                message: "SAMPLE(2): note: macro was redefined".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_missing_compiler_is_reported() {
+        let source = temp_source("missing", "float4 main() : SV_Target { return 0; }");
+
+        let result = compile_dxc(
+            &source,
+            "main",
+            "ps_6_0",
+            &ShaderOptions::new().with_compiler("/nonexistent/dxc"),
+        );
+
+        let _ = std::fs::remove_file(&source);
+        assert!(matches!(result, Err(ShaderError::CompilerNotFound(_))));
+
+        let result = compile_dxc(
+            Path::new("/nonexistent/shader.hlsl"),
+            "main",
+            "ps_6_0",
+            &ShaderOptions::new(),
+        );
+
+        assert!(matches!(result, Err(ShaderError::Io { .. })));
+    }
+
+    #[test]
+    fn test_detects_dxil_containers() {
+        assert!(is_dxil_container(&container(&[b"SFI0", b"ISG1", b"DXIL"])));
+        assert!(!is_dxil_container(&container(&[b"ISGN", b"OSGN", b"SHEX"])));
+        assert!(!is_dxil_container(&container(&[])));
+        assert!(!is_dxil_container(b"DXBC"));
+        assert!(!is_dxil_container(&[]));
+    }
+
+    #[test]
+    fn test_dxc_output_is_dxil_container() {
+        let source = temp_source("dxil", "float4 main() : SV_Target { return 1; }");
+
+        let result = compile_dxc(
+            &source,
+            "main",
+            "ps_6_0",
+            &ShaderOptions::new().with_model(ShaderModel::new(6, 0)),
+        );
+        let _ = std::fs::remove_file(&source);
+
+        match result {
+            Ok(bytecode) => assert!(is_dxil_container(&bytecode)),
+            Err(ShaderError::CompilerNotFound(_)) => {}
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
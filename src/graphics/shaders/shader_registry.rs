@@ -0,0 +1,407 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use oxidx::dx;
+use smallvec::SmallVec;
+
+use crate::graphics::{
+    Compute, ComputePipelineDesc, Device, DiskCache, Graphics, GraphicsPipelineDesc, Pipeline,
+    PipelineError,
+};
+
+use super::{
+    Domain, Geometry, Hull, Pixel, Shader, ShaderError, ShaderOptions, ShaderType, SourceWatcher,
+    Vertex,
+};
+
+#[derive(Clone, Debug)]
+pub enum AnyShader {
+    Vertex(Shader<Vertex>),
+    Pixel(Shader<Pixel>),
+    Geometry(Shader<Geometry>),
+    Hull(Shader<Hull>),
+    Domain(Shader<Domain>),
+    Compute(Shader<Compute>),
+}
+
+impl AnyShader {
+    fn recompile(
+        &self,
+        path: &Path,
+        entry_point: &str,
+        options: &ShaderOptions,
+        cache: Option<&DiskCache>,
+    ) -> Result<AnyShader, ShaderError> {
+        Ok(match self {
+            AnyShader::Vertex(_) => {
+                AnyShader::Vertex(Shader::compile(path, entry_point, options, cache)?)
+            }
+            AnyShader::Pixel(_) => {
+                AnyShader::Pixel(Shader::compile(path, entry_point, options, cache)?)
+            }
+            AnyShader::Geometry(_) => {
+                AnyShader::Geometry(Shader::compile(path, entry_point, options, cache)?)
+            }
+            AnyShader::Hull(_) => {
+                AnyShader::Hull(Shader::compile(path, entry_point, options, cache)?)
+            }
+            AnyShader::Domain(_) => {
+                AnyShader::Domain(Shader::compile(path, entry_point, options, cache)?)
+            }
+            AnyShader::Compute(_) => {
+                AnyShader::Compute(Shader::compile(path, entry_point, options, cache)?)
+            }
+        })
+    }
+
+    fn is_unique(&self) -> bool {
+        match self {
+            AnyShader::Vertex(s) => s.is_unique(),
+            AnyShader::Pixel(s) => s.is_unique(),
+            AnyShader::Geometry(s) => s.is_unique(),
+            AnyShader::Hull(s) => s.is_unique(),
+            AnyShader::Domain(s) => s.is_unique(),
+            AnyShader::Compute(s) => s.is_unique(),
+        }
+    }
+
+    fn swap(&self, other: &AnyShader) {
+        match (self, other) {
+            (AnyShader::Vertex(s), AnyShader::Vertex(o)) => s.swap(o.raw()),
+            (AnyShader::Pixel(s), AnyShader::Pixel(o)) => s.swap(o.raw()),
+            (AnyShader::Geometry(s), AnyShader::Geometry(o)) => s.swap(o.raw()),
+            (AnyShader::Hull(s), AnyShader::Hull(o)) => s.swap(o.raw()),
+            (AnyShader::Domain(s), AnyShader::Domain(o)) => s.swap(o.raw()),
+            (AnyShader::Compute(s), AnyShader::Compute(o)) => s.swap(o.raw()),
+            _ => {}
+        }
+    }
+
+    fn is_used_by_graphics(&self, desc: &GraphicsPipelineDesc) -> bool {
+        match self {
+            AnyShader::Vertex(s) => desc.vs.ptr_eq(s),
+            AnyShader::Pixel(s) => desc.ps.as_ref().is_some_and(|ps| ps.ptr_eq(s)),
+            AnyShader::Geometry(s) => desc.gs.as_ref().is_some_and(|gs| gs.ptr_eq(s)),
+            AnyShader::Hull(s) => desc.hs.as_ref().is_some_and(|hs| hs.ptr_eq(s)),
+            AnyShader::Domain(s) => desc.ds.as_ref().is_some_and(|ds| ds.ptr_eq(s)),
+            AnyShader::Compute(_) => false,
+        }
+    }
+
+    fn is_used_by_compute(&self, desc: &ComputePipelineDesc) -> bool {
+        match self {
+            AnyShader::Compute(s) => desc.cs.ptr_eq(s),
+            _ => false,
+        }
+    }
+
+    fn bind_graphics(&self, desc: &mut GraphicsPipelineDesc) {
+        match self {
+            AnyShader::Vertex(s) => desc.vs = s.clone(),
+            AnyShader::Pixel(s) => desc.ps = Some(s.clone()),
+            AnyShader::Geometry(s) => desc.gs = Some(s.clone()),
+            AnyShader::Hull(s) => desc.hs = Some(s.clone()),
+            AnyShader::Domain(s) => desc.ds = Some(s.clone()),
+            AnyShader::Compute(_) => {}
+        }
+    }
+
+    fn bind_compute(&self, desc: &mut ComputePipelineDesc) {
+        if let AnyShader::Compute(s) = self {
+            desc.cs = s.clone();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReloadError {
+    Shader(ShaderError),
+    Pipeline(PipelineError),
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReloadError::Shader(err) => write!(f, "shader reload failed: {}", err),
+            ReloadError::Pipeline(err) => write!(f, "pipeline rebuild failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ReloadError {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct ShaderKey {
+    stage: &'static str,
+    path: PathBuf,
+    entry_point: String,
+    options: ShaderOptions,
+}
+
+impl ShaderKey {
+    fn new<T: ShaderType>(path: &Path, entry_point: &str, options: &ShaderOptions) -> Self {
+        Self {
+            stage: T::STAGE,
+            path: path.to_path_buf(),
+            entry_point: entry_point.to_string(),
+            options: options.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PipelineEntry {
+    Graphics {
+        desc: GraphicsPipelineDesc,
+        pipeline: Pipeline<Graphics>,
+        shaders: SmallVec<[ShaderKey; 5]>,
+    },
+    Compute {
+        desc: ComputePipelineDesc,
+        pipeline: Pipeline<Compute>,
+        shaders: SmallVec<[ShaderKey; 5]>,
+    },
+}
+
+impl PipelineEntry {
+    fn is_unique(&self) -> bool {
+        match self {
+            PipelineEntry::Graphics { pipeline, .. } => pipeline.is_unique(),
+            PipelineEntry::Compute { pipeline, .. } => pipeline.is_unique(),
+        }
+    }
+
+    fn shaders(&self) -> &[ShaderKey] {
+        match self {
+            PipelineEntry::Graphics { shaders, .. } => shaders,
+            PipelineEntry::Compute { shaders, .. } => shaders,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PendingSwap {
+    Shader {
+        shader: AnyShader,
+        compiled: AnyShader,
+    },
+    Graphics {
+        pipeline: Pipeline<Graphics>,
+        raw: dx::PipelineState,
+    },
+    Compute {
+        pipeline: Pipeline<Compute>,
+        raw: dx::PipelineState,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct ShaderRegistry {
+    watcher: SourceWatcher,
+    shaders: HashMap<ShaderKey, AnyShader>,
+    pipelines: Vec<PipelineEntry>,
+    pending: Vec<PendingSwap>,
+}
+
+impl ShaderRegistry {
+    pub fn shader_count(&self) -> usize {
+        self.shaders.len()
+    }
+
+    pub fn pipeline_count(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub(crate) fn find_shader<T: ShaderType>(
+        &self,
+        path: &Path,
+        entry_point: &str,
+        options: &ShaderOptions,
+    ) -> Option<Shader<T>> {
+        self.shaders
+            .get(&ShaderKey::new::<T>(path, entry_point, options))
+            .and_then(T::from_any)
+    }
+
+    pub(crate) fn register_shader<T: ShaderType>(
+        &mut self,
+        path: &Path,
+        entry_point: &str,
+        options: &ShaderOptions,
+        shader: Shader<T>,
+    ) -> Shader<T> {
+        let key = ShaderKey::new::<T>(path, entry_point, options);
+
+        if let Some(existing) = self.shaders.get(&key).and_then(T::from_any) {
+            return existing;
+        }
+
+        self.watcher.watch(path, &options.include_dirs);
+        self.shaders.insert(key, T::into_any(shader.clone()));
+
+        shader
+    }
+
+    pub(crate) fn register_graphics(
+        &mut self,
+        desc: &GraphicsPipelineDesc,
+        pipeline: &Pipeline<Graphics>,
+    ) {
+        let shaders = self.find_shaders(|s| s.is_used_by_graphics(desc));
+
+        if !shaders.is_empty() {
+            self.pipelines.push(PipelineEntry::Graphics {
+                desc: desc.clone(),
+                pipeline: pipeline.clone(),
+                shaders,
+            });
+        }
+    }
+
+    pub(crate) fn register_compute(
+        &mut self,
+        desc: &ComputePipelineDesc,
+        pipeline: &Pipeline<Compute>,
+    ) {
+        let shaders = self.find_shaders(|s| s.is_used_by_compute(desc));
+
+        if !shaders.is_empty() {
+            self.pipelines.push(PipelineEntry::Compute {
+                desc: desc.clone(),
+                pipeline: pipeline.clone(),
+                shaders,
+            });
+        }
+    }
+
+    fn find_shaders(&self, used: impl Fn(&AnyShader) -> bool) -> SmallVec<[ShaderKey; 5]> {
+        self.shaders
+            .iter()
+            .filter(|(_, shader)| used(shader))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    pub fn poll(&mut self, device: &Device) -> Vec<ReloadError> {
+        self.prune();
+
+        let changed = self.watcher.poll();
+
+        if changed.is_empty() {
+            return vec![];
+        }
+
+        let cache = device.disk_cache();
+        let mut errors = vec![];
+        let mut updated = HashMap::new();
+
+        for (key, shader) in self.shaders.iter() {
+            if !changed.contains(&key.path) {
+                continue;
+            }
+
+            match shader.recompile(&key.path, &key.entry_point, &key.options, cache.as_deref()) {
+                Ok(compiled) => {
+                    updated.insert(key, (shader.clone(), compiled));
+                }
+                Err(err) => errors.push(ReloadError::Shader(err)),
+            }
+        }
+
+        for entry in &self.pipelines {
+            if !entry.shaders().iter().any(|key| updated.contains_key(key)) {
+                continue;
+            }
+
+            let swap = match entry {
+                PipelineEntry::Graphics {
+                    desc,
+                    pipeline,
+                    shaders,
+                } => {
+                    let mut desc = desc.clone();
+
+                    for (_, compiled) in shaders.iter().filter_map(|key| updated.get(key)) {
+                        compiled.bind_graphics(&mut desc);
+                    }
+
+                    Pipeline::build_graphics(device, &desc).map(|raw| PendingSwap::Graphics {
+                        pipeline: pipeline.clone(),
+                        raw,
+                    })
+                }
+                PipelineEntry::Compute {
+                    desc,
+                    pipeline,
+                    shaders,
+                } => {
+                    let mut desc = desc.clone();
+
+                    for (_, compiled) in shaders.iter().filter_map(|key| updated.get(key)) {
+                        compiled.bind_compute(&mut desc);
+                    }
+
+                    Pipeline::build_compute(device, &desc).map(|raw| PendingSwap::Compute {
+                        pipeline: pipeline.clone(),
+                        raw,
+                    })
+                }
+            };
+
+            match swap {
+                Ok(swap) => self.pending.push(swap),
+                Err(err) => errors.push(ReloadError::Pipeline(err)),
+            }
+        }
+
+        self.pending.extend(
+            updated
+                .into_values()
+                .map(|(shader, compiled)| PendingSwap::Shader { shader, compiled }),
+        );
+
+        errors
+    }
+
+    pub fn apply(&mut self, device: &Device) -> usize {
+        let swapped = self.pending.len();
+
+        for swap in self.pending.drain(..) {
+            match swap {
+                PendingSwap::Shader { shader, compiled } => shader.swap(&compiled),
+                PendingSwap::Graphics { pipeline, raw } => device.release(pipeline.swap(raw)),
+                PendingSwap::Compute { pipeline, raw } => device.release(pipeline.swap(raw)),
+            }
+        }
+
+        self.prune();
+
+        swapped
+    }
+
+    fn prune(&mut self) {
+        self.pipelines.retain(|p| !p.is_unique());
+
+        let released = self
+            .shaders
+            .iter()
+            .filter(|(_, shader)| shader.is_unique())
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        for key in released {
+            self.shaders.remove(&key);
+
+            if !self.shaders.keys().any(|k| k.path == key.path) {
+                self.watcher.unwatch(&key.path);
+            }
+        }
+    }
+}
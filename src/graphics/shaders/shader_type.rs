@@ -1,9 +1,20 @@
-use std::ffi::CStr;
+use oxidx::dx;
 
-use crate::graphics::Sealed;
+use crate::graphics::{Compute, Sealed};
+
+use super::{AnyShader, Shader};
 
 pub trait ShaderType: Sealed {
-    const TARGET: &'static CStr;
+    const STAGE: &'static str;
+    const VISIBILITY: dx::ShaderVisibility;
+
+    fn into_any(shader: Shader<Self>) -> AnyShader
+    where
+        Self: Sized;
+
+    fn from_any(shader: &AnyShader) -> Option<Shader<Self>>
+    where
+        Self: Sized;
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -11,7 +22,19 @@ pub struct Vertex;
 
 impl Sealed for Vertex {}
 impl ShaderType for Vertex {
-    const TARGET: &'static CStr = c"vs_5_1";
+    const STAGE: &'static str = "vs";
+    const VISIBILITY: dx::ShaderVisibility = dx::ShaderVisibility::Vertex;
+
+    fn into_any(shader: Shader<Self>) -> AnyShader {
+        AnyShader::Vertex(shader)
+    }
+
+    fn from_any(shader: &AnyShader) -> Option<Shader<Self>> {
+        match shader {
+            AnyShader::Vertex(shader) => Some(shader.clone()),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -19,5 +42,93 @@ pub struct Pixel;
 
 impl Sealed for Pixel {}
 impl ShaderType for Pixel {
-    const TARGET: &'static CStr = c"ps_5_1";
+    const STAGE: &'static str = "ps";
+    const VISIBILITY: dx::ShaderVisibility = dx::ShaderVisibility::Pixel;
+
+    fn into_any(shader: Shader<Self>) -> AnyShader {
+        AnyShader::Pixel(shader)
+    }
+
+    fn from_any(shader: &AnyShader) -> Option<Shader<Self>> {
+        match shader {
+            AnyShader::Pixel(shader) => Some(shader.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Geometry;
+
+impl Sealed for Geometry {}
+impl ShaderType for Geometry {
+    const STAGE: &'static str = "gs";
+    const VISIBILITY: dx::ShaderVisibility = dx::ShaderVisibility::Geometry;
+
+    fn into_any(shader: Shader<Self>) -> AnyShader {
+        AnyShader::Geometry(shader)
+    }
+
+    fn from_any(shader: &AnyShader) -> Option<Shader<Self>> {
+        match shader {
+            AnyShader::Geometry(shader) => Some(shader.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Hull;
+
+impl Sealed for Hull {}
+impl ShaderType for Hull {
+    const STAGE: &'static str = "hs";
+    const VISIBILITY: dx::ShaderVisibility = dx::ShaderVisibility::Hull;
+
+    fn into_any(shader: Shader<Self>) -> AnyShader {
+        AnyShader::Hull(shader)
+    }
+
+    fn from_any(shader: &AnyShader) -> Option<Shader<Self>> {
+        match shader {
+            AnyShader::Hull(shader) => Some(shader.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Domain;
+
+impl Sealed for Domain {}
+impl ShaderType for Domain {
+    const STAGE: &'static str = "ds";
+    const VISIBILITY: dx::ShaderVisibility = dx::ShaderVisibility::Domain;
+
+    fn into_any(shader: Shader<Self>) -> AnyShader {
+        AnyShader::Domain(shader)
+    }
+
+    fn from_any(shader: &AnyShader) -> Option<Shader<Self>> {
+        match shader {
+            AnyShader::Domain(shader) => Some(shader.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl ShaderType for Compute {
+    const STAGE: &'static str = "cs";
+    const VISIBILITY: dx::ShaderVisibility = dx::ShaderVisibility::All;
+
+    fn into_any(shader: Shader<Self>) -> AnyShader {
+        AnyShader::Compute(shader)
+    }
+
+    fn from_any(shader: &AnyShader) -> Option<Shader<Self>> {
+        match shader {
+            AnyShader::Compute(shader) => Some(shader.clone()),
+            _ => None,
+        }
+    }
 }
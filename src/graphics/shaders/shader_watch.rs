@@ -0,0 +1,292 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+pub fn parse_includes(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_start().strip_prefix('#')?;
+            let line = line.trim_start().strip_prefix("include")?.trim();
+
+            let (open, close) = match line.chars().next()? {
+                '"' => ('"', '"'),
+                '<' => ('<', '>'),
+                _ => return None,
+            };
+
+            let name = line.strip_prefix(open)?;
+            let end = name.find(close)?;
+
+            Some(name[..end].to_string())
+        })
+        .collect()
+}
+
+fn resolve_include(from: &Path, name: &str, include_dirs: &[PathBuf]) -> Option<PathBuf> {
+    from.parent()
+        .into_iter()
+        .chain(include_dirs.iter().map(|d| d.as_path()))
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+pub fn scan_dependencies(root: &Path, include_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = vec![root.to_path_buf()];
+    let mut visited = HashSet::from([root.to_path_buf()]);
+    let mut next = 0;
+
+    while next < files.len() {
+        let file = files[next].clone();
+        next += 1;
+
+        let Ok(source) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+
+        for name in parse_includes(&source) {
+            if let Some(include) = resolve_include(&file, &name, include_dirs) {
+                if visited.insert(include.clone()) {
+                    files.push(include);
+                }
+            }
+        }
+    }
+
+    files
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[derive(Clone, Debug)]
+struct WatchedRoot {
+    include_dirs: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SourceWatcher {
+    roots: HashMap<PathBuf, WatchedRoot>,
+    mtimes: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl SourceWatcher {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn is_watched(&self, root: impl AsRef<Path>) -> bool {
+        self.roots.contains_key(root.as_ref())
+    }
+
+    pub fn dependencies(&self, root: impl AsRef<Path>) -> &[PathBuf] {
+        self.roots
+            .get(root.as_ref())
+            .map_or(&[], |r| r.files.as_slice())
+    }
+
+    pub fn watch(&mut self, root: impl AsRef<Path>, include_dirs: &[PathBuf]) {
+        let root = root.as_ref();
+
+        if self.is_watched(root) {
+            return;
+        }
+
+        let files = scan_dependencies(root, include_dirs);
+        self.track(&files);
+
+        self.roots.insert(
+            root.to_path_buf(),
+            WatchedRoot {
+                include_dirs: include_dirs.to_vec(),
+                files,
+            },
+        );
+    }
+
+    pub fn unwatch(&mut self, root: impl AsRef<Path>) {
+        if self.roots.remove(root.as_ref()).is_some() {
+            let files = self
+                .roots
+                .values()
+                .flat_map(|r| r.files.iter())
+                .collect::<HashSet<_>>();
+
+            self.mtimes.retain(|path, _| files.contains(path));
+        }
+    }
+
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let changed = self
+            .mtimes
+            .iter_mut()
+            .filter_map(|(path, mtime)| {
+                let current = modified(path);
+                (current != *mtime).then(|| {
+                    *mtime = current;
+                    path.clone()
+                })
+            })
+            .collect::<HashSet<_>>();
+
+        if changed.is_empty() {
+            return vec![];
+        }
+
+        let mut roots = self
+            .roots
+            .iter()
+            .filter(|(_, r)| r.files.iter().any(|f| changed.contains(f)))
+            .map(|(root, _)| root.clone())
+            .collect::<Vec<_>>();
+        roots.sort();
+
+        for root in &roots {
+            let watched = self.roots.get_mut(root).unwrap();
+            watched.files = scan_dependencies(root, &watched.include_dirs);
+
+            let files = watched.files.clone();
+            self.track(&files);
+        }
+
+        roots
+    }
+
+    fn track(&mut self, files: &[PathBuf]) {
+        for file in files {
+            self.mtimes
+                .entry(file.clone())
+                .or_insert_with(|| modified(file));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime},
+    };
+
+    use super::{parse_includes, scan_dependencies, SourceWatcher};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("shader-watch-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("include")).unwrap();
+        dir
+    }
+
+    fn touch(path: &Path, secs: u64) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    fn write(path: &Path, source: &str) {
+        std::fs::write(path, source).unwrap();
+        touch(path, 1_000);
+    }
+
+    #[test]
+    fn test_parse_includes() {
+        let source = r#"
+            #include "common.hlsli"
+            # include <shadow/pcf.hlsli>
+            #define INCLUDE "not_an_include.hlsli"
+            // float4 main() { #include "commented.hlsli" }
+            #include"tight.hlsli"
+        "#;
+
+        assert_eq!(
+            parse_includes(source),
+            vec!["common.hlsli", "shadow/pcf.hlsli", "tight.hlsli"]
+        );
+    }
+
+    #[test]
+    fn test_scans_nested_includes() {
+        let dir = temp_dir("scan");
+        let root = dir.join("shadow.hlsl");
+
+        write(&root, "#include \"common.hlsli\"\n#include <pcf.hlsli>\n");
+        write(&dir.join("common.hlsli"), "#include \"pcf.hlsli\"\n");
+        write(
+            &dir.join("include/pcf.hlsli"),
+            "#include \"common.hlsli\"\n",
+        );
+
+        let files = scan_dependencies(&root, &[dir.join("include")]);
+
+        assert_eq!(
+            files,
+            vec![
+                root.clone(),
+                dir.join("common.hlsli"),
+                dir.join("include/pcf.hlsli")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_poll_reports_roots_of_changed_includes() {
+        let dir = temp_dir("poll");
+        let shadow = dir.join("shadow.hlsl");
+        let mesh = dir.join("mesh.hlsl");
+        let pcf = dir.join("include/pcf.hlsli");
+
+        write(&shadow, "#include \"pcf.hlsli\"\n");
+        write(&mesh, "float4 main() : SV_Target { return 0; }\n");
+        write(&pcf, "float pcf() { return 1; }\n");
+
+        let mut watcher = SourceWatcher::new();
+        watcher.watch(&shadow, &[dir.join("include")]);
+        watcher.watch(&mesh, &[]);
+
+        assert!(watcher.poll().is_empty());
+
+        touch(&pcf, 2_000);
+        assert_eq!(watcher.poll(), vec![shadow.clone()]);
+        assert!(watcher.poll().is_empty());
+
+        touch(&mesh, 2_000);
+        touch(&shadow, 2_000);
+        assert_eq!(watcher.poll(), vec![mesh.clone(), shadow.clone()]);
+    }
+
+    #[test]
+    fn test_poll_picks_up_new_includes() {
+        let dir = temp_dir("rescan");
+        let root = dir.join("shadow.hlsl");
+        let filter = dir.join("filter.hlsli");
+
+        write(&root, "float4 main() : SV_Target { return 0; }\n");
+        write(&filter, "float filter() { return 1; }\n");
+
+        let mut watcher = SourceWatcher::new();
+        watcher.watch(&root, &[]);
+        assert_eq!(watcher.dependencies(&root), std::slice::from_ref(&root));
+
+        std::fs::write(&root, "#include \"filter.hlsli\"\n").unwrap();
+        touch(&root, 2_000);
+
+        assert_eq!(watcher.poll(), vec![root.clone()]);
+        assert_eq!(watcher.dependencies(&root), &[root.clone(), filter.clone()]);
+
+        touch(&filter, 3_000);
+        assert_eq!(watcher.poll(), vec![root.clone()]);
+
+        watcher.unwatch(&root);
+        touch(&filter, 4_000);
+        assert!(watcher.poll().is_empty());
+    }
+}
@@ -5,6 +5,7 @@ use oxidx::dx::{self, IFactory4, ISwapchain1, ISwapchain3, OUTPUT_NONE};
 use super::{
     commands::{CommandQueue, Direct},
     device::Device,
+    fence_wait::FenceValue,
     resources::{Image, ImageDesc},
     types::{PresentMode, SwapchainDesc, TextureUsage},
     views::{DsvView, GpuView, RtvView, SrvView, ViewAllocator},
@@ -45,7 +46,7 @@ impl Swapchain {
                     rtv: access.push_rtv(&raw, None),
                     raw: Some(raw),
                     srv: Default::default(),
-                    last_access: 0,
+                    last_access: queue.fence.completed(),
                 }
             })
             .collect();
@@ -77,7 +78,8 @@ impl Swapchain {
 impl Swapchain {
     pub fn get_rtv(&self) -> GpuView<RtvView> {
         self.queue
-            .wait_on_cpu(self.images[self.current_back_buffer].last_access);
+            .wait_on_cpu(self.images[self.current_back_buffer].last_access)
+            .unwrap();
 
         self.images[self.current_back_buffer].rtv
     }
@@ -111,7 +113,7 @@ impl Swapchain {
         };
 
         self.raw.present(interval, flags).unwrap();
-        self.images[self.current_back_buffer].last_access = self.queue.fence.get_current_value();
+        self.images[self.current_back_buffer].last_access = self.queue.last_value();
         self.current_back_buffer = self.raw.get_current_back_buffer_index() as usize;
     }
 
@@ -163,7 +165,7 @@ pub struct SwapchainImage {
     raw: Option<dx::Resource>,
     rtv: GpuView<RtvView>,
     srv: Cell<Option<GpuView<SrvView>>>,
-    last_access: u64,
+    last_access: FenceValue,
 }
 
 impl SwapchainImage {
@@ -1,8 +1,11 @@
 use atomig::Atom;
-use oxidx::dx;
+use oxidx::dx::{self, IBlobExt};
 use smallvec::SmallVec;
 
-use super::{PipelineLayout, Pixel, Shader, Vertex};
+use super::{
+    CacheKey, CacheKeyHasher, Compute, Domain, Geometry, Hull, PipelineLayout, PipelineState,
+    PipelineStateError, Pixel, Shader, ShaderBindings, ShaderError, Vertex,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MemoryHeapType {
@@ -168,21 +171,61 @@ impl Atom for ResourceStates {
     }
 }
 
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+    pub struct DescriptorFlags: u32 {
+        const DescriptorsVolatile = dx::DescriptorRangeFlags::DescriptorsVolatile.bits();
+        const DataVolatile = dx::DescriptorRangeFlags::DataVolatile.bits();
+        const DataStaticWhileSetAtExecute = dx::DescriptorRangeFlags::DataStaticWhileSetAtExecute.bits();
+        const DataStatic = dx::DescriptorRangeFlags::DataStatic.bits();
+    }
+}
+
+impl DescriptorFlags {
+    pub const DATA: Self = Self::DataVolatile
+        .union(Self::DataStaticWhileSetAtExecute)
+        .union(Self::DataStatic);
+
+    pub(crate) fn as_range_raw(&self, sampler: bool) -> dx::DescriptorRangeFlags {
+        let flags = if !self.is_empty() {
+            *self
+        } else if sampler {
+            Self::DescriptorsVolatile
+        } else {
+            Self::DescriptorsVolatile | Self::DataVolatile
+        };
+
+        dx::DescriptorRangeFlags::from_bits(flags.bits()).unwrap()
+    }
+
+    pub(crate) fn as_root_raw(&self) -> dx::RootDescriptorFlags {
+        let flags = match self.intersection(Self::DATA) {
+            flags if flags.is_empty() => Self::DataVolatile,
+            flags => flags,
+        };
+
+        dx::RootDescriptorFlags::from_bits(flags.bits()).unwrap()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum BindingType<'a> {
     Cbv {
         slot: u32,
         space: u32,
+        flags: DescriptorFlags,
         visibility: dx::ShaderVisibility,
     },
     Srv {
         slot: u32,
         space: u32,
+        flags: DescriptorFlags,
         visibility: dx::ShaderVisibility,
     },
     Uav {
         slot: u32,
         space: u32,
+        flags: DescriptorFlags,
         visibility: dx::ShaderVisibility,
     },
     PushConstant {
@@ -204,16 +247,19 @@ impl<'a> BindingType<'a> {
                 slot,
                 space,
                 visibility,
+                ..
             } => dx::RootParameter::cbv(*slot, *space).with_visibility(*visibility),
             BindingType::Srv {
                 slot,
                 space,
                 visibility,
+                ..
             } => dx::RootParameter::srv(*slot, *space).with_visibility(*visibility),
             BindingType::Uav {
                 slot,
                 space,
                 visibility,
+                ..
             } => dx::RootParameter::uav(*slot, *space).with_visibility(*visibility),
             BindingType::PushConstant {
                 slot,
@@ -228,47 +274,141 @@ impl<'a> BindingType<'a> {
         }
     }
 
+    pub(crate) fn as_raw1<'b>(&self, ranges: &'b [dx::DescriptorRange1]) -> dx::RootParameter1<'b> {
+        match self {
+            BindingType::Cbv {
+                slot,
+                space,
+                flags,
+                visibility,
+            } => dx::RootParameter1::cbv(*slot, *space)
+                .with_flags(flags.as_root_raw())
+                .with_visibility(*visibility),
+            BindingType::Srv {
+                slot,
+                space,
+                flags,
+                visibility,
+            } => dx::RootParameter1::srv(*slot, *space)
+                .with_flags(flags.as_root_raw())
+                .with_visibility(*visibility),
+            BindingType::Uav {
+                slot,
+                space,
+                flags,
+                visibility,
+            } => dx::RootParameter1::uav(*slot, *space)
+                .with_flags(flags.as_root_raw())
+                .with_visibility(*visibility),
+            BindingType::PushConstant {
+                slot,
+                space,
+                count,
+                visibility,
+            } => dx::RootParameter1::constant_32bit(*slot, *space, *count)
+                .with_visibility(*visibility),
+            BindingType::Table { visibility, .. } => {
+                dx::RootParameter1::descriptor_table(ranges).with_visibility(*visibility)
+            }
+        }
+    }
+
     pub(crate) fn get_ranges(&self) -> SmallVec<[dx::DescriptorRange; 4]> {
         match self {
             BindingType::Table { entries, .. } => entries.iter().map(|e| e.as_raw()).collect(),
             _ => Default::default(),
         }
     }
+
+    pub(crate) fn get_ranges1(&self) -> SmallVec<[dx::DescriptorRange1; 4]> {
+        match self {
+            BindingType::Table { entries, .. } => entries.iter().map(|e| e.as_raw1()).collect(),
+            _ => Default::default(),
+        }
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum BindingTable {
-    Cbv { slot: u32, space: u32, count: u32 },
-    Srv { slot: u32, space: u32, count: u32 },
-    Uav { slot: u32, space: u32, count: u32 },
-    Sampler { slot: u32, space: u32, count: u32 },
+    Cbv {
+        slot: u32,
+        space: u32,
+        count: u32,
+        flags: DescriptorFlags,
+    },
+    Srv {
+        slot: u32,
+        space: u32,
+        count: u32,
+        flags: DescriptorFlags,
+    },
+    Uav {
+        slot: u32,
+        space: u32,
+        count: u32,
+        flags: DescriptorFlags,
+    },
+    Sampler {
+        slot: u32,
+        space: u32,
+        count: u32,
+        flags: DescriptorFlags,
+    },
 }
 
 impl BindingTable {
     pub(crate) fn as_raw(&self) -> dx::DescriptorRange {
         match self {
-            BindingTable::Cbv { slot, space, count } => {
-                dx::DescriptorRange::cbv(*count, *slot).with_register_space(*space)
-            }
-            BindingTable::Srv { slot, space, count } => {
-                dx::DescriptorRange::srv(*count, *slot).with_register_space(*space)
-            }
-            BindingTable::Uav { slot, space, count } => {
-                dx::DescriptorRange::uav(*count, *slot).with_register_space(*space)
-            }
-            BindingTable::Sampler { slot, space, count } => {
-                dx::DescriptorRange::sampler(*count, *slot).with_register_space(*space)
-            }
+            BindingTable::Cbv {
+                slot, space, count, ..
+            } => dx::DescriptorRange::cbv(*count, *slot).with_register_space(*space),
+            BindingTable::Srv {
+                slot, space, count, ..
+            } => dx::DescriptorRange::srv(*count, *slot).with_register_space(*space),
+            BindingTable::Uav {
+                slot, space, count, ..
+            } => dx::DescriptorRange::uav(*count, *slot).with_register_space(*space),
+            BindingTable::Sampler {
+                slot, space, count, ..
+            } => dx::DescriptorRange::sampler(*count, *slot).with_register_space(*space),
         }
     }
-}
 
-#[derive(Clone, Debug)]
-pub struct StaticSampler {}
-
-impl StaticSampler {
-    pub(crate) fn as_raw(&self) -> dx::StaticSamplerDesc {
-        dx::StaticSamplerDesc::default()
+    pub(crate) fn as_raw1(&self) -> dx::DescriptorRange1 {
+        match self {
+            BindingTable::Cbv {
+                slot,
+                space,
+                count,
+                flags,
+            } => dx::DescriptorRange1::cbv(*count, *slot)
+                .with_register_space(*space)
+                .with_flags(flags.as_range_raw(false)),
+            BindingTable::Srv {
+                slot,
+                space,
+                count,
+                flags,
+            } => dx::DescriptorRange1::srv(*count, *slot)
+                .with_register_space(*space)
+                .with_flags(flags.as_range_raw(false)),
+            BindingTable::Uav {
+                slot,
+                space,
+                count,
+                flags,
+            } => dx::DescriptorRange1::uav(*count, *slot)
+                .with_register_space(*space)
+                .with_flags(flags.as_range_raw(false)),
+            BindingTable::Sampler {
+                slot,
+                space,
+                count,
+                flags,
+            } => dx::DescriptorRange1::sampler(*count, *slot)
+                .with_register_space(*space)
+                .with_flags(flags.as_range_raw(true)),
+        }
     }
 }
 
@@ -286,15 +426,126 @@ pub struct GraphicsPipelineDesc {
     pub layout: PipelineLayout,
     pub vs: Shader<Vertex>,
     pub ps: Option<Shader<Pixel>>,
+    pub gs: Option<Shader<Geometry>>,
+    pub hs: Option<Shader<Hull>>,
+    pub ds: Option<Shader<Domain>>,
+    pub state: PipelineState,
 }
 
 impl GraphicsPipelineDesc {
-    pub(crate) fn as_raw(&self) -> dx::GraphicsPipelineDesc {
-        let desc =
-            dx::GraphicsPipelineDesc::new(&self.vs.raw).with_root_signature(&self.layout.raw);
+    pub fn new(layout: PipelineLayout, vs: Shader<Vertex>) -> Self {
+        Self {
+            layout,
+            vs,
+            ps: None,
+            gs: None,
+            hs: None,
+            ds: None,
+            state: PipelineState::default(),
+        }
+    }
+
+    pub fn shadow(layout: PipelineLayout, vs: Shader<Vertex>, depth: dx::Format) -> Self {
+        Self::new(layout, vs).with_state(PipelineState::shadow(depth))
+    }
+
+    pub fn with_ps(mut self, ps: Shader<Pixel>) -> Self {
+        self.ps = Some(ps);
+        self
+    }
+
+    pub fn with_gs(mut self, gs: Shader<Geometry>) -> Self {
+        self.gs = Some(gs);
+        self
+    }
+
+    pub fn with_tessellation(mut self, hs: Shader<Hull>, ds: Shader<Domain>) -> Self {
+        self.hs = Some(hs);
+        self.ds = Some(ds);
+        self
+    }
+
+    pub fn with_state(mut self, state: PipelineState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), PipelineStateError> {
+        self.state.validate(self.ps.is_some())?;
+        self.state
+            .validate_tessellation(self.hs.is_some(), self.ds.is_some())
+    }
+
+    pub fn stage_bindings(&self) -> Result<Vec<ShaderBindings>, ShaderError> {
+        let mut stages = vec![self.vs.bindings()?];
+        stages.extend(self.ps.as_ref().map(|s| s.bindings()).transpose()?);
+        stages.extend(self.gs.as_ref().map(|s| s.bindings()).transpose()?);
+        stages.extend(self.hs.as_ref().map(|s| s.bindings()).transpose()?);
+        stages.extend(self.ds.as_ref().map(|s| s.bindings()).transpose()?);
+        Ok(stages)
+    }
 
-        let desc = if let Some(ps) = &self.ps {
-            desc.with_ps(&ps.raw)
+    pub(crate) fn blobs(&self) -> GraphicsBlobs {
+        GraphicsBlobs {
+            vs: self.vs.raw(),
+            ps: self.ps.as_ref().map(|s| s.raw()),
+            gs: self.gs.as_ref().map(|s| s.raw()),
+            hs: self.hs.as_ref().map(|s| s.raw()),
+            ds: self.ds.as_ref().map(|s| s.raw()),
+        }
+    }
+
+    pub(crate) fn cache_key(&self, device_name: &str, blobs: &GraphicsBlobs) -> CacheKey {
+        fn bytecode(blob: &Option<dx::Blob>) -> &[u8] {
+            blob.as_ref().map_or(&[], |b| b.as_slice())
+        }
+
+        let hasher = CacheKeyHasher::new("graphics_pipeline")
+            .with_str(device_name)
+            .with_key(self.layout.key)
+            .with_bytes(blobs.vs.as_slice())
+            .with_bytes(bytecode(&blobs.ps))
+            .with_bytes(bytecode(&blobs.gs))
+            .with_bytes(bytecode(&blobs.hs))
+            .with_bytes(bytecode(&blobs.ds));
+
+        self.state.hash_into(hasher).finish()
+    }
+
+    pub(crate) fn as_raw<'a>(
+        &'a self,
+        blobs: &'a GraphicsBlobs,
+        input_layout: &'a [dx::InputElementDesc<'a>],
+    ) -> dx::GraphicsPipelineDesc<'a> {
+        let desc = dx::GraphicsPipelineDesc::new(&blobs.vs)
+            .with_root_signature(&self.layout.raw)
+            .with_input_layout(input_layout)
+            .with_rasterizer_state(self.state.rasterizer.as_raw())
+            .with_blend_desc(self.state.blend_raw())
+            .with_render_targets(self.state.render_target_formats())
+            .with_primitive_topology(self.state.topology.as_raw())
+            .with_sample_desc(dx::SampleDesc::new(self.state.sample_count, 0));
+
+        let desc = if let Some(format) = self.state.depth_format {
+            desc.with_depth_stencil(self.state.depth_stencil.as_raw(), format)
+        } else {
+            desc
+        };
+
+        let desc = if let Some(ps) = &blobs.ps {
+            desc.with_ps(ps)
+        } else {
+            desc
+        };
+
+        let desc = if let Some(gs) = &blobs.gs {
+            desc.with_gs(gs)
+        } else {
+            desc
+        };
+
+        let desc = if let (Some(hs), Some(ds)) = (&blobs.hs, &blobs.ds) {
+            desc.with_hs(hs).with_ds(ds)
         } else {
             desc
         };
@@ -302,3 +553,36 @@ impl GraphicsPipelineDesc {
         desc
     }
 }
+
+#[derive(Debug)]
+pub(crate) struct GraphicsBlobs {
+    vs: dx::Blob,
+    ps: Option<dx::Blob>,
+    gs: Option<dx::Blob>,
+    hs: Option<dx::Blob>,
+    ds: Option<dx::Blob>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ComputePipelineDesc {
+    pub layout: PipelineLayout,
+    pub cs: Shader<Compute>,
+}
+
+impl ComputePipelineDesc {
+    pub fn stage_bindings(&self) -> Result<Vec<ShaderBindings>, ShaderError> {
+        Ok(vec![self.cs.bindings()?])
+    }
+
+    pub(crate) fn cache_key(&self, device_name: &str, cs: &dx::Blob) -> CacheKey {
+        CacheKeyHasher::new("compute_pipeline")
+            .with_str(device_name)
+            .with_key(self.layout.key)
+            .with_bytes(cs.as_slice())
+            .finish()
+    }
+
+    pub(crate) fn as_raw<'a>(&'a self, cs: &'a dx::Blob) -> dx::ComputePipelineDesc<'a> {
+        dx::ComputePipelineDesc::new(cs).with_root_signature(&self.layout.raw)
+    }
+}
@@ -7,7 +7,7 @@ use crate::graphics::device::Device;
 
 use super::{
     heap::ViewHeap, CbvSrvUavView, CbvView, DsvView, GpuView, RtvView, SamplerView, SrvView,
-    UavView,
+    UavView, ViewType,
 };
 
 #[derive(Clone, Debug)]
@@ -39,6 +39,13 @@ impl ViewAllocator {
 }
 
 impl ViewAllocator {
+    pub(crate) fn shader_visible_heaps(&self) -> [dx::DescriptorHeap; 2] {
+        [
+            self.cbv_srv_uav.lock().raw().clone(),
+            self.sampler.lock().raw().clone(),
+        ]
+    }
+
     pub fn remove_rtv(&self, handle: GpuView<RtvView>) {
         self.rtv.lock().remove(handle)
     }
@@ -78,6 +85,15 @@ impl ViewAllocator {
         self.sampler.lock().remove(handle)
     }
 
+    pub fn remove_view<V: ViewType>(&self, handle: &GpuView<V>) {
+        match V::RAW_TYPE {
+            dx::DescriptorHeapType::Rtv => self.rtv.lock().remove(handle.cast()),
+            dx::DescriptorHeapType::Dsv => self.dsv.lock().remove(handle.cast()),
+            dx::DescriptorHeapType::CbvSrvUav => self.cbv_srv_uav.lock().remove(handle.cast()),
+            dx::DescriptorHeapType::Sampler => self.sampler.lock().remove(handle.cast()),
+        }
+    }
+
     pub fn push_rtv(
         &self,
         resource: &dx::Resource,
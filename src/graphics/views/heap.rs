@@ -62,6 +62,10 @@ impl<T: ViewType> ViewHeap<T> {
 }
 
 impl<T: ViewType> ViewHeap<T> {
+    pub(super) fn raw(&self) -> &dx::DescriptorHeap {
+        &self.raw
+    }
+
     pub fn remove(&mut self, handle: GpuView<T>) {
         if handle.index >= self.size {
             panic!(
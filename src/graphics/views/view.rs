@@ -20,6 +20,15 @@ impl<T: ViewType> GpuView<T> {
     pub fn cpu(&self) -> dx::CpuDescriptorHandle {
         self.cpu
     }
+
+    pub(crate) fn cast<U: ViewType>(&self) -> GpuView<U> {
+        GpuView {
+            index: self.index,
+            gpu: self.gpu,
+            cpu: self.cpu,
+            _marker: PhantomData,
+        }
+    }
 }
 
 pub trait ViewType: Sealed {
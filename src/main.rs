@@ -3,7 +3,7 @@ use std::num::NonZero;
 use mgpu_shadows::graphics::*;
 use oxidx::dx::{
     create_debug, create_factory4, Debug, Factory4, FactoryCreationFlags, Format, IDebug,
-    IDebugExt, IFactory4, Rect, Viewport,
+    IDebugExt, Rect, Viewport,
 };
 use winit::{
     event::MouseButton,
@@ -14,7 +14,6 @@ use winit::{
 fn main() {
     //run_sample::<Sample>();
     let factory: Factory4 = create_factory4(FactoryCreationFlags::Debug).unwrap();
-    let adapter = factory.enum_adapters(0).unwrap();
 
     let debug: Debug = create_debug().unwrap();
     debug.enable_debug_layer();
@@ -22,22 +21,28 @@ fn main() {
         println!("{:?} {}", level, message);
     }));
 
-    let gpu1 = Device::new(factory, adapter);
+    let adapters = AdapterList::enumerate(factory);
+    tracing::info!("Available adapters:\n{}", adapters);
+
+    let gpu1 = adapters
+        .create_device(&[AdapterPolicy::HighPerformance, AdapterPolicy::Software])
+        .unwrap();
     let heap1 = gpu1.create_heap(1920 * 1080, MemoryHeapType::Shared);
     let desc1 = gpu1.create_descriptor_allocator(8, 8, 8, 8);
 
-    let _res1: SharedResource<Image> = gpu1.create_shared_image(
-        &heap1,
-        0,
-        ImageDesc::new(1920, 1080, Format::R8Unorm).with_usage(TextureUsage::RenderTarget {
-            color: None,
-            srv: false,
-            uav: false,
-        }),
-        ViewAccess(desc1.clone()),
-        ResourceStates::RenderTarget,
-        ResourceStates::CopyDst,
-    );
+    let _res1: SharedResource<Image> = gpu1
+        .create_shared_image(
+            &heap1,
+            ImageDesc::new(1920, 1080, Format::R8Unorm).with_usage(TextureUsage::RenderTarget {
+                color: None,
+                srv: false,
+                uav: false,
+            }),
+            ViewAccess(desc1.clone()),
+            ResourceStates::RenderTarget,
+            ResourceStates::CopyDst,
+        )
+        .unwrap();
 }
 
 /*#[derive(Debug)]